@@ -14,10 +14,10 @@
 
 use serde::Deserialize;
 use std::error::Error;
-use std::fs::File;
+use std::fs;
 use std::path::Path;
 
-use crate::dsp::parametric_eq::FilterType;
+use crate::dsp::parametric_eq::{BandChannel, BandConfig, DynamicConfig, FilterType};
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ParsedEqBand {
@@ -29,29 +29,127 @@ pub struct ParsedEqBand {
     pub q: f32,
     #[serde(rename = "Gain")]
     pub gain: f32,
+    #[serde(rename = "Channel", default)]
+    pub channel_str: Option<String>,
 }
 
-#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct BandSetting {
     pub enabled: bool,
     pub filter_type: FilterType,
     pub frequency: f32,
     pub q: f32,
     pub gain: f32,
+    #[serde(default)]
+    pub channel: BandChannel,
+    #[serde(default)]
+    pub dynamic: bool,
+    #[serde(default)]
+    pub dynamics: DynamicConfig,
+}
+
+impl Default for BandSetting {
+    // Matches the defaults of `EqBandParams`, so unused band slots hold valid settings
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            filter_type: FilterType::Peak,
+            frequency: 1000.0,
+            q: 0.7,
+            gain: 0.0,
+            channel: BandChannel::Both,
+            dynamic: false,
+            dynamics: DynamicConfig::default(),
+        }
+    }
+}
+
+impl BandSetting {
+    pub fn to_band_config(&self) -> BandConfig {
+        BandConfig {
+            filter_type: self.filter_type,
+            center_freq: self.frequency,
+            q: self.q,
+            gain_db: self.gain,
+            enabled: self.enabled,
+            channel: self.channel,
+            dynamics: self.dynamic.then_some(self.dynamics),
+        }
+    }
+}
+
+/// A loaded EQ profile. Equalizer APO files carry the preamp AutoEQ recommends for
+/// the profile, CSV exports do not.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct AutoEqProfile {
+    pub preamp_db: Option<f32>,
+    pub bands: Vec<BandSetting>,
 }
 
 fn map_filter_type(autoeq_type: &str) -> Result<FilterType, String> {
     match autoeq_type {
-        "PK" => Ok(FilterType::Peak),
-        "LS" => Ok(FilterType::LowShelf),
-        "HS" => Ok(FilterType::HighShelf),
+        "PK" | "PEQ" => Ok(FilterType::Peak),
+        "LS" | "LSC" => Ok(FilterType::LowShelf),
+        "HS" | "HSC" => Ok(FilterType::HighShelf),
+        "LP" | "LPQ" => Ok(FilterType::LowPass),
+        "HP" | "HPQ" => Ok(FilterType::HighPass),
+        "BP" => Ok(FilterType::BandPass),
+        "NO" => Ok(FilterType::Notch),
+        "AP" => Ok(FilterType::AllPass),
         _ => Err(format!("Unsupported filter type: {}", autoeq_type)),
     }
 }
 
-pub fn parse_autoeq_csv(path: &Path) -> Result<Vec<BandSetting>, Box<dyn Error>> {
-    let file = File::open(path)?;
-    let mut rdr = csv::Reader::from_reader(file);
+/// Maps a channel specification (`L`, `R`, `L R`, `all`, or `M`/`S` for mid/side) to
+/// the EQ channel it targets.
+fn map_channel(channel: &str) -> Result<BandChannel, String> {
+    let mut first = false;
+    let mut second = false;
+    for token in channel.split_whitespace() {
+        match token.to_ascii_uppercase().as_str() {
+            "ALL" => (first, second) = (true, true),
+            "L" | "LEFT" | "1" | "M" | "MID" => first = true,
+            "R" | "RIGHT" | "2" | "S" | "SIDE" => second = true,
+            _ => return Err(format!("Unsupported channel: {}", channel)),
+        }
+    }
+
+    Ok(match (first, second) {
+        (true, false) => BandChannel::First,
+        (false, true) => BandChannel::Second,
+        _ => BandChannel::Both,
+    })
+}
+
+/// Loads an EQ profile, accepting both AutoEQ's CSV export and Equalizer APO
+/// configurations such as AutoEQ's `ParametricEQ.txt`.
+pub fn parse_autoeq_file(path: &Path) -> Result<AutoEqProfile, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    if text.lines().any(is_apo_line) {
+        parse_apo_config(&text)
+    } else {
+        Ok(AutoEqProfile {
+            preamp_db: None,
+            bands: parse_autoeq_csv(&text)?,
+        })
+    }
+}
+
+/// Whether a line is an Equalizer APO `Preamp:`, `Filter…:` or `Channel:` entry. The
+/// key must end at a colon that comes before any comma, otherwise AutoEQ's CSV header
+/// `Filter-Type,Fc,Q,Gain` would be mistaken for a filter line.
+fn is_apo_line(line: &str) -> bool {
+    let Some((key, _)) = line.trim().split_once(':') else {
+        return false;
+    };
+    !key.contains(',')
+        && (key.starts_with("Filter")
+            || key.eq_ignore_ascii_case("Preamp")
+            || key.eq_ignore_ascii_case("Channel"))
+}
+
+pub fn parse_autoeq_csv(text: &str) -> Result<Vec<BandSetting>, Box<dyn Error>> {
+    let mut rdr = csv::Reader::from_reader(text.as_bytes());
     let mut bands = Vec::new();
 
     for result in rdr.deserialize() {
@@ -62,9 +160,135 @@ pub fn parse_autoeq_csv(path: &Path) -> Result<Vec<BandSetting>, Box<dyn Error>>
             frequency: record.frequency,
             q: record.q,
             gain: record.gain,
+            channel: map_channel(record.channel_str.as_deref().unwrap_or("all"))?,
+            ..BandSetting::default()
         };
         bands.push(band_setting);
     }
 
     Ok(bands)
 }
+
+/// Parses the `Preamp` and `Filter` lines of an Equalizer APO configuration. A
+/// `Channel:` line scopes all filters that follow it, as it does in APO.
+pub fn parse_apo_config(text: &str) -> Result<AutoEqProfile, Box<dyn Error>> {
+    let mut channel = BandChannel::Both;
+    let mut profile = AutoEqProfile::default();
+
+    for line in text.lines() {
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        if key.eq_ignore_ascii_case("Channel") {
+            channel = map_channel(value)?;
+        } else if key.eq_ignore_ascii_case("Preamp") {
+            // APO allows several preamp lines, which add up
+            let gain = value.trim().trim_end_matches("dB").trim().parse::<f32>()?;
+            profile.preamp_db = Some(profile.preamp_db.unwrap_or(0.0) + gain);
+        } else if key.starts_with("Filter") {
+            profile.bands.push(parse_apo_filter(value, channel)?);
+        }
+    }
+
+    Ok(profile)
+}
+
+/// Parses the part of a filter line after the colon, e.g.
+/// `ON PK Fc 105 Hz Gain 5.6 dB Q 0.70`.
+fn parse_apo_filter(definition: &str, channel: BandChannel) -> Result<BandSetting, Box<dyn Error>> {
+    let tokens: Vec<&str> = definition.split_whitespace().collect();
+    let (Some(state), Some(filter_type)) = (tokens.first(), tokens.get(1)) else {
+        return Err(format!("Incomplete filter definition: {}", definition.trim()).into());
+    };
+
+    let value_after = |name: &str| -> Result<Option<f32>, Box<dyn Error>> {
+        match tokens.iter().position(|t| *t == name) {
+            Some(idx) => match tokens.get(idx + 1) {
+                Some(value) => Ok(Some(value.parse::<f32>()?)),
+                None => Err(format!("Missing value for {}", name).into()),
+            },
+            None => Ok(None),
+        }
+    };
+
+    Ok(BandSetting {
+        enabled: *state == "ON",
+        filter_type: map_filter_type(filter_type)?,
+        frequency: value_after("Fc")?
+            .ok_or_else(|| format!("Filter has no frequency: {}", definition.trim()))?,
+        q: value_after("Q")?.unwrap_or(std::f32::consts::FRAC_1_SQRT_2),
+        gain: value_after("Gain")?.unwrap_or(0.0),
+        channel,
+        ..BandSetting::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_apo_config_with_channels() {
+        let text = "Preamp: -6.2 dB\n\
+                    Filter 1: ON LSC Fc 105 Hz Gain 5.6 dB Q 0.70\n\
+                    Channel: L\n\
+                    Filter 2: ON PK Fc 190 Hz Gain -3.3 dB Q 0.51\n\
+                    Channel: R\n\
+                    Filter 3: OFF PK Fc 2000 Hz Gain 2.0 dB Q 1.41\n";
+        let profile = parse_apo_config(text).unwrap();
+        let bands = profile.bands;
+
+        assert_eq!(profile.preamp_db, Some(-6.2));
+        assert_eq!(bands.len(), 3);
+        assert_eq!(bands[0].filter_type, FilterType::LowShelf);
+        assert_eq!(bands[0].channel, BandChannel::Both);
+        assert_eq!(bands[1].frequency, 190.0);
+        assert_eq!(bands[1].gain, -3.3);
+        assert_eq!(bands[1].channel, BandChannel::First);
+        assert_eq!(bands[2].channel, BandChannel::Second);
+        assert!(!bands[2].enabled);
+    }
+
+    #[test]
+    fn test_parse_autoeq_csv_with_optional_channel() {
+        let text = "Filter-Type,Fc,Q,Gain,Channel\nPK,1000,1.0,-2.0,R\nHS,10000,0.7,3.0,\n";
+        let bands = parse_autoeq_csv(text).unwrap();
+
+        assert_eq!(bands[0].channel, BandChannel::Second);
+        assert_eq!(bands[1].channel, BandChannel::Both);
+
+        let without_channel =
+            parse_autoeq_csv("Filter-Type,Fc,Q,Gain\nPK,1000,1.0,-2.0\n").unwrap();
+        assert_eq!(without_channel[0].channel, BandChannel::Both);
+    }
+
+    #[test]
+    fn test_parse_autoeq_file_detects_csv_and_apo() {
+        let dir =
+            std::env::temp_dir().join(format!("open-headstage-autoeq-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let csv_path = dir.join("ParametricEQ.csv");
+        fs::write(
+            &csv_path,
+            "Filter-Type,Fc,Q,Gain\nPK,1000,1.0,-2.0\nHS,10000,0.7,3.0\n",
+        )
+        .unwrap();
+        let csv_profile = parse_autoeq_file(&csv_path).unwrap();
+        assert_eq!(csv_profile.preamp_db, None);
+        assert_eq!(csv_profile.bands.len(), 2);
+        assert_eq!(csv_profile.bands[1].filter_type, FilterType::HighShelf);
+
+        let apo_path = dir.join("ParametricEQ.txt");
+        fs::write(
+            &apo_path,
+            "Preamp: -3.0 dB\nFilter 1: ON PK Fc 1000 Hz Gain -2.0 dB Q 1.0\n",
+        )
+        .unwrap();
+        let apo_profile = parse_autoeq_file(&apo_path).unwrap();
+        assert_eq!(apo_profile.preamp_db, Some(-3.0));
+        assert_eq!(apo_profile.bands.len(), 1);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
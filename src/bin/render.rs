@@ -0,0 +1,59 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use open_headstage::render::{self, RenderError, RenderOptions, USAGE};
+use std::process::ExitCode;
+use std::time::Instant;
+
+fn main() -> ExitCode {
+    let options = match RenderOptions::from_args(std::env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(RenderError::Usage(message)) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let started = Instant::now();
+    match render::render(&options) {
+        Ok(summary) => {
+            let duration = summary.frames as f64 / summary.sample_rate as f64;
+            let elapsed = started.elapsed().as_secs_f64();
+            println!(
+                "Rendered {:.1} s of audio to {} in {:.1} s ({:.0}x real-time), peak {:.1} dBFS",
+                duration,
+                options.output.display(),
+                elapsed,
+                duration / elapsed.max(f64::EPSILON),
+                summary.peak_db
+            );
+            if summary.peak_db > 0.0 && options.bit_depth != render::BitDepth::Float32 {
+                eprintln!("Warning: the output clipped, lower --gain or --preamp");
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
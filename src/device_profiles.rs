@@ -0,0 +1,187 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/device_profiles.rs
+
+//! Profiles hold the settings that belong to a pair of headphones: the EQ, the SOFA file,
+//! the speaker layout and the output gain. Each is tied to the output devices the
+//! headphones are plugged into, and the standalone app applies the matching profile when
+//! it opens or switches to one. Devices without a profile get the default profile, if
+//! there is one.
+
+use crate::presets::Preset;
+use serde::{Deserialize, Serialize};
+
+/// The parameters a profile keeps, besides the EQ bands
+const PROFILE_PARAMS: [&str; 11] = [
+    "out_gain",
+    "az_l",
+    "el_l",
+    "az_r",
+    "el_r",
+    "eq_enable",
+    "eq_mode",
+    "eq_design",
+    "eq_phase",
+    "preamp_mode",
+    "preamp",
+];
+
+/// The prefixes of the EQ band parameters, which end in `_<band number>`
+const BAND_PARAM_PREFIXES: [&str; 11] = [
+    "en", "type", "fc", "q", "gain", "ch", "dyn", "thr", "ratio", "atk", "rel",
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceProfile {
+    pub name: String,
+    /// The output devices the profile is applied for
+    pub devices: Vec<String>,
+    /// Empty to keep the loaded SOFA file
+    pub sofa_file_path: String,
+    /// The EQ, speaker and gain parameters
+    pub settings: Preset,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceProfiles {
+    pub profiles: Vec<DeviceProfile>,
+    /// The profile for devices that don't have one
+    #[serde(default)]
+    pub default_profile: Option<String>,
+}
+
+/// Whether a profile keeps the parameter with this ID.
+pub fn is_profile_param(id: &str) -> bool {
+    PROFILE_PARAMS.contains(&id)
+        || id.rsplit_once('_').is_some_and(|(prefix, band)| {
+            BAND_PARAM_PREFIXES.contains(&prefix) && band.parse::<usize>().is_ok()
+        })
+}
+
+impl DeviceProfile {
+    /// A profile of the current settings. `params` are plain values by parameter ID;
+    /// the ones a profile doesn't keep are left out.
+    pub fn capture(
+        name: &str,
+        sofa_file_path: &str,
+        params: impl IntoIterator<Item = (String, f32)>,
+        eq_band_count: usize,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            devices: Vec::new(),
+            sofa_file_path: sofa_file_path.to_string(),
+            settings: Preset {
+                params: params
+                    .into_iter()
+                    .filter(|(id, _)| is_profile_param(id))
+                    .collect(),
+                eq_band_count: Some(eq_band_count),
+            },
+        }
+    }
+}
+
+impl DeviceProfiles {
+    pub fn get(&self, name: &str) -> Option<&DeviceProfile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// The profile to apply for an output device, falling back to the default profile.
+    pub fn for_device(&self, device: &str) -> Option<&DeviceProfile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.devices.iter().any(|d| d == device))
+            .or_else(|| self.get(self.default_profile.as_deref()?))
+    }
+
+    /// Saves a profile for a device, replacing the profile with the same name. The device
+    /// moves over from the profile it had before, and the replaced profile's other
+    /// devices stay with it.
+    pub fn save(&mut self, mut profile: DeviceProfile, device: &str) {
+        for other in &mut self.profiles {
+            other.devices.retain(|d| d != device);
+        }
+        match self.profiles.iter_mut().find(|p| p.name == profile.name) {
+            Some(existing) => {
+                profile.devices = std::mem::take(&mut existing.devices);
+                profile.devices.push(device.to_string());
+                *existing = profile;
+            }
+            None => {
+                profile.devices = vec![device.to_string()];
+                self.profiles.push(profile);
+            }
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.profiles.retain(|profile| profile.name != name);
+        if self.default_profile.as_deref() == Some(name) {
+            self.default_profile = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile(name: &str) -> DeviceProfile {
+        DeviceProfile::capture(
+            name,
+            "/hrtf/kemar.sofa",
+            [
+                ("out_gain".to_string(), -3.0),
+                ("fc_12".to_string(), 1000.0),
+                ("bypass".to_string(), 1.0),
+                ("head_tracking".to_string(), 1.0),
+            ],
+            12,
+        )
+    }
+
+    #[test]
+    fn test_capture_keeps_profile_params() {
+        let profile = profile("HD 600");
+        let ids: Vec<&str> = profile.settings.params.keys().map(String::as_str).collect();
+        assert_eq!(ids, ["fc_12", "out_gain"]);
+        assert_eq!(profile.settings.eq_band_count, Some(12));
+        assert!(!is_profile_param("gain_x"));
+        assert!(!is_profile_param("loudness"));
+    }
+
+    #[test]
+    fn test_profiles_follow_devices() {
+        let mut profiles = DeviceProfiles::default();
+        profiles.save(profile("HD 600"), "USB DAC");
+        profiles.save(profile("HD 600"), "Dock");
+        profiles.save(profile("IEMs"), "Built-in Audio");
+        assert_eq!(profiles.get("HD 600").unwrap().devices, ["USB DAC", "Dock"]);
+        assert_eq!(profiles.for_device("Dock").unwrap().name, "HD 600");
+        assert!(profiles.for_device("HDMI").is_none());
+
+        // A device saved with another profile leaves its old one
+        profiles.save(profile("IEMs"), "Dock");
+        assert_eq!(profiles.get("HD 600").unwrap().devices, ["USB DAC"]);
+        assert_eq!(profiles.for_device("Dock").unwrap().name, "IEMs");
+
+        profiles.default_profile = Some("HD 600".to_string());
+        assert_eq!(profiles.for_device("HDMI").unwrap().name, "HD 600");
+        profiles.remove("HD 600");
+        assert!(profiles.default_profile.is_none());
+        assert!(profiles.for_device("USB DAC").is_none());
+    }
+}
@@ -0,0 +1,334 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/dsp/analyzer.rs
+
+use ringbuf::{HeapConsumer, HeapProducer, HeapRb};
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::sync::Arc;
+use strum_macros::EnumIter;
+
+// Room for a few editor frames of audio at the largest FFT size
+const TAP_CAPACITY: usize = 4 * 16384;
+
+// The level spectra start from before any audio arrives
+pub const SPECTRUM_FLOOR_DB: f32 = -120.0;
+
+/// The points in the signal chain the analyser listens to. The input and the EQ output
+/// are analysed as the mid signal of the two channels.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum AnalyzerTap {
+    Input,
+    PostEq,
+    OutputLeft,
+    OutputRight,
+}
+
+impl AnalyzerTap {
+    pub const COUNT: usize = 4;
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AnalyzerTap::Input => "Input",
+            AnalyzerTap::PostEq => "Post-EQ",
+            AnalyzerTap::OutputLeft => "Left Ear",
+            AnalyzerTap::OutputRight => "Right Ear",
+        }
+    }
+}
+
+/// The FFT size of the analyser. Larger sizes resolve the bass better but react slower.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Default)]
+pub enum AnalyzerResolution {
+    Low,
+    #[default]
+    Medium,
+    High,
+    VeryHigh,
+}
+
+impl AnalyzerResolution {
+    pub fn fft_size(self) -> usize {
+        match self {
+            AnalyzerResolution::Low => 2048,
+            AnalyzerResolution::Medium => 4096,
+            AnalyzerResolution::High => 8192,
+            AnalyzerResolution::VeryHigh => 16384,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AnalyzerResolution::Low => "2048",
+            AnalyzerResolution::Medium => "4096",
+            AnalyzerResolution::High => "8192",
+            AnalyzerResolution::VeryHigh => "16384",
+        }
+    }
+}
+
+/// How the analyser displays its spectra.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnalyzerSettings {
+    pub resolution: AnalyzerResolution,
+    // The time constant of the exponential average in milliseconds, 0 for none
+    pub averaging_ms: f32,
+    pub peak_hold: bool,
+}
+
+impl Default for AnalyzerSettings {
+    fn default() -> Self {
+        Self {
+            resolution: AnalyzerResolution::default(),
+            averaging_ms: 300.0,
+            peak_hold: false,
+        }
+    }
+}
+
+/// The audio thread's end of the analyser. Pushing never blocks or allocates; samples
+/// that don't fit because the editor isn't reading are dropped.
+pub struct AnalyzerTaps {
+    producers: [HeapProducer<f32>; AnalyzerTap::COUNT],
+}
+
+impl AnalyzerTaps {
+    pub fn push(&mut self, tap: AnalyzerTap, samples: &[f32]) {
+        self.producers[tap as usize].push_slice(samples);
+    }
+
+    /// Pushes the mid signal of a stereo pair.
+    pub fn push_mid(&mut self, tap: AnalyzerTap, left: &[f32], right: &[f32]) {
+        let mut mid = left.iter().zip(right).map(|(l, r)| 0.5 * (l + r));
+        self.producers[tap as usize].push_iter(&mut mid);
+    }
+}
+
+/// The editor's end of the analyser. It keeps the newest `fft_size` samples of every tap
+/// and turns them into Hann-windowed level spectra, where a full-scale sine reads 0 dB.
+pub struct SpectrumAnalyzer {
+    consumers: [HeapConsumer<f32>; AnalyzerTap::COUNT],
+    history: [Vec<f32>; AnalyzerTap::COUNT],
+    spectra: [Vec<f32>; AnalyzerTap::COUNT],
+    peaks: [Vec<f32>; AnalyzerTap::COUNT],
+    window: Vec<f32>,
+    fft: Arc<dyn Fft<f32>>,
+    fft_buffer: Vec<Complex<f32>>,
+    fft_scratch: Vec<Complex<f32>>,
+    settings: AnalyzerSettings,
+}
+
+/// Creates the two ends of an analyser.
+pub fn spectrum_analyzer() -> (AnalyzerTaps, SpectrumAnalyzer) {
+    let (producers, consumers): (Vec<_>, Vec<_>) = (0..AnalyzerTap::COUNT)
+        .map(|_| HeapRb::<f32>::new(TAP_CAPACITY).split())
+        .unzip();
+    let taps = AnalyzerTaps {
+        producers: producers.try_into().unwrap_or_else(|_| unreachable!()),
+    };
+    let settings = AnalyzerSettings::default();
+    let fft_size = settings.resolution.fft_size();
+    let fft = FftPlanner::new().plan_fft_forward(fft_size);
+    let analyzer = SpectrumAnalyzer {
+        consumers: consumers.try_into().unwrap_or_else(|_| unreachable!()),
+        history: std::array::from_fn(|_| vec![0.0; fft_size]),
+        spectra: std::array::from_fn(|_| vec![SPECTRUM_FLOOR_DB; fft_size / 2 + 1]),
+        peaks: std::array::from_fn(|_| vec![SPECTRUM_FLOOR_DB; fft_size / 2 + 1]),
+        window: hann_window(fft_size),
+        fft_scratch: vec![Complex::default(); fft.get_inplace_scratch_len()],
+        fft_buffer: vec![Complex::default(); fft_size],
+        fft,
+        settings,
+    };
+    (taps, analyzer)
+}
+
+fn hann_window(size: usize) -> Vec<f32> {
+    (0..size)
+        .map(|i| 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / size as f32).cos())
+        .collect()
+}
+
+impl SpectrumAnalyzer {
+    /// Reads the audio that arrived since the last update and recomputes the spectra.
+    /// `elapsed` is the time since the last update in seconds, which the averaging uses.
+    pub fn update(&mut self, settings: AnalyzerSettings, elapsed: f32) {
+        if settings.resolution != self.settings.resolution {
+            self.resize(settings.resolution.fft_size());
+        }
+        if !settings.peak_hold {
+            for peaks in self.peaks.iter_mut() {
+                peaks.fill(SPECTRUM_FLOOR_DB);
+            }
+        }
+        self.settings = settings;
+
+        // A one-pole average of the power, so the time constant doesn't depend on the
+        // editor's frame rate
+        let keep = if settings.averaging_ms > 0.0 {
+            (-elapsed * 1000.0 / settings.averaging_ms).exp()
+        } else {
+            0.0
+        };
+        // Scale so a full-scale sine in a bin reads 0 dB
+        let window_sum: f32 = self.window.iter().sum();
+        let scale = 2.0 / window_sum;
+
+        for tap in 0..AnalyzerTap::COUNT {
+            let consumer = &mut self.consumers[tap];
+            let history = &mut self.history[tap];
+            let size = history.len();
+            // Only the newest `size` samples matter
+            let available = consumer.len();
+            if available == 0 {
+                continue;
+            }
+            let skipped = consumer.skip(available.saturating_sub(size));
+            let new = available - skipped;
+            history.copy_within(new.., 0);
+            consumer.pop_slice(&mut history[size - new..]);
+
+            for ((bin, &sample), &weight) in self
+                .fft_buffer
+                .iter_mut()
+                .zip(history.iter())
+                .zip(&self.window)
+            {
+                *bin = Complex::new(sample * weight, 0.0);
+            }
+            self.fft
+                .process_with_scratch(&mut self.fft_buffer, &mut self.fft_scratch);
+
+            for ((level_db, peak_db), bin) in self.spectra[tap]
+                .iter_mut()
+                .zip(self.peaks[tap].iter_mut())
+                .zip(&self.fft_buffer)
+            {
+                let power = (bin.norm() * scale).powi(2);
+                let previous_power = 10f32.powf(*level_db / 10.0);
+                let averaged = keep * previous_power + (1.0 - keep) * power;
+                *level_db = (10.0 * averaged.max(1e-12).log10()).max(SPECTRUM_FLOOR_DB);
+                *peak_db = peak_db.max(*level_db);
+            }
+        }
+    }
+
+    fn resize(&mut self, fft_size: usize) {
+        self.fft = FftPlanner::new().plan_fft_forward(fft_size);
+        self.fft_buffer = vec![Complex::default(); fft_size];
+        self.fft_scratch = vec![Complex::default(); self.fft.get_inplace_scratch_len()];
+        self.window = hann_window(fft_size);
+        for history in self.history.iter_mut() {
+            // Keep the newest samples so the display doesn't drop out
+            let old_size = history.len();
+            if fft_size < old_size {
+                history.drain(..old_size - fft_size);
+            } else {
+                history.splice(0..0, std::iter::repeat_n(0.0, fft_size - old_size));
+            }
+        }
+        for levels in self.spectra.iter_mut().chain(self.peaks.iter_mut()) {
+            *levels = vec![SPECTRUM_FLOOR_DB; fft_size / 2 + 1];
+        }
+    }
+
+    /// Clears the held peaks.
+    pub fn reset_peaks(&mut self) {
+        for peaks in self.peaks.iter_mut() {
+            peaks.fill(SPECTRUM_FLOOR_DB);
+        }
+    }
+
+    /// The frequency of every bin of the spectra.
+    pub fn frequencies(&self, sample_rate: f32) -> Vec<f32> {
+        let fft_size = self.window.len();
+        (0..=fft_size / 2)
+            .map(|bin| bin as f32 * sample_rate / fft_size as f32)
+            .collect()
+    }
+
+    /// The level spectrum of a tap in dB.
+    pub fn spectrum(&self, tap: AnalyzerTap) -> &[f32] {
+        &self.spectra[tap as usize]
+    }
+
+    /// The highest levels of a tap since peak hold was switched on or reset.
+    pub fn peaks(&self, tap: AnalyzerTap) -> Option<&[f32]> {
+        self.settings
+            .peak_hold
+            .then_some(&self.peaks[tap as usize][..])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    #[test]
+    fn test_full_scale_sine_reads_zero_db() {
+        let (mut taps, mut analyzer) = spectrum_analyzer();
+        let settings = AnalyzerSettings {
+            averaging_ms: 0.0,
+            ..AnalyzerSettings::default()
+        };
+        let fft_size = settings.resolution.fft_size();
+        // Exactly on bin 100 so the window's main lobe peaks there
+        let freq = 100.0 * SAMPLE_RATE / fft_size as f32;
+        let sine: Vec<f32> = (0..fft_size)
+            .map(|i| (2.0 * std::f32::consts::PI * freq * i as f32 / SAMPLE_RATE).sin())
+            .collect();
+        taps.push(AnalyzerTap::OutputLeft, &sine);
+        analyzer.update(settings, 0.02);
+
+        let spectrum = analyzer.spectrum(AnalyzerTap::OutputLeft);
+        assert!(
+            (spectrum[100]).abs() < 0.1,
+            "Sine read {} dB",
+            spectrum[100]
+        );
+        assert!(spectrum[300] < -60.0, "Leakage of {} dB", spectrum[300]);
+        // Taps that received nothing stay silent
+        assert_eq!(
+            analyzer.spectrum(AnalyzerTap::Input)[100],
+            SPECTRUM_FLOOR_DB
+        );
+    }
+
+    #[test]
+    fn test_peak_hold_keeps_maximum() {
+        let (mut taps, mut analyzer) = spectrum_analyzer();
+        let settings = AnalyzerSettings {
+            averaging_ms: 0.0,
+            peak_hold: true,
+            ..AnalyzerSettings::default()
+        };
+        let fft_size = settings.resolution.fft_size();
+        let freq = 64.0 * SAMPLE_RATE / fft_size as f32;
+        let sine: Vec<f32> = (0..fft_size)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * freq * i as f32 / SAMPLE_RATE).sin())
+            .collect();
+        taps.push_mid(AnalyzerTap::Input, &sine, &sine);
+        analyzer.update(settings, 0.02);
+        taps.push(AnalyzerTap::Input, &vec![0.0; fft_size]);
+        analyzer.update(settings, 0.02);
+
+        let level = analyzer.spectrum(AnalyzerTap::Input)[64];
+        let peak = analyzer.peaks(AnalyzerTap::Input).unwrap()[64];
+        assert_eq!(level, SPECTRUM_FLOOR_DB);
+        assert!((peak + 6.02).abs() < 0.1, "Peak held at {} dB", peak);
+    }
+}
@@ -0,0 +1,254 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/dsp/bypass.rs
+
+use biquad::{Biquad, DirectForm2Transposed};
+
+use crate::dsp::meter::k_weighting_coefficients;
+
+const CROSSFADE_MS: f32 = 20.0;
+// The loudness of both paths is averaged over a few seconds, like the short-term
+// loudness of EBU R128
+const MEASUREMENT_MS: f32 = 3000.0;
+// How fast the bypassed path follows a new matching gain
+const GAIN_GLIDE_MS: f32 = 200.0;
+// Below about -70 LUFS the dry signal is too quiet to compare against
+const MEASUREMENT_GATE: f32 = 1e-7;
+const MAX_MATCH_DB: f32 = 24.0;
+
+/// Switches between the processed signal and the unprocessed input with an equal-power
+/// crossfade. The input is delayed by the latency of the processing so both paths line
+/// up. With loudness matching on, the bypassed path is turned to the K-weighted
+/// loudness of the processed path, so comparisons aren't biased by level.
+pub struct MatchedBypass {
+    // The delayed input of the current block
+    dry: [Vec<f32>; 2],
+    delay_lines: [Vec<f32>; 2],
+    delay_pos: usize,
+    delay: usize,
+
+    // 0 plays the processed path, 1 the bypassed path
+    mix: f32,
+    mix_step: f32,
+
+    // K-weighting for the dry and the processed path
+    k_weighting: [[[DirectForm2Transposed<f32>; 2]; 2]; 2],
+    dry_energy: f32,
+    wet_energy: f32,
+    measurement_coeff: f32,
+    match_gain: f32,
+    gain_coeff: f32,
+}
+
+impl MatchedBypass {
+    /// `max_delay` is the largest latency `set_delay` will be given.
+    pub fn new(sample_rate: f32, max_block_size: usize, max_delay: usize) -> Self {
+        let [shelf, high_pass] = k_weighting_coefficients(sample_rate);
+        let k_weighting = [
+            DirectForm2Transposed::<f32>::new(shelf),
+            DirectForm2Transposed::<f32>::new(high_pass),
+        ];
+        let one_pole = |ms: f32| 1.0 - (-1.0 / (ms / 1000.0 * sample_rate)).exp();
+        Self {
+            dry: [vec![0.0; max_block_size], vec![0.0; max_block_size]],
+            delay_lines: [vec![0.0; max_delay + 1], vec![0.0; max_delay + 1]],
+            delay_pos: 0,
+            delay: 0,
+            mix: 0.0,
+            mix_step: 1.0 / (CROSSFADE_MS / 1000.0 * sample_rate).max(1.0),
+            k_weighting: [[k_weighting; 2]; 2],
+            dry_energy: 0.0,
+            wet_energy: 0.0,
+            measurement_coeff: one_pole(MEASUREMENT_MS),
+            match_gain: 1.0,
+            gain_coeff: one_pole(GAIN_GLIDE_MS),
+        }
+    }
+
+    /// Sets the latency of the processed path, in samples.
+    pub fn set_delay(&mut self, delay: usize) {
+        let delay = delay.min(self.delay_lines[0].len() - 1);
+        if delay != self.delay {
+            self.delay = delay;
+            for line in self.delay_lines.iter_mut() {
+                line.fill(0.0);
+            }
+        }
+    }
+
+    /// Starts at the given state without a crossfade.
+    pub fn reset(&mut self, bypassed: bool) {
+        self.mix = if bypassed { 1.0 } else { 0.0 };
+        for line in self.delay_lines.iter_mut() {
+            line.fill(0.0);
+        }
+        for filter in self.k_weighting.iter_mut().flatten().flatten() {
+            filter.reset_state();
+        }
+        self.dry_energy = 0.0;
+        self.wet_energy = 0.0;
+        self.match_gain = 1.0;
+    }
+
+    /// Keeps the input of a block for the bypassed path. Call before processing it.
+    pub fn capture_input(&mut self, left: &[f32], right: &[f32]) {
+        let len = self.delay_lines[0].len();
+        for (channel, input) in [left, right].into_iter().enumerate() {
+            let dry = &mut self.dry[channel];
+            if dry.len() < input.len() {
+                dry.resize(input.len(), 0.0);
+            }
+            let line = &mut self.delay_lines[channel];
+            let mut pos = self.delay_pos;
+            for (out, &sample) in dry.iter_mut().zip(input) {
+                line[pos] = sample;
+                *out = line[(pos + len - self.delay) % len];
+                pos = (pos + 1) % len;
+            }
+        }
+        self.delay_pos = (self.delay_pos + left.len()) % len;
+    }
+
+    /// Mixes the processed block in `left` and `right` with the captured input.
+    pub fn process_block(
+        &mut self,
+        left: &mut [f32],
+        right: &mut [f32],
+        bypassed: bool,
+        match_loudness: bool,
+    ) {
+        let target_mix = if bypassed { 1.0 } else { 0.0 };
+        let [dry_left, dry_right] = &self.dry;
+        for (i, (l, r)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+            let (dry_l, dry_r) = (dry_left[i], dry_right[i]);
+
+            let mut dry_power = 0.0;
+            let mut wet_power = 0.0;
+            for (channel, (dry, wet)) in [(dry_l, *l), (dry_r, *r)].into_iter().enumerate() {
+                let [[dry_shelf, dry_high_pass], [wet_shelf, wet_high_pass]] =
+                    &mut self.k_weighting[channel];
+                let dry = dry_high_pass.run(dry_shelf.run(dry));
+                let wet = wet_high_pass.run(wet_shelf.run(wet));
+                dry_power += dry * dry;
+                wet_power += wet * wet;
+            }
+            self.dry_energy += (dry_power - self.dry_energy) * self.measurement_coeff;
+            self.wet_energy += (wet_power - self.wet_energy) * self.measurement_coeff;
+
+            let target_gain = if !match_loudness {
+                1.0
+            } else if self.dry_energy > MEASUREMENT_GATE {
+                let max_gain = 10f32.powf(MAX_MATCH_DB / 20.0);
+                (self.wet_energy / self.dry_energy)
+                    .sqrt()
+                    .clamp(1.0 / max_gain, max_gain)
+            } else {
+                // Hold the last match through silence
+                self.match_gain
+            };
+            self.match_gain += (target_gain - self.match_gain) * self.gain_coeff;
+
+            if self.mix < target_mix {
+                self.mix = (self.mix + self.mix_step).min(target_mix);
+            } else if self.mix > target_mix {
+                self.mix = (self.mix - self.mix_step).max(target_mix);
+            }
+            // The paths are only loosely correlated after the HRIR convolution, so an
+            // equal-power fade keeps the level steady
+            let angle = self.mix * std::f32::consts::FRAC_PI_2;
+            let wet_gain = angle.cos();
+            let dry_gain = angle.sin() * self.match_gain;
+            *l = *l * wet_gain + dry_l * dry_gain;
+            *r = *r * wet_gain + dry_r * dry_gain;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+    const BLOCK: usize = 480;
+
+    fn sine(freq: f32, amplitude: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / SAMPLE_RATE).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_toggling_crossfades_without_steps() {
+        let mut bypass = MatchedBypass::new(SAMPLE_RATE, BLOCK, 0);
+        bypass.reset(false);
+        let input = vec![1.0; BLOCK];
+        let mut output = Vec::new();
+        for block in 0..6 {
+            // The processed path is 12 dB quieter
+            let mut left = vec![0.25; BLOCK];
+            let mut right = vec![0.25; BLOCK];
+            bypass.capture_input(&input, &input);
+            bypass.process_block(&mut left, &mut right, block >= 2, false);
+            output.extend(left);
+        }
+
+        assert_eq!(output[2 * BLOCK - 1], 0.25);
+        assert!((output.last().unwrap() - 1.0).abs() < 1e-6);
+        let max_step = output
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).abs())
+            .fold(0.0, f32::max);
+        assert!(max_step < 0.002, "Jumped by {}", max_step);
+    }
+
+    #[test]
+    fn test_bypassed_path_matches_processed_loudness() {
+        let mut bypass = MatchedBypass::new(SAMPLE_RATE, BLOCK, 0);
+        bypass.reset(true);
+        let input = sine(1000.0, 0.5, 10 * SAMPLE_RATE as usize);
+        let mut output = Vec::new();
+        for block in input.chunks(BLOCK) {
+            // The processed path is 6 dB quieter
+            let mut left: Vec<f32> = block.iter().map(|sample| sample * 0.5).collect();
+            let mut right = left.clone();
+            bypass.capture_input(block, block);
+            bypass.process_block(&mut left, &mut right, true, true);
+            output.extend(left);
+        }
+
+        let tail = &output[output.len() - SAMPLE_RATE as usize..];
+        let peak = tail
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        assert!((peak - 0.25).abs() < 0.005, "Bypassed peak of {}", peak);
+    }
+
+    #[test]
+    fn test_bypassed_path_is_delayed_by_latency() {
+        let mut bypass = MatchedBypass::new(SAMPLE_RATE, BLOCK, 100);
+        bypass.set_delay(10);
+        bypass.reset(true);
+        let input: Vec<f32> = (0..BLOCK).map(|i| i as f32).collect();
+        let mut left = vec![0.0; BLOCK];
+        let mut right = vec![0.0; BLOCK];
+        bypass.capture_input(&input, &input);
+        bypass.process_block(&mut left, &mut right, true, false);
+
+        assert_eq!(&left[..10], &[0.0; 10]);
+        assert_eq!(left[10], 0.0);
+        assert_eq!(left[11], 1.0);
+        assert_eq!(left[BLOCK - 1], (BLOCK - 11) as f32);
+    }
+}
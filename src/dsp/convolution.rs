@@ -19,7 +19,9 @@ use rustfft::{Fft, FftPlanner};
 use std::sync::Arc;
 
 // Configuration for partitioned convolution
-const BLOCK_SIZE: usize = 512; // Internal processing block size
+/// Internal processing block size. Blocks of exactly this length pass through without
+/// delay.
+pub const BLOCK_SIZE: usize = 512;
 const FFT_SIZE: usize = BLOCK_SIZE * 2; // FFT size, typically 2 * block_size for 50% overlap-add
 
 /// Enum to identify one of the four convolution paths in a binaural setup.
@@ -64,6 +66,23 @@ impl ConvolutionPathData {
     }
 }
 
+/// The IRs of all four convolution paths, split into partitions and transformed.
+/// Preparing them takes an FFT per partition, so it is best done off the audio thread.
+#[derive(Clone)]
+pub struct HrirSet {
+    partitions: [Vec<Vec<Complex<f32>>>; 4],
+}
+
+impl HrirSet {
+    /// Takes the IRs in `ConvolutionPath` order.
+    pub fn new(irs: [&[f32]; 4]) -> Self {
+        let forward_fft = FftPlanner::<f32>::new().plan_fft_forward(FFT_SIZE);
+        Self {
+            partitions: irs.map(|ir| partition_ir(ir, &forward_fft)),
+        }
+    }
+}
+
 /// Manages four convolution paths for binaural processing using partitioned convolution.
 #[derive(Clone)]
 pub struct ConvolutionEngine {
@@ -81,6 +100,9 @@ pub struct ConvolutionEngine {
     // Temporary buffers for FFT processing
     input_fft_buffer: Vec<Complex<f32>>,
     conv_accumulator: Vec<Complex<f32>>,
+
+    // IRs to crossfade to in the next internal block
+    pending_irs: Option<HrirSet>,
 }
 
 impl ConvolutionEngine {
@@ -104,33 +126,14 @@ impl ConvolutionEngine {
             output_buffer_r: Vec::with_capacity(BLOCK_SIZE * 2),
             input_fft_buffer: vec![Complex::new(0.0, 0.0); FFT_SIZE],
             conv_accumulator: vec![Complex::new(0.0, 0.0); FFT_SIZE],
+            pending_irs: None,
         }
     }
 
     #[allow(dead_code)]
     pub fn set_ir(&mut self, path: ConvolutionPath, ir_data: &[f32]) {
         let path_data = &mut self.paths[path as usize];
-
-        if ir_data.is_empty() {
-            // Handle empty IR (mute)
-            let mut silent_part = vec![Complex::new(0.0, 0.0); FFT_SIZE];
-            self.forward_fft.process(&mut silent_part);
-            path_data.ir_fft_partitions = vec![silent_part];
-        } else {
-            path_data.ir_fft_partitions = ir_data
-                .chunks(BLOCK_SIZE)
-                .map(|ir_chunk| {
-                    let mut padded_chunk = ir_chunk.to_vec();
-                    padded_chunk.resize(FFT_SIZE, 0.0);
-                    let mut complex_chunk = padded_chunk
-                        .into_iter()
-                        .map(|s| Complex::new(s, 0.0))
-                        .collect::<Vec<_>>();
-                    self.forward_fft.process(&mut complex_chunk);
-                    complex_chunk
-                })
-                .collect();
-        }
+        path_data.ir_fft_partitions = partition_ir(ir_data, &self.forward_fft);
 
         let num_partitions = path_data.ir_fft_partitions.len();
         path_data.input_fft_history = vec![vec![Complex::new(0.0, 0.0); FFT_SIZE]; num_partitions];
@@ -138,6 +141,26 @@ impl ConvolutionEngine {
         path_data.overlap_buffer.iter_mut().for_each(|s| *s = 0.0);
     }
 
+    /// Sets the IRs of all four paths, in `ConvolutionPath` order, without a crossfade.
+    pub fn set_irs(&mut self, irs: [&[f32]; 4]) {
+        let paths = [
+            ConvolutionPath::Lsl,
+            ConvolutionPath::Lsr,
+            ConvolutionPath::Rsl,
+            ConvolutionPath::Rsr,
+        ];
+        for (path, ir) in paths.into_iter().zip(irs) {
+            self.set_ir(path, ir);
+        }
+    }
+
+    /// Switches all four paths to a new set of IRs, crossfading from the old ones across
+    /// the next internal block. Paths whose partition count changes switch immediately
+    /// and start with an empty history instead.
+    pub fn crossfade_to(&mut self, irs: HrirSet) {
+        self.pending_irs = Some(irs);
+    }
+
     pub fn process_block(
         &mut self,
         input_left: &[f32],
@@ -185,46 +208,24 @@ impl ConvolutionEngine {
         let mut out_l = vec![0.0; BLOCK_SIZE];
         let mut out_r = vec![0.0; BLOCK_SIZE];
 
-        let (lsl, lsr, rsl, rsr) = {
-            let (paths_l, paths_r) = self.paths.split_at_mut(2);
-            let (path_lsl, path_lsr) = paths_l.split_at_mut(1);
-            let (path_rsl, path_rsr) = paths_r.split_at_mut(1);
-
-            let lsl = convolve_path_partitioned(
-                input_l,
-                &mut path_lsl[0],
-                &self.forward_fft,
-                &self.inverse_fft,
-                &mut self.input_fft_buffer,
-                &mut self.conv_accumulator,
-            );
-            let lsr = convolve_path_partitioned(
-                input_l,
-                &mut path_lsr[0],
-                &self.forward_fft,
-                &self.inverse_fft,
-                &mut self.input_fft_buffer,
-                &mut self.conv_accumulator,
-            );
-            let rsl = convolve_path_partitioned(
-                input_r,
-                &mut path_rsl[0],
-                &self.forward_fft,
-                &self.inverse_fft,
-                &mut self.input_fft_buffer,
-                &mut self.conv_accumulator,
-            );
-            let rsr = convolve_path_partitioned(
-                input_r,
-                &mut path_rsr[0],
+        let mut new_partitions = self.pending_irs.take().map(|irs| irs.partitions);
+        let inputs = [input_l, input_l, input_r, input_r];
+        let mut outputs: [Vec<f32>; 4] = Default::default();
+        for (i, (path_data, input)) in self.paths.iter_mut().zip(inputs).enumerate() {
+            outputs[i] = convolve_path_partitioned(
+                input,
+                path_data,
+                new_partitions
+                    .as_mut()
+                    .map(|partitions| std::mem::take(&mut partitions[i])),
                 &self.forward_fft,
                 &self.inverse_fft,
                 &mut self.input_fft_buffer,
                 &mut self.conv_accumulator,
             );
-            (lsl, lsr, rsl, rsr)
-        };
+        }
 
+        let [lsl, lsr, rsl, rsr] = &outputs;
         for i in 0..BLOCK_SIZE {
             out_l[i] = lsl[i] + rsl[i];
             out_r[i] = lsr[i] + rsr[i];
@@ -233,14 +234,93 @@ impl ConvolutionEngine {
     }
 }
 
+/// Splits an IR into `BLOCK_SIZE` partitions and transforms each of them.
+fn partition_ir(ir_data: &[f32], forward_fft: &Arc<dyn Fft<f32>>) -> Vec<Vec<Complex<f32>>> {
+    if ir_data.is_empty() {
+        // Handle empty IR (mute)
+        let mut silent_part = vec![Complex::new(0.0, 0.0); FFT_SIZE];
+        forward_fft.process(&mut silent_part);
+        return vec![silent_part];
+    }
+
+    ir_data
+        .chunks(BLOCK_SIZE)
+        .map(|ir_chunk| {
+            let mut padded_chunk = ir_chunk.to_vec();
+            padded_chunk.resize(FFT_SIZE, 0.0);
+            let mut complex_chunk = padded_chunk
+                .into_iter()
+                .map(|s| Complex::new(s, 0.0))
+                .collect::<Vec<_>>();
+            forward_fft.process(&mut complex_chunk);
+            complex_chunk
+        })
+        .collect()
+}
+
+/// Sums the products of the input history and the IR partitions into `accumulator`,
+/// pairing the input at `newest` with the first partition.
+fn accumulate_partitions(
+    input_fft_history: &[Vec<Complex<f32>>],
+    newest: usize,
+    ir_fft_partitions: &[Vec<Complex<f32>>],
+    accumulator: &mut [Complex<f32>],
+) {
+    accumulator
+        .iter_mut()
+        .for_each(|c| *c = Complex::new(0.0, 0.0));
+    let num_partitions = ir_fft_partitions.len();
+    for (i, ir_fft) in ir_fft_partitions.iter().enumerate() {
+        let input_fft = &input_fft_history[(newest + num_partitions - i) % num_partitions];
+        for j in 0..FFT_SIZE {
+            accumulator[j] += input_fft[j] * ir_fft[j];
+        }
+    }
+}
+
 fn convolve_path_partitioned(
     input_signal: &[f32],
     path_data: &mut ConvolutionPathData,
+    new_partitions: Option<Vec<Vec<Complex<f32>>>>,
     forward_fft: &Arc<dyn Fft<f32>>,
     inverse_fft: &Arc<dyn Fft<f32>>,
     input_fft_buffer: &mut [Complex<f32>],
     conv_accumulator: &mut [Complex<f32>],
 ) -> Vec<f32> {
+    let scale = 1.0 / FFT_SIZE as f32;
+
+    // 0. A new IR with the same partitioning can reuse the input history, so its output
+    // is exact from the first block on. That only needs the tail it would have left
+    // from the previous block, computed before the oldest input is overwritten.
+    let mut crossfade = None;
+    match new_partitions {
+        Some(partitions) if partitions.len() == path_data.ir_fft_partitions.len() => {
+            let num_partitions = partitions.len();
+            let previous = (path_data.history_index + num_partitions - 1) % num_partitions;
+            accumulate_partitions(
+                &path_data.input_fft_history,
+                previous,
+                &partitions,
+                conv_accumulator,
+            );
+            inverse_fft.process(conv_accumulator);
+            let tail: Vec<f32> = conv_accumulator[BLOCK_SIZE..]
+                .iter()
+                .map(|c| c.re * scale)
+                .collect();
+            crossfade = Some((partitions, tail));
+        }
+        Some(partitions) => {
+            let num_partitions = partitions.len();
+            path_data.ir_fft_partitions = partitions;
+            path_data.input_fft_history =
+                vec![vec![Complex::new(0.0, 0.0); FFT_SIZE]; num_partitions];
+            path_data.history_index = 0;
+            path_data.overlap_buffer.iter_mut().for_each(|s| *s = 0.0);
+        }
+        None => {}
+    }
+
     // 1. FFT the current input block
     for (i, sample) in input_signal.iter().enumerate() {
         input_fft_buffer[i] = Complex::new(*sample, 0.0);
@@ -258,31 +338,42 @@ fn convolve_path_partitioned(
     path_data.input_fft_history[path_data.history_index] = input_fft_buffer.to_vec();
 
     // 3. Perform convolution with all partitions
-    conv_accumulator
-        .iter_mut()
-        .for_each(|c| *c = Complex::new(0.0, 0.0));
-    let num_partitions = path_data.ir_fft_partitions.len();
-    for i in 0..num_partitions {
-        let history_idx = (path_data.history_index + num_partitions - i) % num_partitions;
-        let input_fft = &path_data.input_fft_history[history_idx];
-        let ir_fft = &path_data.ir_fft_partitions[i];
-
-        for j in 0..FFT_SIZE {
-            conv_accumulator[j] += input_fft[j] * ir_fft[j];
-        }
-    }
+    accumulate_partitions(
+        &path_data.input_fft_history,
+        path_data.history_index,
+        &path_data.ir_fft_partitions,
+        conv_accumulator,
+    );
 
     // 4. Inverse FFT
     inverse_fft.process(conv_accumulator);
 
     // 5. Overlap-add
     let mut output = vec![0.0; BLOCK_SIZE];
-    let scale = 1.0 / FFT_SIZE as f32;
     for i in 0..BLOCK_SIZE {
         output[i] = conv_accumulator[i].re * scale + path_data.overlap_buffer[i];
         path_data.overlap_buffer[i] = conv_accumulator[i + BLOCK_SIZE].re * scale;
     }
 
+    // 6. Fade over to the new IR, which continues from here on
+    if let Some((partitions, tail)) = crossfade {
+        accumulate_partitions(
+            &path_data.input_fft_history,
+            path_data.history_index,
+            &partitions,
+            conv_accumulator,
+        );
+        inverse_fft.process(conv_accumulator);
+        for i in 0..BLOCK_SIZE {
+            let new_sample = conv_accumulator[i].re * scale + tail[i];
+            let fade = (i as f32 + 0.5) / BLOCK_SIZE as f32;
+            output[i] += (new_sample - output[i]) * fade;
+            path_data.overlap_buffer[i] = conv_accumulator[i + BLOCK_SIZE].re * scale;
+        }
+        path_data.ir_fft_partitions = partitions;
+    }
+
+    let num_partitions = path_data.ir_fft_partitions.len();
     path_data.history_index = (path_data.history_index + 1) % num_partitions;
 
     output
@@ -419,4 +510,79 @@ mod tests {
             "Long IR convolution",
         );
     }
+
+    #[test]
+    fn test_crossfade_continues_with_new_ir_history() {
+        // Both IRs span two partitions, so the echo of the new IR has to come from
+        // input that was convolved before the switch
+        let ir_len = BLOCK_SIZE + 10;
+        let mut old_ir = vec![0.0; ir_len];
+        old_ir[0] = 1.0;
+        let mut new_ir = vec![0.0; ir_len];
+        new_ir[0] = 0.5;
+        new_ir[ir_len - 1] = 0.5;
+        let silent = vec![0.0; ir_len];
+
+        let mut engine = ConvolutionEngine::new();
+        let mut reference = ConvolutionEngine::new();
+        for (path, ir) in [
+            (ConvolutionPath::Lsl, &old_ir),
+            (ConvolutionPath::Lsr, &silent),
+            (ConvolutionPath::Rsl, &silent),
+            (ConvolutionPath::Rsr, &silent),
+        ] {
+            engine.set_ir(path, ir);
+        }
+        for (path, ir) in [
+            (ConvolutionPath::Lsl, &new_ir),
+            (ConvolutionPath::Lsr, &silent),
+            (ConvolutionPath::Rsl, &silent),
+            (ConvolutionPath::Rsr, &silent),
+        ] {
+            reference.set_ir(path, ir);
+        }
+
+        let input_l: Vec<f32> = (0..BLOCK_SIZE * 4)
+            .map(|i| (i as f32 * 0.05).sin())
+            .collect();
+        let input_r = vec![0.0; BLOCK_SIZE * 4];
+        let mut output = vec![0.0; BLOCK_SIZE * 4];
+        let mut reference_output = vec![0.0; BLOCK_SIZE * 4];
+        let mut output_r = vec![0.0; BLOCK_SIZE];
+        for block in 0..4 {
+            let range = block * BLOCK_SIZE..(block + 1) * BLOCK_SIZE;
+            if block == 2 {
+                engine.crossfade_to(HrirSet::new([&new_ir, &silent, &silent, &silent]));
+            }
+            engine.process_block(
+                &input_l[range.clone()],
+                &input_r[range.clone()],
+                &mut output[range.clone()],
+                &mut output_r,
+            );
+            reference.process_block(
+                &input_l[range.clone()],
+                &input_r[range.clone()],
+                &mut reference_output[range],
+                &mut output_r,
+            );
+        }
+
+        // Before the switch the old IR plays, the fade ends on the new one and from
+        // then on the output is the same as if the new IR had always been there
+        assert_approx_eq_slice(
+            &output[BLOCK_SIZE..2 * BLOCK_SIZE],
+            &input_l[BLOCK_SIZE..2 * BLOCK_SIZE],
+            TOLERANCE,
+            "Before the crossfade",
+        );
+        let last = 3 * BLOCK_SIZE - 1;
+        assert!((output[last] - reference_output[last]).abs() < 0.01);
+        assert_approx_eq_slice(
+            &output[3 * BLOCK_SIZE..],
+            &reference_output[3 * BLOCK_SIZE..],
+            TOLERANCE,
+            "After the crossfade",
+        );
+    }
 }
@@ -0,0 +1,273 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/dsp/limiter.rs
+
+// True peaks are estimated by 4x oversampling, as in ITU-R BS.1770
+const OVERSAMPLING: usize = 4;
+// Taps per interpolation phase. The detector runs this many samples / 2 behind the input.
+const INTERPOLATION_TAPS: usize = 12;
+const DETECTION_DELAY: usize = INTERPOLATION_TAPS / 2;
+
+const LOOKAHEAD_MS: f32 = 1.5;
+const RELEASE_MS: f32 = 80.0;
+
+/// Estimates the true (inter-sample) peak level of one channel by interpolating three
+/// points between every pair of samples.
+#[derive(Clone)]
+pub struct TruePeakDetector {
+    // Polyphase coefficients for the fractional positions 1/4, 2/4 and 3/4
+    phases: [[f32; INTERPOLATION_TAPS]; OVERSAMPLING - 1],
+    history: [f32; INTERPOLATION_TAPS],
+}
+
+impl Default for TruePeakDetector {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TruePeakDetector {
+    pub fn new() -> Self {
+        let mut phases = [[0.0; INTERPOLATION_TAPS]; OVERSAMPLING - 1];
+        for (p, phase) in phases.iter_mut().enumerate() {
+            let fraction = (p + 1) as f32 / OVERSAMPLING as f32;
+            for (i, tap) in phase.iter_mut().enumerate() {
+                // Distance from the interpolated position to the input sample at tap `i`
+                let x = (DETECTION_DELAY - 1) as f32 + fraction - i as f32;
+                let window = 0.5
+                    + 0.5
+                        * (std::f32::consts::PI * x / (INTERPOLATION_TAPS as f32 / 2.0 + 1.0))
+                            .cos();
+                *tap = sinc(x) * window;
+            }
+            let sum: f32 = phase.iter().sum();
+            phase.iter_mut().for_each(|tap| *tap /= sum);
+        }
+        Self {
+            phases,
+            history: [0.0; INTERPOLATION_TAPS],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.history = [0.0; INTERPOLATION_TAPS];
+    }
+
+    /// Adds a sample and returns the true peak between the two samples at the center
+    /// of the interpolation window, which runs `INTERPOLATION_TAPS / 2` samples behind.
+    pub fn push(&mut self, sample: f32) -> f32 {
+        self.history.copy_within(1.., 0);
+        self.history[INTERPOLATION_TAPS - 1] = sample;
+
+        let mut peak = self.history[DETECTION_DELAY - 1].abs();
+        for phase in &self.phases {
+            let interpolated: f32 = phase
+                .iter()
+                .zip(self.history.iter())
+                .map(|(c, x)| c * x)
+                .sum();
+            peak = peak.max(interpolated.abs());
+        }
+        peak
+    }
+}
+
+/// A stereo-linked lookahead limiter that keeps the true (inter-sample) peak level
+/// below a ceiling.
+///
+/// The required gain is held at its minimum over the lookahead window and then
+/// averaged over the same window, which guarantees that the gain applied to a sample
+/// never exceeds the gain that sample needs. The audio is delayed accordingly, also
+/// while the limiter is disabled, so the reported latency never changes.
+pub struct TruePeakLimiter {
+    ceiling: f32,
+    enabled: bool,
+    release_coeff: f32,
+
+    detectors: [TruePeakDetector; 2],
+
+    required_gain: Vec<f32>,
+    smoothed_gain: Vec<f32>,
+    window_pos: usize,
+    envelope: f32,
+
+    delay_lines: [Vec<f32>; 2],
+    delay_pos: usize,
+}
+
+impl TruePeakLimiter {
+    pub fn new(sample_rate: f32, ceiling_db: f32) -> Self {
+        let lookahead = ((LOOKAHEAD_MS / 1000.0 * sample_rate) as usize).max(1);
+        let delay = lookahead - 1 + DETECTION_DELAY;
+
+        Self {
+            ceiling: 10f32.powf(ceiling_db / 20.0),
+            enabled: false,
+            release_coeff: 1.0 - (-1.0 / (RELEASE_MS / 1000.0 * sample_rate)).exp(),
+            detectors: [TruePeakDetector::new(), TruePeakDetector::new()],
+            required_gain: vec![1.0; lookahead],
+            smoothed_gain: vec![1.0; lookahead],
+            window_pos: 0,
+            envelope: 1.0,
+            delay_lines: [vec![0.0; delay], vec![0.0; delay]],
+            delay_pos: 0,
+        }
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    pub fn latency_samples(&self) -> u32 {
+        self.delay_lines[0].len() as u32
+    }
+
+    pub fn reset(&mut self) {
+        for detector in self.detectors.iter_mut() {
+            detector.reset();
+        }
+        self.required_gain.fill(1.0);
+        self.smoothed_gain.fill(1.0);
+        self.envelope = 1.0;
+        for line in self.delay_lines.iter_mut() {
+            line.fill(0.0);
+        }
+    }
+
+    pub fn process_block(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            let peak = self.detectors[0].push(*l).max(self.detectors[1].push(*r));
+            let required = if peak > self.ceiling {
+                self.ceiling / peak
+            } else {
+                1.0
+            };
+
+            self.required_gain[self.window_pos] = required;
+            let held = self
+                .required_gain
+                .iter()
+                .copied()
+                .fold(f32::INFINITY, f32::min);
+            self.envelope = held.min(self.envelope + (1.0 - self.envelope) * self.release_coeff);
+            self.smoothed_gain[self.window_pos] = self.envelope;
+            let gain = self.smoothed_gain.iter().sum::<f32>() / self.smoothed_gain.len() as f32;
+            self.window_pos = (self.window_pos + 1) % self.required_gain.len();
+
+            let delayed_l = std::mem::replace(&mut self.delay_lines[0][self.delay_pos], *l);
+            let delayed_r = std::mem::replace(&mut self.delay_lines[1][self.delay_pos], *r);
+            self.delay_pos = (self.delay_pos + 1) % self.delay_lines[0].len();
+
+            if self.enabled {
+                *l = delayed_l * gain;
+                *r = delayed_r * gain;
+            } else {
+                *l = delayed_l;
+                *r = delayed_r;
+            }
+        }
+    }
+}
+
+fn sinc(x: f32) -> f32 {
+    if x.abs() < 1e-6 {
+        1.0
+    } else {
+        let pi_x = std::f32::consts::PI * x;
+        pi_x.sin() / pi_x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+    const CEILING_DB: f32 = -1.0;
+
+    #[test]
+    fn test_quiet_signal_passes_unchanged() {
+        let mut limiter = TruePeakLimiter::new(SAMPLE_RATE, CEILING_DB);
+        limiter.set_enabled(true);
+        let latency = limiter.latency_samples() as usize;
+
+        let input: Vec<f32> = (0..1024).map(|i| 0.5 * (i as f32 * 0.05).sin()).collect();
+        let mut left = input.clone();
+        let mut right = input.clone();
+        limiter.process_block(&mut left, &mut right);
+
+        for i in latency..input.len() {
+            assert!(
+                (left[i] - input[i - latency]).abs() < 1e-6,
+                "Mismatch at index {}",
+                i
+            );
+        }
+    }
+
+    #[test]
+    fn test_limits_inter_sample_peaks() {
+        // A sine at a quarter of the sample rate, sampled 45 degrees off its peaks, has
+        // sample values of 0.707 but a true peak of 1.0.
+        let mut limiter = TruePeakLimiter::new(SAMPLE_RATE, CEILING_DB);
+        limiter.set_enabled(true);
+        let input: Vec<f32> = (0..4096)
+            .map(|i| (std::f32::consts::FRAC_PI_2 * i as f32 + std::f32::consts::FRAC_PI_4).sin())
+            .collect();
+        let mut left = input.clone();
+        let mut right = input;
+        limiter.process_block(&mut left, &mut right);
+
+        let ceiling = 10f32.powf(CEILING_DB / 20.0);
+        let max_sample = left[1024..].iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!(
+            max_sample <= ceiling * std::f32::consts::FRAC_1_SQRT_2 * 1.02,
+            "Sample peak {} exceeds the true-peak ceiling",
+            max_sample
+        );
+    }
+
+    #[test]
+    fn test_output_never_exceeds_ceiling() {
+        let mut limiter = TruePeakLimiter::new(SAMPLE_RATE, CEILING_DB);
+        limiter.set_enabled(true);
+        let mut left: Vec<f32> = (0..8192)
+            .map(|i| 4.0 * ((i * 7919) % 1000) as f32 / 1000.0 - 2.0)
+            .collect();
+        let mut right: Vec<f32> = left.iter().map(|s| -0.5 * s).collect();
+        limiter.process_block(&mut left, &mut right);
+
+        let ceiling = 10f32.powf(CEILING_DB / 20.0);
+        for s in left.iter().chain(right.iter()) {
+            assert!(
+                s.abs() <= ceiling + 1e-5,
+                "Sample {} exceeds the ceiling",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn test_disabled_limiter_only_delays() {
+        let mut limiter = TruePeakLimiter::new(SAMPLE_RATE, CEILING_DB);
+        let latency = limiter.latency_samples() as usize;
+        let mut left = vec![0.0; 256];
+        left[0] = 4.0;
+        let mut right = vec![0.0; 256];
+        limiter.process_block(&mut left, &mut right);
+
+        assert_eq!(left[latency], 4.0);
+    }
+}
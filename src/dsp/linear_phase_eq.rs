@@ -0,0 +1,445 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/dsp/linear_phase_eq.rs
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+use std::sync::Arc;
+
+use super::parametric_eq::{BandConfig, BiquadDesign, EqChannelMode, StereoParametricEQ};
+
+// Partition size of the FFT convolution. The output runs one partition behind the input.
+const BLOCK_SIZE: usize = 512;
+const FFT_SIZE: usize = BLOCK_SIZE * 2;
+// FIR length up to 48 kHz, which resolves about 12 Hz. It grows with the sample rate so
+// the resolution stays the same.
+const BASE_FIR_LENGTH: usize = 4096;
+
+fn fir_length(sample_rate: f32) -> usize {
+    let factor = (sample_rate / 48000.0).ceil().max(1.0) as usize;
+    BASE_FIR_LENGTH * factor.next_power_of_two()
+}
+
+/// A snapshot of the EQ settings, rendered into a `LinearPhaseKernel` off the audio thread.
+#[derive(Debug, Clone)]
+pub struct LinearPhaseRequest {
+    pub sample_rate: f32,
+    pub channel_mode: EqChannelMode,
+    pub design: BiquadDesign,
+    pub bands: Vec<BandConfig>,
+}
+
+impl LinearPhaseRequest {
+    pub fn from_eq(eq: &StereoParametricEQ, sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            channel_mode: eq.channel_mode(),
+            design: eq.design(),
+            bands: eq.band_configs().copied().collect(),
+        }
+    }
+
+    /// A flat response, which renders to a pure delay.
+    pub fn flat(sample_rate: f32) -> Self {
+        Self {
+            sample_rate,
+            channel_mode: EqChannelMode::default(),
+            design: BiquadDesign::default(),
+            bands: Vec::new(),
+        }
+    }
+
+    /// Samples the magnitude response of the equivalent biquad EQ at every FFT bin and
+    /// turns it into a linear-phase FIR per channel. This is too slow for the audio thread.
+    /// Dynamic bands are rendered at rest, where they are flat.
+    pub fn design(&self) -> LinearPhaseKernel {
+        let eq = StereoParametricEQ::with_bands(
+            self.sample_rate,
+            self.channel_mode,
+            self.design,
+            &self.bands,
+        );
+
+        let length = fir_length(self.sample_rate);
+        let frequencies: Vec<f32> = (0..=length / 2)
+            .map(|k| k as f32 * self.sample_rate / length as f32)
+            .collect();
+        let [first, second] =
+            eq.calculate_channel_frequency_responses(self.sample_rate, &frequencies);
+
+        let mut planner = FftPlanner::new();
+        let first = design_fir(&first, &mut planner);
+        let second = design_fir(&second, &mut planner);
+        let forward_fft = planner.plan_fft_forward(FFT_SIZE);
+
+        if self.channel_mode == EqChannelMode::MidSide {
+            // With M = (L + R) / 2 and S = (L - R) / 2, the decoded output is
+            // L' = L * (h_m + h_s) / 2 + R * (h_m - h_s) / 2, and R' likewise
+            let direct: Vec<f32> = first
+                .iter()
+                .zip(&second)
+                .map(|(m, s)| 0.5 * (m + s))
+                .collect();
+            let cross: Vec<f32> = first
+                .iter()
+                .zip(&second)
+                .map(|(m, s)| 0.5 * (m - s))
+                .collect();
+            LinearPhaseKernel {
+                direct: [
+                    partition(&direct, &forward_fft),
+                    partition(&direct, &forward_fft),
+                ],
+                cross: Some(partition(&cross, &forward_fft)),
+            }
+        } else {
+            LinearPhaseKernel {
+                direct: [
+                    partition(&first, &forward_fft),
+                    partition(&second, &forward_fft),
+                ],
+                cross: None,
+            }
+        }
+    }
+}
+
+/// The FFT'd FIR partitions for each path through the convolver.
+pub struct LinearPhaseKernel {
+    // Left to left and right to right
+    direct: [Vec<Vec<Complex<f32>>>; 2],
+    // Left to right and right to left, only needed in mid/side mode
+    cross: Option<Vec<Vec<Complex<f32>>>>,
+}
+
+/// Runs the EQ as a linear-phase FIR using uniformly partitioned FFT convolution.
+///
+/// New kernels are crossfaded in over one partition, so EQ changes don't click. The
+/// latency is half the FIR length plus one partition, independent of the host block size.
+pub struct LinearPhaseEq {
+    forward_fft: Arc<dyn Fft<f32>>,
+    inverse_fft: Arc<dyn Fft<f32>>,
+    fir_length: usize,
+
+    kernel: LinearPhaseKernel,
+    previous_kernel: Option<LinearPhaseKernel>,
+
+    // Spectra of the most recent input partitions for each channel
+    input_history: [Vec<Vec<Complex<f32>>>; 2],
+    history_index: usize,
+
+    input_block: [Vec<f32>; 2],
+    output_block: [Vec<f32>; 2],
+    overlap: [Vec<f32>; 2],
+    block_pos: usize,
+
+    accumulators: [Vec<Complex<f32>>; 2],
+    previous_accumulators: [Vec<Complex<f32>>; 2],
+}
+
+impl LinearPhaseEq {
+    pub fn new(sample_rate: f32) -> Self {
+        let mut planner = FftPlanner::<f32>::new();
+        let fir_length = fir_length(sample_rate);
+        let num_partitions = fir_length / BLOCK_SIZE;
+        let empty_history = vec![vec![Complex::new(0.0, 0.0); FFT_SIZE]; num_partitions];
+
+        Self {
+            forward_fft: planner.plan_fft_forward(FFT_SIZE),
+            inverse_fft: planner.plan_fft_inverse(FFT_SIZE),
+            fir_length,
+            kernel: LinearPhaseRequest::flat(sample_rate).design(),
+            previous_kernel: None,
+            input_history: [empty_history.clone(), empty_history],
+            history_index: 0,
+            input_block: [vec![0.0; BLOCK_SIZE], vec![0.0; BLOCK_SIZE]],
+            output_block: [vec![0.0; BLOCK_SIZE], vec![0.0; BLOCK_SIZE]],
+            overlap: [vec![0.0; BLOCK_SIZE], vec![0.0; BLOCK_SIZE]],
+            block_pos: 0,
+            accumulators: [
+                vec![Complex::new(0.0, 0.0); FFT_SIZE],
+                vec![Complex::new(0.0, 0.0); FFT_SIZE],
+            ],
+            previous_accumulators: [
+                vec![Complex::new(0.0, 0.0); FFT_SIZE],
+                vec![Complex::new(0.0, 0.0); FFT_SIZE],
+            ],
+        }
+    }
+
+    pub fn latency_samples(&self) -> u32 {
+        (self.fir_length / 2 + BLOCK_SIZE) as u32
+    }
+
+    /// Kernels designed for another sample rate are ignored.
+    pub fn set_kernel(&mut self, kernel: LinearPhaseKernel) {
+        if kernel.direct[0].len() == self.input_history[0].len() {
+            self.previous_kernel = Some(std::mem::replace(&mut self.kernel, kernel));
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for channel in 0..2 {
+            for spectrum in self.input_history[channel].iter_mut() {
+                spectrum.fill(Complex::new(0.0, 0.0));
+            }
+            self.input_block[channel].fill(0.0);
+            self.output_block[channel].fill(0.0);
+            self.overlap[channel].fill(0.0);
+        }
+        self.history_index = 0;
+        self.block_pos = 0;
+        self.previous_kernel = None;
+    }
+
+    pub fn process_block(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (l, r) in left.iter_mut().zip(right.iter_mut()) {
+            self.input_block[0][self.block_pos] = *l;
+            self.input_block[1][self.block_pos] = *r;
+            *l = self.output_block[0][self.block_pos];
+            *r = self.output_block[1][self.block_pos];
+
+            self.block_pos += 1;
+            if self.block_pos == BLOCK_SIZE {
+                self.process_partition();
+                self.block_pos = 0;
+            }
+        }
+    }
+
+    fn process_partition(&mut self) {
+        for channel in 0..2 {
+            let spectrum = &mut self.input_history[channel][self.history_index];
+            for (bin, sample) in spectrum.iter_mut().zip(&self.input_block[channel]) {
+                *bin = Complex::new(*sample, 0.0);
+            }
+            spectrum[BLOCK_SIZE..].fill(Complex::new(0.0, 0.0));
+            self.forward_fft.process(spectrum);
+        }
+
+        accumulate(
+            &self.kernel,
+            &self.input_history,
+            self.history_index,
+            &mut self.accumulators,
+        );
+        for accumulator in self.accumulators.iter_mut() {
+            self.inverse_fft.process(accumulator);
+        }
+        // The overlap from the previous partition already used the old kernel, so only
+        // this partition needs to be faded
+        let crossfade = match self.previous_kernel.take() {
+            Some(previous_kernel) => {
+                accumulate(
+                    &previous_kernel,
+                    &self.input_history,
+                    self.history_index,
+                    &mut self.previous_accumulators,
+                );
+                for accumulator in self.previous_accumulators.iter_mut() {
+                    self.inverse_fft.process(accumulator);
+                }
+                true
+            }
+            None => false,
+        };
+
+        let scale = 1.0 / FFT_SIZE as f32;
+        for channel in 0..2 {
+            let current = &self.accumulators[channel];
+            let previous = &self.previous_accumulators[channel];
+            for i in 0..BLOCK_SIZE {
+                let sample = if crossfade {
+                    let t = i as f32 / BLOCK_SIZE as f32;
+                    (1.0 - t) * previous[i].re + t * current[i].re
+                } else {
+                    current[i].re
+                };
+                self.output_block[channel][i] = sample * scale + self.overlap[channel][i];
+                self.overlap[channel][i] = current[i + BLOCK_SIZE].re * scale;
+            }
+        }
+
+        self.history_index = (self.history_index + 1) % self.input_history[0].len();
+    }
+}
+
+/// Sums the products of the input history and the kernel partitions for both outputs.
+fn accumulate(
+    kernel: &LinearPhaseKernel,
+    input_history: &[Vec<Vec<Complex<f32>>>; 2],
+    history_index: usize,
+    accumulators: &mut [Vec<Complex<f32>>; 2],
+) {
+    for accumulator in accumulators.iter_mut() {
+        accumulator.fill(Complex::new(0.0, 0.0));
+    }
+    let num_partitions = input_history[0].len();
+    for partition in 0..num_partitions {
+        let index = (history_index + num_partitions - partition) % num_partitions;
+        let (input_l, input_r) = (&input_history[0][index], &input_history[1][index]);
+        let [accumulator_l, accumulator_r] = accumulators;
+
+        let (direct_l, direct_r) = (&kernel.direct[0][partition], &kernel.direct[1][partition]);
+        for j in 0..FFT_SIZE {
+            accumulator_l[j] += input_l[j] * direct_l[j];
+            accumulator_r[j] += input_r[j] * direct_r[j];
+        }
+        if let Some(cross) = &kernel.cross {
+            let cross = &cross[partition];
+            for j in 0..FFT_SIZE {
+                accumulator_l[j] += input_r[j] * cross[j];
+                accumulator_r[j] += input_l[j] * cross[j];
+            }
+        }
+    }
+}
+
+/// Designs a linear-phase FIR from magnitudes at the `n / 2 + 1` bins of an `n`-point FFT.
+/// The result has `n` taps and a delay of `n / 2` samples.
+fn design_fir(magnitudes: &[f32], planner: &mut FftPlanner<f32>) -> Vec<f32> {
+    let length = 2 * (magnitudes.len() - 1);
+    let mut spectrum: Vec<Complex<f32>> = (0..length)
+        .map(|k| Complex::new(magnitudes[k.min(length - k)], 0.0))
+        .collect();
+    planner.plan_fft_inverse(length).process(&mut spectrum);
+
+    // The zero-phase impulse response is centered on sample 0. It is moved to the
+    // middle and windowed, which trades a little resolution for less time aliasing.
+    (0..length)
+        .map(|i| {
+            let window = 0.5 - 0.5 * (2.0 * std::f32::consts::PI * i as f32 / length as f32).cos();
+            spectrum[(i + length / 2) % length].re / length as f32 * window
+        })
+        .collect()
+}
+
+fn partition(fir: &[f32], forward_fft: &Arc<dyn Fft<f32>>) -> Vec<Vec<Complex<f32>>> {
+    fir.chunks(BLOCK_SIZE)
+        .map(|chunk| {
+            let mut spectrum: Vec<Complex<f32>> =
+                chunk.iter().map(|&s| Complex::new(s, 0.0)).collect();
+            spectrum.resize(FFT_SIZE, Complex::new(0.0, 0.0));
+            forward_fft.process(&mut spectrum);
+            spectrum
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dsp::parametric_eq::{BandChannel, FilterType};
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    fn band(gain_db: f32, channel: BandChannel) -> BandConfig {
+        BandConfig {
+            filter_type: FilterType::Peak,
+            center_freq: 1000.0,
+            q: 1.0,
+            gain_db,
+            enabled: true,
+            channel,
+            dynamics: None,
+        }
+    }
+
+    /// Runs an impulse on the left input through `eq` and returns both outputs with the
+    /// latency removed.
+    fn impulse_response(eq: &mut LinearPhaseEq, length: usize) -> [Vec<f32>; 2] {
+        let latency = eq.latency_samples() as usize;
+        let mut left = vec![0.0; latency + length];
+        left[0] = 1.0;
+        let mut right = vec![0.0; latency + length];
+        eq.process_block(&mut left, &mut right);
+        [left.split_off(latency), right.split_off(latency)]
+    }
+
+    #[test]
+    fn test_flat_kernel_is_a_pure_delay() {
+        let mut eq = LinearPhaseEq::new(SAMPLE_RATE);
+        let [left, right] = impulse_response(&mut eq, 256);
+
+        assert!((left[0] - 1.0).abs() < 1e-4, "Impulse was {}", left[0]);
+        assert!(left[1..].iter().chain(&right).all(|s| s.abs() < 1e-4));
+    }
+
+    #[test]
+    fn test_kernel_is_symmetric_and_matches_iir_magnitude() {
+        let request = LinearPhaseRequest {
+            sample_rate: SAMPLE_RATE,
+            channel_mode: EqChannelMode::Linked,
+            design: BiquadDesign::Bilinear,
+            bands: vec![band(6.0, BandChannel::Both)],
+        };
+        let mut eq = LinearPhaseEq::new(SAMPLE_RATE);
+        eq.set_kernel(request.design());
+        eq.reset();
+
+        // The FIR is centered on the latency, so it extends half its length either side
+        let half = eq.fir_length / 2;
+        let latency = eq.latency_samples() as usize;
+        let mut left = vec![0.0; latency + half];
+        left[0] = 1.0;
+        let mut right = left.clone();
+        eq.process_block(&mut left, &mut right);
+        let fir = &left[latency - half..];
+
+        for k in 1..half {
+            assert!(
+                (fir[half + k] - fir[half - k]).abs() < 1e-5,
+                "FIR is not symmetric at offset {}",
+                k
+            );
+        }
+
+        let omega = 2.0 * std::f32::consts::PI * 1000.0 / SAMPLE_RATE;
+        let gain = fir
+            .iter()
+            .enumerate()
+            .fold(Complex::new(0.0f32, 0.0), |sum, (n, &h)| {
+                sum + Complex::from_polar(h, -omega * n as f32)
+            })
+            .norm();
+        let gain_db = 20.0 * gain.log10();
+        assert!(
+            (gain_db - 6.0).abs() < 0.2,
+            "Gain at 1 kHz was {} dB",
+            gain_db
+        );
+    }
+
+    #[test]
+    fn test_mid_side_side_band_leaves_mono_untouched() {
+        let mut eq = StereoParametricEQ::new(1, SAMPLE_RATE);
+        eq.set_channel_mode(EqChannelMode::MidSide);
+        eq.update_band_coeffs(0, SAMPLE_RATE, &band(-12.0, BandChannel::Second), 0);
+
+        let mut linear_phase = LinearPhaseEq::new(SAMPLE_RATE);
+        linear_phase.set_kernel(LinearPhaseRequest::from_eq(&eq, SAMPLE_RATE).design());
+        linear_phase.reset();
+
+        let latency = linear_phase.latency_samples() as usize;
+        let mut left = vec![0.0; latency + 64];
+        left[0] = 1.0;
+        let mut right = left.clone();
+        linear_phase.process_block(&mut left, &mut right);
+
+        assert!((left[latency] - 1.0).abs() < 1e-4);
+        assert!((right[latency] - 1.0).abs() < 1e-4);
+        assert!(left[latency + 1..].iter().all(|s| s.abs() < 1e-4));
+    }
+}
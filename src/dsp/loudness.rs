@@ -0,0 +1,238 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/dsp/loudness.rs
+
+use crate::dsp::parametric_eq::{BiquadFilter, FilterType};
+
+// ISO 226:2003, table 1: the frequencies, the exponent of loudness perception alpha_f,
+// the transfer function of the ear L_U relative to 1 kHz and the threshold of hearing T_f
+const ISO_FREQUENCIES: [f32; 29] = [
+    20.0, 25.0, 31.5, 40.0, 50.0, 63.0, 80.0, 100.0, 125.0, 160.0, 200.0, 250.0, 315.0, 400.0,
+    500.0, 630.0, 800.0, 1000.0, 1250.0, 1600.0, 2000.0, 2500.0, 3150.0, 4000.0, 5000.0, 6300.0,
+    8000.0, 10000.0, 12500.0,
+];
+const ALPHA_F: [f32; 29] = [
+    0.532, 0.506, 0.480, 0.455, 0.432, 0.409, 0.387, 0.367, 0.349, 0.330, 0.315, 0.301, 0.288,
+    0.276, 0.267, 0.259, 0.253, 0.250, 0.246, 0.244, 0.243, 0.243, 0.243, 0.242, 0.242, 0.245,
+    0.254, 0.271, 0.301,
+];
+const L_U: [f32; 29] = [
+    -31.6, -27.2, -23.0, -19.1, -15.9, -13.0, -10.3, -8.1, -6.2, -4.5, -3.1, -2.0, -1.1, -0.4, 0.0,
+    0.3, 0.5, 0.0, -2.7, -4.1, -1.0, 1.7, 2.5, 1.2, -2.1, -7.1, -11.2, -10.7, -3.1,
+];
+const T_F: [f32; 29] = [
+    78.5, 68.7, 59.5, 51.1, 44.0, 37.5, 31.5, 26.5, 22.1, 17.9, 14.4, 11.4, 8.6, 6.2, 4.4, 3.0,
+    2.2, 2.4, 3.5, 1.7, -1.3, -4.2, -6.0, -5.4, -1.5, 6.0, 12.6, 13.9, 12.3,
+];
+const INDEX_1KHZ: usize = 17;
+
+// The loudness levels the contours are specified for
+const MIN_PHON: f32 = 20.0;
+const MAX_PHON: f32 = 90.0;
+
+// Smaller changes of the listening level keep the current shelves
+const LEVEL_TOLERANCE_PHON: f32 = 0.05;
+
+/// The sound pressure level in dB at which a tone at `ISO_FREQUENCIES[index]` sounds as
+/// loud as a 1 kHz tone at `phon`.
+fn equal_loudness_spl(index: usize, phon: f32) -> f32 {
+    let a_f = 4.47e-3 * (10f32.powf(0.025 * phon) - 1.15)
+        + (0.4 * 10f32.powf((T_F[index] + L_U[index]) / 10.0 - 9.0)).powf(ALPHA_F[index]);
+    10.0 / ALPHA_F[index] * a_f.log10() - L_U[index] + 94.0
+}
+
+/// The gain at each ISO frequency, relative to 1 kHz, that makes a recording played back
+/// at `listening_phon` sound as balanced as at `reference_phon`.
+fn compensation_db(listening_phon: f32, reference_phon: f32) -> [f32; 29] {
+    let contour =
+        |index, phon| equal_loudness_spl(index, phon) - equal_loudness_spl(INDEX_1KHZ, phon);
+    std::array::from_fn(|i| contour(i, listening_phon) - contour(i, reference_phon))
+}
+
+/// Fits a shelf to one end of the compensation curve. The shelf takes the gain at the
+/// outermost frequency and is centered where the curve passes half of that gain, which
+/// is where the shelves of `BiquadFilter` reach half their gain too.
+fn fit_shelf(curve: &[f32; 29], outer: usize) -> (f32, f32) {
+    let gain_db = curve[outer];
+    let half_db = gain_db / 2.0;
+    // Walk from the outer frequency towards 1 kHz, where the curve is 0 dB
+    for k in 0..outer.abs_diff(INDEX_1KHZ) {
+        let (i, j) = if outer < INDEX_1KHZ {
+            (outer + k, outer + k + 1)
+        } else {
+            (outer - k, outer - k - 1)
+        };
+        if (curve[i] - half_db) * (curve[j] - half_db) <= 0.0 && curve[i] != curve[j] {
+            let t = (half_db - curve[i]) / (curve[j] - curve[i]);
+            let (log_i, log_j) = (ISO_FREQUENCIES[i].ln(), ISO_FREQUENCIES[j].ln());
+            return (gain_db, (log_i + t * (log_j - log_i)).exp());
+        }
+    }
+    (gain_db, ISO_FREQUENCIES[INDEX_1KHZ])
+}
+
+/// Equal-loudness compensation after ISO 226:2003. Quieter playback makes low and very
+/// high frequencies fade faster than the midrange, so this boosts them by the difference
+/// between the equal-loudness contours of the listening level and a reference level.
+///
+/// The curve is approximated by a first-order low shelf, which follows the gradual bass
+/// rise to within about a decibel, and a second-order high shelf for the rise above
+/// 8 kHz. The small dip around 3 kHz is not corrected.
+pub struct LoudnessCompensation {
+    // A low and a high shelf per channel
+    filters: [[BiquadFilter; 2]; 2],
+    sample_rate: f32,
+    // The (listening, reference) levels the shelves are designed for
+    levels: Option<(f32, f32)>,
+}
+
+impl LoudnessCompensation {
+    pub fn new(sample_rate: f32) -> Self {
+        let shelf = || {
+            let mut filter = BiquadFilter::new(sample_rate);
+            filter.set_enabled(true);
+            filter
+        };
+        Self {
+            filters: [[shelf(), shelf()], [shelf(), shelf()]],
+            sample_rate,
+            levels: None,
+        }
+    }
+
+    /// Designs the shelves for the level the listener hears and the level the material
+    /// sounds right at, both in phon. After the first call, the shelves glide to a new
+    /// design over `glide_samples` samples.
+    pub fn set_levels(&mut self, listening_phon: f32, reference_phon: f32, glide_samples: usize) {
+        let listening_phon = listening_phon.clamp(MIN_PHON, MAX_PHON);
+        let reference_phon = reference_phon.clamp(MIN_PHON, MAX_PHON);
+        let glide_samples = match self.levels {
+            Some((listening, reference))
+                if (listening - listening_phon).abs() < LEVEL_TOLERANCE_PHON
+                    && reference == reference_phon =>
+            {
+                return;
+            }
+            Some(_) => glide_samples,
+            None => 0,
+        };
+        self.levels = Some((listening_phon, reference_phon));
+
+        let curve = compensation_db(listening_phon, reference_phon);
+        let (low_gain_db, low_freq) = fit_shelf(&curve, 0);
+        let (high_gain_db, high_freq) = fit_shelf(&curve, ISO_FREQUENCIES.len() - 1);
+        let high_freq = high_freq.min(self.sample_rate * 0.45);
+        for [low, high] in self.filters.iter_mut() {
+            // The first-order shelf has no Q
+            low.glide_coeffs(
+                FilterType::LowShelf1,
+                self.sample_rate,
+                low_freq,
+                1.0,
+                low_gain_db,
+                glide_samples,
+            );
+            high.glide_coeffs(
+                FilterType::HighShelf,
+                self.sample_rate,
+                high_freq,
+                std::f32::consts::FRAC_1_SQRT_2,
+                high_gain_db,
+                glide_samples,
+            );
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for filter in self.filters.iter_mut().flatten() {
+            filter.reset_state();
+        }
+    }
+
+    pub fn process_block(&mut self, left: &mut [f32], right: &mut [f32]) {
+        for (channel, [low, high]) in [left, right].into_iter().zip(self.filters.iter_mut()) {
+            for sample in channel.iter_mut() {
+                *sample = high.process_sample(low.process_sample(*sample));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    #[test]
+    fn test_contours_match_iso_226_table() {
+        // The 40 phon contour as tabulated in ISO 226:2003
+        for (index, expected) in [
+            (0, 99.85),
+            (7, 64.37),
+            (17, 40.01),
+            (24, 40.01),
+            (28, 51.49),
+        ] {
+            let spl = equal_loudness_spl(index, 40.0);
+            assert!(
+                (spl - expected).abs() < 0.05,
+                "{} Hz: {} dB instead of {} dB",
+                ISO_FREQUENCIES[index],
+                spl,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_shelves_follow_compensation_curve() {
+        let mut loudness = LoudnessCompensation::new(SAMPLE_RATE);
+        loudness.set_levels(50.0, 80.0, 0);
+        let curve = compensation_db(50.0, 80.0);
+        assert!(curve[0] > 15.0, "Bass boost was only {} dB", curve[0]);
+
+        let [low, high] = &loudness.filters[0];
+        for (index, &freq) in ISO_FREQUENCIES.iter().enumerate() {
+            let response = low.frequency_response(freq, SAMPLE_RATE)
+                * high.frequency_response(freq, SAMPLE_RATE);
+            let response_db = 20.0 * response.norm().log10();
+            // The high shelf skips the dip around 3 kHz
+            let tolerance = if index <= INDEX_1KHZ { 1.0 } else { 2.0 };
+            assert!(
+                (response_db - curve[index]).abs() < tolerance,
+                "{} Hz: {} dB instead of {} dB",
+                freq,
+                response_db,
+                curve[index]
+            );
+        }
+    }
+
+    #[test]
+    fn test_flat_at_reference_level() {
+        let mut loudness = LoudnessCompensation::new(SAMPLE_RATE);
+        loudness.set_levels(80.0, 80.0, 0);
+
+        let input: Vec<f32> = (0..256).map(|i| (i as f32 * 0.37).sin()).collect();
+        let mut left = input.clone();
+        let mut right = input.clone();
+        loudness.process_block(&mut left, &mut right);
+
+        for (output, input) in left.iter().chain(&right).zip(input.iter().cycle()) {
+            assert!((output - input).abs() < 1e-4, "{} vs {}", output, input);
+        }
+    }
+}
@@ -0,0 +1,402 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/dsp/meter.rs
+
+use biquad::{Biquad, Coefficients, DirectForm2Transposed};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use crate::dsp::limiter::TruePeakDetector;
+
+// Levels below this read as silence
+pub const METER_FLOOR_DB: f32 = -100.0;
+
+const RMS_WINDOW_MS: f32 = 300.0;
+
+// EBU R128 / ITU-R BS.1770: loudness is measured in 100 ms steps over 400 ms
+// (momentary) and 3 s (short-term) windows
+const LOUDNESS_STEP_MS: f32 = 100.0;
+const MOMENTARY_STEPS: usize = 4;
+const SHORT_TERM_STEPS: usize = 30;
+const ABSOLUTE_GATE_LUFS: f32 = -70.0;
+const RELATIVE_GATE_LU: f32 = -10.0;
+// The momentary blocks for the integrated loudness are kept as a histogram, so a
+// measurement can run for any length of time without allocating
+const HISTOGRAM_STEP_LU: f32 = 0.1;
+const HISTOGRAM_BINS: usize = 800;
+
+fn mean_square_to_lufs(mean_square: f64) -> f32 {
+    if mean_square > 0.0 {
+        ((-0.691 + 10.0 * mean_square.log10()) as f32).max(METER_FLOOR_DB)
+    } else {
+        METER_FLOOR_DB
+    }
+}
+
+/// The BS.1770 K-weighting filter: a high shelf for the acoustic effect of the head,
+/// then a high-pass, designed for any sample rate.
+pub fn k_weighting_coefficients(sample_rate: f32) -> [Coefficients<f32>; 2] {
+    let sample_rate = sample_rate as f64;
+
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (std::f64::consts::PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Coefficients {
+        b0: ((vh + vb * k / q + k * k) / a0) as f32,
+        b1: (2.0 * (k * k - vh) / a0) as f32,
+        b2: ((vh - vb * k / q + k * k) / a0) as f32,
+        a1: (2.0 * (k * k - 1.0) / a0) as f32,
+        a2: ((1.0 - k / q + k * k) / a0) as f32,
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (std::f64::consts::PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Coefficients {
+        b0: 1.0,
+        b1: -2.0,
+        b2: 1.0,
+        a1: (2.0 * (k * k - 1.0) / a0) as f32,
+        a2: ((1.0 - k / q + k * k) / a0) as f32,
+    };
+
+    [shelf, high_pass]
+}
+
+/// Levels a `LevelMeter` publishes for the editor, readable from any thread without
+/// locking. Sample and true peaks accumulate until they are taken.
+pub struct MeterReadings {
+    // Linear amplitudes and loudness values, as `f32` bits
+    peak: [AtomicU32; 2],
+    true_peak: [AtomicU32; 2],
+    rms: [AtomicU32; 2],
+    momentary_lufs: AtomicU32,
+    short_term_lufs: AtomicU32,
+    integrated_lufs: AtomicU32,
+    clipped: AtomicBool,
+    reset_requested: AtomicBool,
+}
+
+impl Default for MeterReadings {
+    fn default() -> Self {
+        let floor = || AtomicU32::new(METER_FLOOR_DB.to_bits());
+        Self {
+            peak: Default::default(),
+            true_peak: Default::default(),
+            rms: Default::default(),
+            momentary_lufs: floor(),
+            short_term_lufs: floor(),
+            integrated_lufs: floor(),
+            clipped: AtomicBool::new(false),
+            reset_requested: AtomicBool::new(false),
+        }
+    }
+}
+
+impl MeterReadings {
+    /// The highest sample amplitude of a channel since the last call.
+    pub fn take_peak(&self, channel: usize) -> f32 {
+        f32::from_bits(self.peak[channel].swap(0, Ordering::Relaxed))
+    }
+
+    /// The highest true peak amplitude of a channel since the last call.
+    pub fn take_true_peak(&self, channel: usize) -> f32 {
+        f32::from_bits(self.true_peak[channel].swap(0, Ordering::Relaxed))
+    }
+
+    pub fn rms(&self, channel: usize) -> f32 {
+        f32::from_bits(self.rms[channel].load(Ordering::Relaxed))
+    }
+
+    pub fn momentary_lufs(&self) -> f32 {
+        f32::from_bits(self.momentary_lufs.load(Ordering::Relaxed))
+    }
+
+    pub fn short_term_lufs(&self) -> f32 {
+        f32::from_bits(self.short_term_lufs.load(Ordering::Relaxed))
+    }
+
+    pub fn integrated_lufs(&self) -> f32 {
+        f32::from_bits(self.integrated_lufs.load(Ordering::Relaxed))
+    }
+
+    /// Whether the true peak went above full scale since the last `clear_clip`.
+    pub fn clipped(&self) -> bool {
+        self.clipped.load(Ordering::Relaxed)
+    }
+
+    pub fn clear_clip(&self) {
+        self.clipped.store(false, Ordering::Relaxed);
+    }
+
+    /// Asks the meter to restart the integrated loudness measurement.
+    pub fn reset_integrated(&self) {
+        self.reset_requested.store(true, Ordering::Relaxed);
+    }
+
+    fn publish_peaks(&self, channel: usize, peak: f32, true_peak: f32) {
+        // Non-negative floats order the same as their bit patterns
+        self.peak[channel].fetch_max(peak.to_bits(), Ordering::Relaxed);
+        self.true_peak[channel].fetch_max(true_peak.to_bits(), Ordering::Relaxed);
+        if true_peak > 1.0 {
+            self.clipped.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Measures the sample peak, RMS and true peak of each channel of a stereo signal and
+/// its EBU R128 momentary, short-term and integrated loudness. Runs on the audio thread
+/// and never allocates after construction.
+pub struct LevelMeter {
+    k_weighting: [[DirectForm2Transposed<f32>; 2]; 2],
+    true_peak: [TruePeakDetector; 2],
+    rms_coeff: f32,
+    mean_square: [f32; 2],
+
+    step_len: usize,
+    step_pos: usize,
+    // The K-weighted energy of both channels in the current step
+    step_energy: f64,
+    // The mean square of the last `SHORT_TERM_STEPS` steps, as a ring
+    steps: [f64; SHORT_TERM_STEPS],
+    step_index: usize,
+    steps_measured: usize,
+
+    histogram_counts: Vec<u32>,
+    histogram_energy: Vec<f64>,
+}
+
+impl LevelMeter {
+    pub fn new(sample_rate: f32) -> Self {
+        let [shelf, high_pass] = k_weighting_coefficients(sample_rate);
+        let k_weighting = || {
+            [
+                DirectForm2Transposed::<f32>::new(shelf),
+                DirectForm2Transposed::<f32>::new(high_pass),
+            ]
+        };
+        Self {
+            k_weighting: [k_weighting(), k_weighting()],
+            true_peak: [TruePeakDetector::new(), TruePeakDetector::new()],
+            rms_coeff: 1.0 - (-1.0 / (RMS_WINDOW_MS / 1000.0 * sample_rate)).exp(),
+            mean_square: [0.0; 2],
+            step_len: ((LOUDNESS_STEP_MS / 1000.0 * sample_rate) as usize).max(1),
+            step_pos: 0,
+            step_energy: 0.0,
+            steps: [0.0; SHORT_TERM_STEPS],
+            step_index: 0,
+            steps_measured: 0,
+            histogram_counts: vec![0; HISTOGRAM_BINS],
+            histogram_energy: vec![0.0; HISTOGRAM_BINS],
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for filter in self.k_weighting.iter_mut().flatten() {
+            filter.reset_state();
+        }
+        for detector in self.true_peak.iter_mut() {
+            detector.reset();
+        }
+        self.mean_square = [0.0; 2];
+        self.step_pos = 0;
+        self.step_energy = 0.0;
+        self.steps = [0.0; SHORT_TERM_STEPS];
+        self.steps_measured = 0;
+        self.reset_integrated();
+    }
+
+    fn reset_integrated(&mut self) {
+        self.histogram_counts.fill(0);
+        self.histogram_energy.fill(0.0);
+    }
+
+    pub fn process_block(&mut self, left: &[f32], right: &[f32], readings: &MeterReadings) {
+        if readings.reset_requested.swap(false, Ordering::Relaxed) {
+            self.reset_integrated();
+            readings
+                .integrated_lufs
+                .store(METER_FLOOR_DB.to_bits(), Ordering::Relaxed);
+        }
+
+        let mut peaks = [0.0f32; 2];
+        let mut true_peaks = [0.0f32; 2];
+        for (&l, &r) in left.iter().zip(right) {
+            let mut energy = 0.0;
+            for (channel, sample) in [l, r].into_iter().enumerate() {
+                peaks[channel] = peaks[channel].max(sample.abs());
+                true_peaks[channel] = true_peaks[channel].max(self.true_peak[channel].push(sample));
+                self.mean_square[channel] +=
+                    (sample * sample - self.mean_square[channel]) * self.rms_coeff;
+
+                let [shelf, high_pass] = &mut self.k_weighting[channel];
+                let weighted = high_pass.run(shelf.run(sample));
+                energy += weighted * weighted;
+            }
+
+            self.step_energy += energy as f64;
+            self.step_pos += 1;
+            if self.step_pos == self.step_len {
+                self.finish_step(readings);
+            }
+        }
+
+        for channel in 0..2 {
+            readings.publish_peaks(channel, peaks[channel], true_peaks[channel]);
+            readings.rms[channel].store(
+                self.mean_square[channel].sqrt().to_bits(),
+                Ordering::Relaxed,
+            );
+        }
+    }
+
+    /// Publishes the loudness at the end of a 100 ms step.
+    fn finish_step(&mut self, readings: &MeterReadings) {
+        self.steps[self.step_index] = self.step_energy / self.step_len as f64;
+        self.step_index = (self.step_index + 1) % SHORT_TERM_STEPS;
+        self.steps_measured += 1;
+        self.step_pos = 0;
+        self.step_energy = 0.0;
+
+        // The newest steps, with steps before the measurement started counting as silence
+        let window_mean = |len: usize| {
+            (0..len)
+                .map(|i| {
+                    self.steps[(self.step_index + SHORT_TERM_STEPS - 1 - i) % SHORT_TERM_STEPS]
+                })
+                .sum::<f64>()
+                / len as f64
+        };
+        let momentary = window_mean(MOMENTARY_STEPS);
+        let momentary_lufs = mean_square_to_lufs(momentary);
+        let short_term_lufs = mean_square_to_lufs(window_mean(SHORT_TERM_STEPS));
+        readings
+            .momentary_lufs
+            .store(momentary_lufs.to_bits(), Ordering::Relaxed);
+        readings
+            .short_term_lufs
+            .store(short_term_lufs.to_bits(), Ordering::Relaxed);
+
+        // Every full momentary window is a gating block, overlapping the previous by 75%
+        if self.steps_measured >= MOMENTARY_STEPS && momentary_lufs > ABSOLUTE_GATE_LUFS {
+            let bin = ((momentary_lufs - ABSOLUTE_GATE_LUFS) / HISTOGRAM_STEP_LU) as usize;
+            let bin = bin.min(HISTOGRAM_BINS - 1);
+            self.histogram_counts[bin] += 1;
+            self.histogram_energy[bin] += momentary;
+        }
+        readings
+            .integrated_lufs
+            .store(self.integrated_lufs().to_bits(), Ordering::Relaxed);
+    }
+
+    /// The gated loudness of all blocks since the measurement started.
+    fn integrated_lufs(&self) -> f32 {
+        let gated_mean = |first_bin: usize| {
+            let count: u32 = self.histogram_counts[first_bin..].iter().sum();
+            let energy: f64 = self.histogram_energy[first_bin..].iter().sum();
+            (count > 0).then(|| energy / count as f64)
+        };
+        let Some(ungated) = gated_mean(0) else {
+            return METER_FLOOR_DB;
+        };
+        let relative_gate = mean_square_to_lufs(ungated) + RELATIVE_GATE_LU;
+        let first_bin = ((relative_gate - ABSOLUTE_GATE_LUFS) / HISTOGRAM_STEP_LU)
+            .ceil()
+            .max(0.0) as usize;
+        gated_mean(first_bin.min(HISTOGRAM_BINS - 1)).map_or(METER_FLOOR_DB, mean_square_to_lufs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: f32 = 48000.0;
+
+    fn sine(freq: f32, level_db: f32, seconds: f32) -> Vec<f32> {
+        let amplitude = 10f32.powf(level_db / 20.0);
+        (0..(seconds * SAMPLE_RATE) as usize)
+            .map(|i| amplitude * (2.0 * std::f32::consts::PI * freq * i as f32 / SAMPLE_RATE).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_stereo_sine_reads_its_level() {
+        // EBU Tech 3341, test 1: a 1 kHz sine at -23 dBFS in both channels is -23 LUFS
+        let mut meter = LevelMeter::new(SAMPLE_RATE);
+        let readings = MeterReadings::default();
+        let signal = sine(1000.0, -23.0, 5.0);
+        for block in signal.chunks(512) {
+            meter.process_block(block, block, &readings);
+        }
+
+        for lufs in [
+            readings.momentary_lufs(),
+            readings.short_term_lufs(),
+            readings.integrated_lufs(),
+        ] {
+            assert!((lufs + 23.0).abs() < 0.1, "Read {} LUFS", lufs);
+        }
+        let peak_db = 20.0 * readings.take_peak(0).log10();
+        let rms_db = 20.0 * readings.rms(1).log10();
+        assert!((peak_db + 23.0).abs() < 0.01, "Peak of {} dB", peak_db);
+        assert!((rms_db + 26.01).abs() < 0.1, "RMS of {} dB", rms_db);
+        assert_eq!(readings.take_peak(0), 0.0);
+        assert!(!readings.clipped());
+    }
+
+    #[test]
+    fn test_integrated_loudness_gates_quiet_passages() {
+        // EBU Tech 3341, test 3: 10 s at -36, 60 s at -23 and 10 s at -36 dBFS
+        let mut meter = LevelMeter::new(SAMPLE_RATE);
+        let readings = MeterReadings::default();
+        for (level_db, seconds) in [(-36.0, 10.0), (-23.0, 60.0), (-36.0, 10.0)] {
+            let signal = sine(1000.0, level_db, seconds);
+            for block in signal.chunks(1024) {
+                meter.process_block(block, block, &readings);
+            }
+        }
+        let integrated = readings.integrated_lufs();
+        assert!((integrated + 23.0).abs() < 0.1, "Read {} LUFS", integrated);
+
+        readings.reset_integrated();
+        meter.process_block(&[0.0; 64], &[0.0; 64], &readings);
+        assert_eq!(readings.integrated_lufs(), METER_FLOOR_DB);
+    }
+
+    #[test]
+    fn test_inter_sample_peak_latches_clip() {
+        // A quarter of the sample rate at 45° never samples its crests
+        let signal: Vec<f32> = (0..4800)
+            .map(|i| {
+                1.1 * (std::f32::consts::FRAC_PI_2 * i as f32 + std::f32::consts::FRAC_PI_4).sin()
+            })
+            .collect();
+        let mut meter = LevelMeter::new(SAMPLE_RATE);
+        let readings = MeterReadings::default();
+        meter.process_block(&signal, &signal, &readings);
+
+        assert!(readings.take_peak(0) < 0.8);
+        let true_peak = readings.take_true_peak(0);
+        assert!((true_peak - 1.1).abs() < 0.02, "True peak of {}", true_peak);
+        assert!(readings.clipped());
+
+        meter.process_block(&[0.0; 64], &[0.0; 64], &readings);
+        assert!(readings.clipped(), "The clip indicator must latch");
+        readings.clear_clip();
+        assert!(!readings.clipped());
+    }
+}
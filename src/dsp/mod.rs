@@ -16,5 +16,12 @@
 
 /// This module contains Digital Signal Processing (DSP) components
 /// for the Open Headstage plugin.
+pub mod analyzer;
+pub mod bypass;
 pub mod convolution;
+pub mod limiter;
+pub mod linear_phase_eq;
+pub mod loudness;
+pub mod meter;
 pub mod parametric_eq;
+pub mod resampler;
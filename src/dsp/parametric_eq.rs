@@ -20,6 +20,38 @@ use num_complex::Complex;
 use serde::{Deserialize, Serialize};
 use strum_macros::EnumIter;
 
+// Number of log-spaced frequencies searched for the peak gain of the EQ curve
+const PEAK_SEARCH_POINTS: usize = 128;
+
+// Number of log-spaced frequencies the editor's curves are drawn from
+const RESPONSE_POINTS: usize = 256;
+
+// The 48 dB/oct slopes need the most second-order sections
+const MAX_SECTIONS: usize = 4;
+
+// Dynamic bands recompute their gain this often and glide to it in between
+const DYNAMIC_UPDATE_INTERVAL: usize = 32;
+
+const IDENTITY: Coefficients<f32> = Coefficients {
+    a1: 0.0,
+    a2: 0.0,
+    b0: 1.0,
+    b1: 0.0,
+    b2: 0.0,
+};
+
+// Section Qs of the fixed slopes. A Linkwitz-Riley filter is a Butterworth filter of half
+// the order applied twice.
+const BUTTERWORTH_12_QS: &[f32] = &[std::f32::consts::FRAC_1_SQRT_2];
+const BUTTERWORTH_24_QS: &[f32] = &[0.541_196_1, 1.306_563];
+const BUTTERWORTH_48_QS: &[f32] = &[0.509_795_6, 0.601_344_9, 0.899_976_2, 2.562_915_5];
+const LINKWITZ_RILEY_12_QS: &[f32] = &[0.5];
+const LINKWITZ_RILEY_24_QS: &[f32] = &[
+    std::f32::consts::FRAC_1_SQRT_2,
+    std::f32::consts::FRAC_1_SQRT_2,
+];
+const LINKWITZ_RILEY_48_QS: &[f32] = &[0.541_196_1, 1.306_563, 0.541_196_1, 1.306_563];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, EnumIter, Serialize, Deserialize, Default)]
 #[allow(dead_code)]
 pub enum FilterType {
@@ -32,51 +64,448 @@ pub enum FilterType {
     BandPass,
     Notch,
     AllPass,
+    #[name = "Low Shelf 6 dB/oct"]
+    LowShelf1,
+    #[name = "High Shelf 6 dB/oct"]
+    HighShelf1,
+    Tilt,
+    #[name = "Butterworth LP 12"]
+    ButterworthLowPass12,
+    #[name = "Butterworth LP 24"]
+    ButterworthLowPass24,
+    #[name = "Butterworth LP 48"]
+    ButterworthLowPass48,
+    #[name = "Butterworth HP 12"]
+    ButterworthHighPass12,
+    #[name = "Butterworth HP 24"]
+    ButterworthHighPass24,
+    #[name = "Butterworth HP 48"]
+    ButterworthHighPass48,
+    #[name = "Linkwitz-Riley LP 12"]
+    LinkwitzRileyLowPass12,
+    #[name = "Linkwitz-Riley LP 24"]
+    LinkwitzRileyLowPass24,
+    #[name = "Linkwitz-Riley LP 48"]
+    LinkwitzRileyLowPass48,
+    #[name = "Linkwitz-Riley HP 12"]
+    LinkwitzRileyHighPass12,
+    #[name = "Linkwitz-Riley HP 24"]
+    LinkwitzRileyHighPass24,
+    #[name = "Linkwitz-Riley HP 48"]
+    LinkwitzRileyHighPass48,
 }
 
-#[derive(Debug, Clone, Copy)]
+impl FilterType {
+    pub fn name(self) -> &'static str {
+        match self {
+            FilterType::Peak => "Peak",
+            FilterType::LowShelf => "Low Shelf",
+            FilterType::HighShelf => "High Shelf",
+            FilterType::LowPass => "Low Pass",
+            FilterType::HighPass => "High Pass",
+            FilterType::BandPass => "Band Pass",
+            FilterType::Notch => "Notch",
+            FilterType::AllPass => "All Pass",
+            FilterType::LowShelf1 => "Low Shelf 6 dB/oct",
+            FilterType::HighShelf1 => "High Shelf 6 dB/oct",
+            FilterType::Tilt => "Tilt",
+            FilterType::ButterworthLowPass12 => "Butterworth LP 12",
+            FilterType::ButterworthLowPass24 => "Butterworth LP 24",
+            FilterType::ButterworthLowPass48 => "Butterworth LP 48",
+            FilterType::ButterworthHighPass12 => "Butterworth HP 12",
+            FilterType::ButterworthHighPass24 => "Butterworth HP 24",
+            FilterType::ButterworthHighPass48 => "Butterworth HP 48",
+            FilterType::LinkwitzRileyLowPass12 => "Linkwitz-Riley LP 12",
+            FilterType::LinkwitzRileyLowPass24 => "Linkwitz-Riley LP 24",
+            FilterType::LinkwitzRileyLowPass48 => "Linkwitz-Riley LP 48",
+            FilterType::LinkwitzRileyHighPass12 => "Linkwitz-Riley HP 12",
+            FilterType::LinkwitzRileyHighPass24 => "Linkwitz-Riley HP 24",
+            FilterType::LinkwitzRileyHighPass48 => "Linkwitz-Riley HP 48",
+        }
+    }
+
+    /// Whether the band's gain changes the filter. Only these types can be dynamic.
+    pub fn has_gain(self) -> bool {
+        matches!(
+            self,
+            FilterType::Peak
+                | FilterType::LowShelf
+                | FilterType::HighShelf
+                | FilterType::LowShelf1
+                | FilterType::HighShelf1
+                | FilterType::Tilt
+        )
+    }
+
+    /// Whether the band's Q changes the filter. The fixed slopes and first-order types
+    /// ignore it.
+    pub fn has_q(self) -> bool {
+        self.slope_sections().is_none()
+            && !matches!(
+                self,
+                FilterType::LowShelf1 | FilterType::HighShelf1 | FilterType::Tilt
+            )
+    }
+
+    /// The section type and section Qs of the fixed-slope filters, which ignore the
+    /// band's Q.
+    fn slope_sections(self) -> Option<(FilterType, &'static [f32])> {
+        match self {
+            FilterType::ButterworthLowPass12 => Some((FilterType::LowPass, BUTTERWORTH_12_QS)),
+            FilterType::ButterworthLowPass24 => Some((FilterType::LowPass, BUTTERWORTH_24_QS)),
+            FilterType::ButterworthLowPass48 => Some((FilterType::LowPass, BUTTERWORTH_48_QS)),
+            FilterType::ButterworthHighPass12 => Some((FilterType::HighPass, BUTTERWORTH_12_QS)),
+            FilterType::ButterworthHighPass24 => Some((FilterType::HighPass, BUTTERWORTH_24_QS)),
+            FilterType::ButterworthHighPass48 => Some((FilterType::HighPass, BUTTERWORTH_48_QS)),
+            FilterType::LinkwitzRileyLowPass12 => Some((FilterType::LowPass, LINKWITZ_RILEY_12_QS)),
+            FilterType::LinkwitzRileyLowPass24 => Some((FilterType::LowPass, LINKWITZ_RILEY_24_QS)),
+            FilterType::LinkwitzRileyLowPass48 => Some((FilterType::LowPass, LINKWITZ_RILEY_48_QS)),
+            FilterType::LinkwitzRileyHighPass12 => {
+                Some((FilterType::HighPass, LINKWITZ_RILEY_12_QS))
+            }
+            FilterType::LinkwitzRileyHighPass24 => {
+                Some((FilterType::HighPass, LINKWITZ_RILEY_24_QS))
+            }
+            FilterType::LinkwitzRileyHighPass48 => {
+                Some((FilterType::HighPass, LINKWITZ_RILEY_48_QS))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// How the two EQ channels relate to the stereo signal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, EnumIter, Serialize, Deserialize, Default)]
+pub enum EqChannelMode {
+    /// Every band acts on both channels, regardless of its `BandChannel`.
+    #[default]
+    Linked,
+    /// The first channel is left, the second is right.
+    #[name = "Left/Right"]
+    LeftRight,
+    /// The signal is encoded to mid/side before the bands and decoded afterwards. The
+    /// first channel is mid, the second is side.
+    #[name = "Mid/Side"]
+    MidSide,
+}
+
+impl EqChannelMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            EqChannelMode::Linked => "Linked",
+            EqChannelMode::LeftRight => "Left/Right",
+            EqChannelMode::MidSide => "Mid/Side",
+        }
+    }
+}
+
+/// The channel(s) a band acts on. What the channels are depends on the `EqChannelMode`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, EnumIter, Serialize, Deserialize, Default)]
+pub enum BandChannel {
+    #[default]
+    Both,
+    First,
+    Second,
+}
+
+impl BandChannel {
+    pub fn label(self, mode: EqChannelMode) -> &'static str {
+        match (self, mode) {
+            (BandChannel::Both, _) => "Both",
+            (BandChannel::First, EqChannelMode::MidSide) => "Mid",
+            (BandChannel::Second, EqChannelMode::MidSide) => "Side",
+            (BandChannel::First, _) => "Left",
+            (BandChannel::Second, _) => "Right",
+        }
+    }
+}
+
+/// How the gain ahead of the EQ is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, EnumIter, Serialize, Deserialize, Default)]
+pub enum PreampMode {
+    /// Attenuate by the peak gain of the EQ curve, so it never boosts above 0 dB.
+    #[default]
+    Auto,
+    /// Use the preamp parameter, e.g. the value supplied with an AutoEQ profile.
+    Manual,
+}
+
+impl PreampMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            PreampMode::Auto => "Auto",
+            PreampMode::Manual => "Manual",
+        }
+    }
+}
+
+/// How a band's analog prototype is turned into biquad coefficients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, EnumIter, Serialize, Deserialize, Default)]
+pub enum BiquadDesign {
+    /// The RBJ cookbook bilinear transform. Peaks and shelves are squeezed ("cramped")
+    /// towards Nyquist, noticeably so above ~10 kHz at 44.1/48 kHz.
+    #[default]
+    Bilinear,
+    /// Vicanek's matched design: impulse-invariant poles and a numerator that matches
+    /// the analog magnitude at DC, Nyquist and the center frequency. Notch, all-pass and
+    /// first-order filters keep the bilinear design.
+    Matched,
+}
+
+impl BiquadDesign {
+    pub fn name(self) -> &'static str {
+        match self {
+            BiquadDesign::Bilinear => "Bilinear (RBJ)",
+            BiquadDesign::Matched => "Matched (Vicanek)",
+        }
+    }
+}
+
+/// Whether the EQ runs as the biquad cascade or as a linear-phase FIR rendered from the
+/// cascade's magnitude response (see `linear_phase_eq`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum, EnumIter, Serialize, Deserialize, Default)]
+pub enum EqPhaseMode {
+    #[default]
+    #[name = "Minimum Phase"]
+    MinimumPhase,
+    #[name = "Linear Phase"]
+    LinearPhase,
+}
+
+impl EqPhaseMode {
+    pub fn name(self) -> &'static str {
+        match self {
+            EqPhaseMode::MinimumPhase => "Minimum Phase",
+            EqPhaseMode::LinearPhase => "Linear Phase",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct BandConfig {
     pub filter_type: FilterType,
     pub center_freq: f32,
     pub q: f32,
     pub gain_db: f32,
     pub enabled: bool,
+    pub channel: BandChannel,
+    pub dynamics: Option<DynamicConfig>,
 }
 
-pub struct BiquadFilter {
-    filter: DirectForm2Transposed<f32>,
-    coeffs: Coefficients<f32>,
-    pub enabled: bool,
+/// The side-chain settings of a dynamic band. A dynamic band rests at 0 dB and moves
+/// towards its gain, which becomes its range, as the level around its frequency rises
+/// above the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DynamicConfig {
+    pub threshold_db: f32,
+    pub ratio: f32,
+    pub attack_ms: f32,
+    pub release_ms: f32,
 }
 
-impl Clone for BiquadFilter {
-    fn clone(&self) -> Self {
+impl Default for DynamicConfig {
+    fn default() -> Self {
         Self {
-            filter: self.filter,
-            coeffs: self.coeffs,
-            enabled: self.enabled,
+            threshold_db: -20.0,
+            ratio: 2.0,
+            attack_ms: 5.0,
+            release_ms: 100.0,
         }
     }
 }
 
+/// The continuous-time transfer function a filter type approximates, as second-order
+/// polynomial coefficients `[s^0, s^1, s^2]` in the normalized frequency `s / w0`.
+#[derive(Debug, Clone, Copy)]
+struct AnalogPrototype {
+    numerator: [f64; 3],
+    denominator: [f64; 3],
+}
+
+impl AnalogPrototype {
+    /// The RBJ cookbook prototypes, which the bilinear designs are derived from.
+    fn new(filter_type: FilterType, q: f32, gain_db: f32) -> Self {
+        let q = q as f64;
+        let a = 10f64.powf(gain_db as f64 / 40.0);
+        let (numerator, denominator) = match filter_type {
+            FilterType::Peak => ([1.0, a / q, 1.0], [1.0, 1.0 / (a * q), 1.0]),
+            FilterType::LowShelf => ([a * a, a * a.sqrt() / q, a], [1.0, a.sqrt() / q, a]),
+            FilterType::HighShelf => ([a, a * a.sqrt() / q, a * a], [a, a.sqrt() / q, 1.0]),
+            FilterType::LowPass => ([1.0, 0.0, 0.0], [1.0, 1.0 / q, 1.0]),
+            FilterType::HighPass => ([0.0, 0.0, 1.0], [1.0, 1.0 / q, 1.0]),
+            FilterType::BandPass => ([0.0, 1.0 / q, 0.0], [1.0, 1.0 / q, 1.0]),
+            FilterType::Notch => ([1.0, 0.0, 1.0], [1.0, 1.0 / q, 1.0]),
+            FilterType::AllPass => ([1.0, -1.0 / q, 1.0], [1.0, 1.0 / q, 1.0]),
+            // First-order, so `s^2` drops out
+            FilterType::LowShelf1 => ([a, 1.0, 0.0], [1.0 / a, 1.0, 0.0]),
+            FilterType::HighShelf1 => ([a, a * a, 0.0], [a, 1.0, 0.0]),
+            FilterType::Tilt => ([1.0, a, 0.0], [a, 1.0, 0.0]),
+            _ => unreachable!("{:?} is a cascade of sections", filter_type),
+        };
+        Self {
+            numerator,
+            denominator,
+        }
+    }
+
+    /// `|H(j * omega)|^2`, with `omega` relative to the center frequency.
+    fn magnitude_squared(&self, omega: f64) -> f64 {
+        let evaluate =
+            |[c0, c1, c2]: [f64; 3]| (c0 - c2 * omega * omega).powi(2) + (c1 * omega).powi(2);
+        evaluate(self.numerator) / evaluate(self.denominator)
+    }
+
+    /// Vicanek, "Matched Second Order Digital Filters" (2016), generalized to any
+    /// prototype with a complex or real pole pair.
+    fn matched_coefficients(&self, sample_rate: f32, center_freq: f32) -> Coefficients<f32> {
+        use std::f64::consts::PI;
+
+        let w0 = 2.0 * PI * center_freq as f64 / sample_rate as f64;
+        let [d0, d1, d2] = self.denominator;
+
+        // Impulse invariance maps the analog poles exactly
+        let pole_freq = w0 * (d0 / d2).sqrt();
+        let zeta = d1 / (2.0 * (d0 * d2).sqrt());
+        let decay = (-zeta * pole_freq).exp();
+        let a1 = if zeta <= 1.0 {
+            -2.0 * decay * (pole_freq * (1.0 - zeta * zeta).sqrt()).cos()
+        } else {
+            -2.0 * decay * (pole_freq * (zeta * zeta - 1.0).sqrt()).cosh()
+        };
+        let a2 = decay * decay;
+
+        // Squared magnitudes are linear in these basis functions of `sin^2(w / 2)`
+        let basis = |w: f64| {
+            let s = (w / 2.0).sin().powi(2);
+            [1.0 - s, s, 4.0 * (1.0 - s) * s]
+        };
+        let big_a = [(1.0 + a1 + a2).powi(2), (1.0 - a1 + a2).powi(2), -4.0 * a2];
+        let denominator_squared =
+            |phi: [f64; 3]| big_a[0] * phi[0] + big_a[1] * phi[1] + big_a[2] * phi[2];
+
+        // Matching at the center frequency is ill-conditioned right at Nyquist
+        let w_match = w0.min(0.95 * PI);
+        let phi = basis(w_match);
+        let target = self.magnitude_squared(w_match / w0) * denominator_squared(phi);
+
+        // A high-pass needs its double zero at DC, which leaves one degree of freedom
+        if self.numerator[0] == 0.0 && self.numerator[1] == 0.0 {
+            let b0 = target.sqrt() / (4.0 * phi[1]);
+            return Coefficients {
+                a1: a1 as f32,
+                a2: a2 as f32,
+                b0: b0 as f32,
+                b1: (-2.0 * b0) as f32,
+                b2: b0 as f32,
+            };
+        }
+
+        let big_b0 = self.magnitude_squared(0.0) * big_a[0];
+        let big_b1 = self.magnitude_squared(PI / w0) * big_a[1];
+        let big_b2 = (target - big_b0 * phi[0] - big_b1 * phi[1]) / phi[2];
+
+        let (root_b0, root_b1) = (big_b0.sqrt(), big_b1.sqrt());
+        let w = 0.5 * (root_b0 + root_b1);
+        let b0 = 0.5 * (w + (w * w + big_b2).max(0.0).sqrt());
+        let b1 = 0.5 * (root_b0 - root_b1);
+        let b2 = if b0 > 0.0 { -big_b2 / (4.0 * b0) } else { 0.0 };
+
+        Coefficients {
+            a1: a1 as f32,
+            a2: a2 as f32,
+            b0: b0 as f32,
+            b1: b1 as f32,
+            b2: b2 as f32,
+        }
+    }
+}
+
+/// One second-order section of a `BiquadFilter`.
+#[derive(Clone, Copy)]
+struct Section {
+    filter: DirectForm2Transposed<f32>,
+    // The target coefficients. While gliding, `filter` runs with `running` instead.
+    coeffs: Coefficients<f32>,
+    running: Coefficients<f32>,
+    glide_step: Coefficients<f32>,
+}
+
+impl Section {
+    fn set_coeffs(&mut self, coeffs: Coefficients<f32>) {
+        self.coeffs = coeffs;
+        self.running = coeffs;
+        self.filter.update_coefficients(coeffs);
+    }
+
+    fn start_glide(&mut self, coeffs: Coefficients<f32>, num_samples: usize) {
+        let steps = num_samples as f32;
+        self.coeffs = coeffs;
+        self.glide_step = Coefficients {
+            a1: (coeffs.a1 - self.running.a1) / steps,
+            a2: (coeffs.a2 - self.running.a2) / steps,
+            b0: (coeffs.b0 - self.running.b0) / steps,
+            b1: (coeffs.b1 - self.running.b1) / steps,
+            b2: (coeffs.b2 - self.running.b2) / steps,
+        };
+    }
+
+    #[inline]
+    fn advance_glide(&mut self, finished: bool) {
+        self.running = if finished {
+            self.coeffs
+        } else {
+            Coefficients {
+                a1: self.running.a1 + self.glide_step.a1,
+                a2: self.running.a2 + self.glide_step.a2,
+                b0: self.running.b0 + self.glide_step.b0,
+                b1: self.running.b1 + self.glide_step.b1,
+                b2: self.running.b2 + self.glide_step.b2,
+            }
+        };
+        self.filter.update_coefficients(self.running);
+    }
+}
+
+/// A single EQ band: one biquad, or a cascade of them for the steep slopes.
+#[derive(Clone)]
+pub struct BiquadFilter {
+    // Sections past `active_sections` hold the identity filter and have no state
+    sections: [Section; MAX_SECTIONS],
+    num_sections: usize,
+    // More than `num_sections` while gliding away from a longer cascade
+    active_sections: usize,
+    glide_remaining: usize,
+    design: BiquadDesign,
+    pub enabled: bool,
+}
+
 impl BiquadFilter {
-    pub fn new(initial_sample_rate: f32) -> Self {
-        let coeffs = Coefficients::<f32>::from_params(
-            Type::PeakingEQ(0.0),
-            initial_sample_rate.hz(),
-            20.0.hz(),
-            0.707,
-        )
-        .unwrap();
+    pub fn new(_initial_sample_rate: f32) -> Self {
+        let section = Section {
+            filter: DirectForm2Transposed::<f32>::new(IDENTITY),
+            coeffs: IDENTITY,
+            running: IDENTITY,
+            glide_step: IDENTITY,
+        };
         Self {
-            filter: DirectForm2Transposed::<f32>::new(coeffs),
-            coeffs,
+            sections: [section; MAX_SECTIONS],
+            num_sections: 1,
+            active_sections: 1,
+            glide_remaining: 0,
+            design: BiquadDesign::default(),
             enabled: false,
         }
     }
 
+    /// Takes effect on the next `update_coeffs` call.
+    pub fn set_design(&mut self, design: BiquadDesign) {
+        self.design = design;
+    }
+
     pub fn reset_state(&mut self) {
-        self.filter.reset_state();
+        for section in self.sections.iter_mut() {
+            section.filter.reset_state();
+        }
     }
 
     pub fn set_enabled(&mut self, enabled: bool) {
@@ -91,6 +520,90 @@ impl BiquadFilter {
         q: f32,
         gain_db: f32,
     ) {
+        let (coeffs, num_sections) =
+            self.design_sections(filter_type, sample_rate, center_freq, q, gain_db);
+        for (section, coeffs) in self.sections.iter_mut().zip(coeffs) {
+            section.set_coeffs(coeffs);
+        }
+        for section in self.sections[num_sections..].iter_mut() {
+            section.filter.reset_state();
+        }
+        self.num_sections = num_sections;
+        self.active_sections = num_sections;
+        self.glide_remaining = 0;
+    }
+
+    /// Like `update_coeffs`, but moves the running coefficients to the new ones linearly
+    /// over the next `num_samples` samples. Both endpoints lie inside the biquad
+    /// stability triangle, which is convex, so every intermediate filter is stable too.
+    /// Sections that are added or removed glide from or to the identity filter.
+    pub fn glide_coeffs(
+        &mut self,
+        filter_type: FilterType,
+        sample_rate: f32,
+        center_freq: f32,
+        q: f32,
+        gain_db: f32,
+        num_samples: usize,
+    ) {
+        if !self.enabled || num_samples == 0 {
+            self.update_coeffs(filter_type, sample_rate, center_freq, q, gain_db);
+            return;
+        }
+
+        let (coeffs, num_sections) =
+            self.design_sections(filter_type, sample_rate, center_freq, q, gain_db);
+        for (section, coeffs) in self.sections.iter_mut().zip(coeffs) {
+            section.start_glide(coeffs, num_samples);
+        }
+        self.active_sections = self.active_sections.max(num_sections);
+        self.num_sections = num_sections;
+        self.glide_remaining = num_samples;
+    }
+
+    /// The coefficients of each section, padded with the identity filter.
+    fn design_sections(
+        &self,
+        filter_type: FilterType,
+        sample_rate: f32,
+        center_freq: f32,
+        q: f32,
+        gain_db: f32,
+    ) -> ([Coefficients<f32>; MAX_SECTIONS], usize) {
+        let mut sections = [IDENTITY; MAX_SECTIONS];
+        if let Some((section_type, section_qs)) = filter_type.slope_sections() {
+            for (section, &section_q) in sections.iter_mut().zip(section_qs) {
+                *section =
+                    self.design_coeffs(section_type, sample_rate, center_freq, section_q, 0.0);
+            }
+            return (sections, section_qs.len());
+        }
+
+        sections[0] = match filter_type {
+            FilterType::LowShelf1 | FilterType::HighShelf1 | FilterType::Tilt => {
+                first_order_coeffs(filter_type, sample_rate, center_freq, gain_db)
+            }
+            _ => self.design_coeffs(filter_type, sample_rate, center_freq, q, gain_db),
+        };
+        (sections, 1)
+    }
+
+    /// Designs one of the second-order filter types.
+    fn design_coeffs(
+        &self,
+        filter_type: FilterType,
+        sample_rate: f32,
+        center_freq: f32,
+        q: f32,
+        gain_db: f32,
+    ) -> Coefficients<f32> {
+        if self.design == BiquadDesign::Matched
+            && !matches!(filter_type, FilterType::Notch | FilterType::AllPass)
+        {
+            return AnalogPrototype::new(filter_type, q, gain_db)
+                .matched_coefficients(sample_rate, center_freq);
+        }
+
         let filter_type_biquad = match filter_type {
             FilterType::Peak => Type::PeakingEQ(gain_db),
             FilterType::LowShelf => Type::LowShelf(gain_db),
@@ -100,17 +613,46 @@ impl BiquadFilter {
             FilterType::BandPass => Type::BandPass,
             FilterType::Notch => Type::Notch,
             FilterType::AllPass => Type::AllPass,
+            _ => unreachable!("{:?} is not a second-order filter", filter_type),
         };
 
-        let coeffs = Coefficients::<f32>::from_params(
-            filter_type_biquad,
-            sample_rate.hz(),
-            center_freq.hz(),
-            q,
-        )
-        .unwrap();
-        self.filter.update_coefficients(coeffs);
-        self.coeffs = coeffs;
+        Coefficients::<f32>::from_params(filter_type_biquad, sample_rate.hz(), center_freq.hz(), q)
+            .unwrap()
+    }
+
+    /// Evaluates the filter's transfer function at `freq` Hz, ignoring the enabled flag.
+    pub fn frequency_response(&self, freq: f32, sample_rate: f32) -> Complex<f32> {
+        let omega = 2.0 * std::f32::consts::PI * freq / sample_rate;
+        self.response_at(Complex::from_polar(1.0, -omega))
+    }
+
+    /// Like `frequency_response`, for a precomputed `z^-1 = e^(-j omega)`.
+    fn response_at(&self, z_inv: Complex<f32>) -> Complex<f32> {
+        self.sections[..self.num_sections]
+            .iter()
+            .fold(Complex::new(1.0, 0.0), |response, section| {
+                response * section_response(&section.coeffs, z_inv)
+            })
+    }
+
+    /// The filter's group delay at `freq` Hz in samples, ignoring the enabled flag. Each
+    /// polynomial `sum(p_k * z^-k)` of a section delays by `Re(sum(k * p_k * z^-k) /
+    /// sum(p_k * z^-k))` samples.
+    pub fn group_delay(&self, freq: f32, sample_rate: f32) -> f32 {
+        let omega = 2.0 * std::f32::consts::PI * freq / sample_rate;
+        let z_inv = Complex::from_polar(1.0, -omega);
+        let z_inv2 = z_inv * z_inv;
+        let polynomial_delay = |p0: f32, p1: f32, p2: f32| {
+            ((p1 * z_inv + 2.0 * p2 * z_inv2) / (p0 + p1 * z_inv + p2 * z_inv2)).re
+        };
+        self.sections[..self.num_sections]
+            .iter()
+            .map(|section| {
+                let coeffs = section.coeffs;
+                polynomial_delay(coeffs.b0, coeffs.b1, coeffs.b2)
+                    - polynomial_delay(1.0, coeffs.a1, coeffs.a2)
+            })
+            .sum()
     }
 
     #[inline]
@@ -118,14 +660,271 @@ impl BiquadFilter {
         if !self.enabled {
             return input_sample;
         }
-        self.filter.run(input_sample)
+        if self.glide_remaining > 0 {
+            self.glide_remaining -= 1;
+            let finished = self.glide_remaining == 0;
+            for section in self.sections[..self.active_sections].iter_mut() {
+                section.advance_glide(finished);
+            }
+            if finished {
+                for section in self.sections[self.num_sections..self.active_sections].iter_mut() {
+                    section.filter.reset_state();
+                }
+                self.active_sections = self.num_sections;
+            }
+        }
+        self.sections[..self.active_sections]
+            .iter_mut()
+            .fold(input_sample, |sample, section| section.filter.run(sample))
+    }
+}
+
+/// First-order shelves and the tilt, via the bilinear transform prewarped to the center
+/// frequency. The shelves reach half their gain at the center frequency; the tilt goes
+/// from `-gain_db / 2` at DC to `+gain_db / 2` at Nyquist.
+fn first_order_coeffs(
+    filter_type: FilterType,
+    sample_rate: f32,
+    center_freq: f32,
+    gain_db: f32,
+) -> Coefficients<f32> {
+    let k = (std::f32::consts::PI * center_freq / sample_rate).tan();
+    let root_gain = 10f32.powf(gain_db / 40.0);
+    // H(s) = (n1 * s + n0) / (s + d0), with s normalized to the center frequency
+    let (n1, n0, d0) = match filter_type {
+        FilterType::LowShelf1 => (1.0, root_gain * k, k / root_gain),
+        FilterType::HighShelf1 => (root_gain * root_gain, root_gain * k, root_gain * k),
+        FilterType::Tilt => (root_gain, k, root_gain * k),
+        _ => unreachable!("{:?} is not a first-order filter", filter_type),
+    };
+    let norm = 1.0 + d0;
+    Coefficients {
+        a1: (d0 - 1.0) / norm,
+        a2: 0.0,
+        b0: (n0 + n1) / norm,
+        b1: (n0 - n1) / norm,
+        b2: 0.0,
+    }
+}
+
+/// The transfer function of one second-order section at `z^-1 = e^(-j omega)`.
+fn section_response(coeffs: &Coefficients<f32>, z_inv: Complex<f32>) -> Complex<f32> {
+    let z_inv2 = z_inv * z_inv;
+    let numerator = coeffs.b0 + coeffs.b1 * z_inv + coeffs.b2 * z_inv2;
+    let denominator = 1.0 + coeffs.a1 * z_inv + coeffs.a2 * z_inv2;
+    numerator / denominator
+}
+
+/// `count` frequencies evenly spaced on a log scale from 20 Hz to 20 kHz, or to just
+/// below Nyquist at low sample rates.
+pub fn log_spaced_frequencies(count: usize, sample_rate: f32) -> Vec<f32> {
+    (0..count)
+        .map(|i| log_spaced_frequency(i, count, sample_rate))
+        .collect()
+}
+
+fn log_spaced_frequency(i: usize, count: usize, sample_rate: f32) -> f32 {
+    let min_freq: f32 = 20.0;
+    let max_freq = 20000.0f32.min(sample_rate * 0.49);
+    min_freq * (max_freq / min_freq).powf(i as f32 / (count - 1) as f32)
+}
+
+/// The response of both EQ channels, in the order `process_block` sees them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EqResponse {
+    pub frequencies: Vec<f32>,
+    pub magnitude_db: [Vec<f32>; 2],
+    // Wrapped to +-180 degrees
+    pub phase_deg: [Vec<f32>; 2],
+    pub group_delay_ms: [Vec<f32>; 2],
+    // Each band's magnitude on the first channel it acts on; empty for disabled bands
+    pub band_magnitude_db: Vec<Vec<f32>>,
+}
+
+/// A set of EQ settings whose response the editor wants to draw. It carries its own
+/// bands, so the editor can show settings it has not applied yet, and it is computed
+/// as a background task without touching the audio thread's EQ.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EqResponseRequest {
+    pub sample_rate: f32,
+    pub channel_mode: EqChannelMode,
+    pub design: BiquadDesign,
+    pub bands: Vec<BandConfig>,
+}
+
+impl EqResponseRequest {
+    /// Dynamic bands are drawn at rest.
+    pub fn compute(&self) -> EqResponse {
+        let eq = StereoParametricEQ::with_bands(
+            self.sample_rate,
+            self.channel_mode,
+            self.design,
+            &self.bands,
+        );
+        eq.calculate_response(
+            self.sample_rate,
+            &log_spaced_frequencies(RESPONSE_POINTS, self.sample_rate),
+        )
+    }
+}
+
+/// The side-chain of a dynamic band: a detector filter per channel that isolates the
+/// band's frequency range, followed by a peak envelope across both channels.
+#[derive(Clone)]
+struct DynamicBand {
+    config: DynamicConfig,
+    detectors: [BiquadFilter; 2],
+    attack_coeff: f32,
+    release_coeff: f32,
+    envelope: f32,
+    // The gain the band's filters were last set to
+    gain_db: f32,
+}
+
+impl DynamicBand {
+    fn new(sample_rate: f32) -> Self {
+        Self {
+            config: DynamicConfig::default(),
+            detectors: [
+                BiquadFilter::new(sample_rate),
+                BiquadFilter::new(sample_rate),
+            ],
+            attack_coeff: 1.0,
+            release_coeff: 1.0,
+            envelope: 0.0,
+            gain_db: 0.0,
+        }
+    }
+
+    /// Tunes the detectors to the band. Shelves listen to everything past their corner,
+    /// the other types to the band around their center frequency.
+    fn configure(
+        &mut self,
+        band: &BandConfig,
+        config: DynamicConfig,
+        sample_rate: f32,
+        enabled: [bool; 2],
+    ) {
+        let (detector_type, detector_q) = match band.filter_type {
+            FilterType::LowShelf | FilterType::LowShelf1 => {
+                (FilterType::LowPass, std::f32::consts::FRAC_1_SQRT_2)
+            }
+            FilterType::HighShelf | FilterType::HighShelf1 => {
+                (FilterType::HighPass, std::f32::consts::FRAC_1_SQRT_2)
+            }
+            _ => (FilterType::BandPass, band.q),
+        };
+        for (detector, enabled) in self.detectors.iter_mut().zip(enabled) {
+            detector.update_coeffs(
+                detector_type,
+                sample_rate,
+                band.center_freq,
+                detector_q,
+                0.0,
+            );
+            if !enabled {
+                detector.reset_state();
+            }
+            detector.set_enabled(enabled);
+        }
+
+        self.config = config;
+        self.attack_coeff = 1.0 - (-1.0 / (config.attack_ms / 1000.0 * sample_rate)).exp();
+        self.release_coeff = 1.0 - (-1.0 / (config.release_ms / 1000.0 * sample_rate)).exp();
+        // The range may have shrunk
+        self.gain_db = self
+            .gain_db
+            .clamp(band.gain_db.min(0.0), band.gain_db.max(0.0));
+    }
+
+    /// Feeds one sample of each channel, as it enters the band, to the side-chain.
+    #[inline]
+    fn detect(&mut self, first: f32, second: f32) {
+        let level = self
+            .detectors
+            .iter_mut()
+            .zip([first, second])
+            .filter(|(detector, _)| detector.enabled)
+            .map(|(detector, sample)| detector.process_sample(sample).abs())
+            .fold(0.0f32, f32::max);
+        let coeff = if level > self.envelope {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.envelope += coeff * (level - self.envelope);
+    }
+
+    /// The band gain for the current envelope. The level above the threshold is
+    /// reduced by the ratio, up to the band's range.
+    fn target_gain_db(&self, range_db: f32) -> f32 {
+        let level_db = 20.0 * self.envelope.max(1e-9).log10();
+        let over_db = (level_db - self.config.threshold_db).max(0.0);
+        (over_db * (1.0 - 1.0 / self.config.ratio))
+            .min(range_db.abs())
+            .copysign(range_db)
+    }
+
+    fn reset(&mut self) {
+        for detector in self.detectors.iter_mut() {
+            detector.reset_state();
+        }
+        self.envelope = 0.0;
+    }
+}
+
+/// Scratch space for `StereoParametricEQ::peak_gain_db`, allocated up front so the
+/// search can run on the audio thread.
+struct PeakSearch {
+    sample_rate: f32,
+    // `e^(-j omega)` at each of the `PEAK_SEARCH_POINTS` search frequencies
+    z_inv: Vec<Complex<f32>>,
+    // Both channels' complex response at the search frequencies
+    response: [Vec<Complex<f32>>; 2],
+    // Each dynamic band's sections and section count at its full range
+    full_range: Vec<Option<([Coefficients<f32>; MAX_SECTIONS], usize)>>,
+}
+
+impl PeakSearch {
+    fn new(num_bands: usize) -> Self {
+        let unity = vec![Complex::new(1.0, 0.0); PEAK_SEARCH_POINTS];
+        Self {
+            sample_rate: 0.0,
+            z_inv: unity.clone(),
+            response: [unity.clone(), unity],
+            full_range: vec![None; num_bands],
+        }
+    }
+
+    fn set_sample_rate(&mut self, sample_rate: f32) {
+        if sample_rate == self.sample_rate {
+            return;
+        }
+        self.sample_rate = sample_rate;
+        for (i, z_inv) in self.z_inv.iter_mut().enumerate() {
+            let freq = log_spaced_frequency(i, PEAK_SEARCH_POINTS, sample_rate);
+            *z_inv = Complex::from_polar(1.0, -2.0 * std::f32::consts::PI * freq / sample_rate);
+        }
     }
 }
 
 pub struct StereoParametricEQ {
+    // In mid/side mode these process the mid and side signals
     bands_left: Vec<BiquadFilter>,
     bands_right: Vec<BiquadFilter>,
     num_bands: usize,
+    channel_mode: EqChannelMode,
+    design: BiquadDesign,
+    sample_rate: f32,
+    // The configuration each band's coefficients were last computed for. Cleared when
+    // the coefficients depend on something else that changed.
+    band_configs: Vec<Option<BandConfig>>,
+    dynamic_bands: Vec<Option<DynamicBand>>,
+    // Invalidated whenever a band or the channel mode changes
+    peak_gain_db: f32,
+    peak_gain_stale: bool,
+    peak_search: PeakSearch,
+    revision: u64,
 }
 
 impl StereoParametricEQ {
@@ -138,43 +937,218 @@ impl StereoParametricEQ {
                 .map(|_| BiquadFilter::new(initial_sample_rate))
                 .collect(),
             num_bands,
+            channel_mode: EqChannelMode::default(),
+            design: BiquadDesign::default(),
+            sample_rate: initial_sample_rate,
+            band_configs: vec![None; num_bands],
+            dynamic_bands: vec![None; num_bands],
+            peak_gain_db: 0.0,
+            peak_gain_stale: true,
+            peak_search: PeakSearch::new(num_bands),
+            revision: 0,
         }
     }
 
-    pub fn update_band_coeffs(&mut self, band_idx: usize, sample_rate: f32, config: &BandConfig) {
-        if band_idx < self.num_bands {
-            self.bands_left[band_idx].update_coeffs(
-                config.filter_type,
-                sample_rate,
-                config.center_freq,
-                config.q,
-                config.gain_db,
-            );
-            self.bands_left[band_idx].set_enabled(config.enabled);
+    /// An EQ with its coefficients set up for `bands`, for analysing their response.
+    pub fn with_bands(
+        sample_rate: f32,
+        channel_mode: EqChannelMode,
+        design: BiquadDesign,
+        bands: &[BandConfig],
+    ) -> Self {
+        let mut eq = Self::new(bands.len(), sample_rate);
+        eq.set_channel_mode(channel_mode);
+        eq.set_design(design);
+        for (i, band) in bands.iter().enumerate() {
+            eq.update_band_coeffs(i, sample_rate, band, 0);
+        }
+        eq
+    }
 
-            self.bands_right[band_idx].update_coeffs(
+    /// Switching modes changes what the filters' internal state represents, so the
+    /// state is cleared whenever the mode actually changes.
+    pub fn set_channel_mode(&mut self, mode: EqChannelMode) {
+        if mode != self.channel_mode {
+            self.channel_mode = mode;
+            self.invalidate_response();
+            // Which filters are enabled depends on the mode
+            self.band_configs.fill(None);
+            self.reset_all_bands_state();
+        }
+    }
+
+    pub fn channel_mode(&self) -> EqChannelMode {
+        self.channel_mode
+    }
+
+    pub fn design(&self) -> BiquadDesign {
+        self.design
+    }
+
+    /// The configurations the bands were last updated with.
+    pub fn band_configs(&self) -> impl Iterator<Item = &BandConfig> {
+        self.band_configs.iter().flatten()
+    }
+
+    /// Changes whenever the EQ's frequency response may have changed.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn invalidate_response(&mut self) {
+        self.peak_gain_stale = true;
+        self.revision = self.revision.wrapping_add(1);
+    }
+
+    /// Applies to all bands from their next coefficient update.
+    pub fn set_design(&mut self, design: BiquadDesign) {
+        if design != self.design {
+            self.design = design;
+            self.invalidate_response();
+            self.band_configs.fill(None);
+            for band in self
+                .bands_left
+                .iter_mut()
+                .chain(self.bands_right.iter_mut())
+            {
+                band.set_design(design);
+            }
+        }
+    }
+
+    /// Recomputes a band's coefficients if its configuration changed since the last call.
+    /// Enabled filters glide to the new coefficients over `glide_samples` samples, which
+    /// should be the length of the next block; pass 0 to switch immediately. Dynamic bands
+    /// keep their current gain, which `process_block` then moves within the new range.
+    pub fn update_band_coeffs(
+        &mut self,
+        band_idx: usize,
+        sample_rate: f32,
+        config: &BandConfig,
+        glide_samples: usize,
+    ) {
+        if band_idx >= self.num_bands {
+            return;
+        }
+        if sample_rate != self.sample_rate {
+            self.sample_rate = sample_rate;
+            self.band_configs.fill(None);
+        }
+        if self.band_configs[band_idx] == Some(*config) {
+            return;
+        }
+        self.band_configs[band_idx] = Some(*config);
+        self.invalidate_response();
+
+        let (first, second) = match (self.channel_mode, config.channel) {
+            (EqChannelMode::Linked, _) | (_, BandChannel::Both) => (true, true),
+            (_, BandChannel::First) => (true, false),
+            (_, BandChannel::Second) => (false, true),
+        };
+        let enabled = [config.enabled && first, config.enabled && second];
+        let gain_db = match config.dynamics.filter(|_| config.filter_type.has_gain()) {
+            Some(dynamics) => {
+                let dynamic = self.dynamic_bands[band_idx]
+                    .get_or_insert_with(|| DynamicBand::new(sample_rate));
+                dynamic.configure(config, dynamics, sample_rate, enabled);
+                dynamic.gain_db
+            }
+            None => {
+                self.dynamic_bands[band_idx] = None;
+                config.gain_db
+            }
+        };
+        for (band, enabled) in [
+            (&mut self.bands_left[band_idx], enabled[0]),
+            (&mut self.bands_right[band_idx], enabled[1]),
+        ] {
+            // Only filters that stay enabled glide; `glide_coeffs` jumps for disabled
+            // ones, which covers bands being switched on as well as off
+            if !enabled {
+                band.set_enabled(false);
+            }
+            band.glide_coeffs(
                 config.filter_type,
                 sample_rate,
                 config.center_freq,
                 config.q,
-                config.gain_db,
+                gain_db,
+                glide_samples,
             );
-            self.bands_right[band_idx].set_enabled(config.enabled);
+            band.set_enabled(enabled);
         }
     }
 
     pub fn process_block(&mut self, input_left: &mut [f32], input_right: &mut [f32]) {
-        for i in 0..input_left.len() {
-            let mut sample_l = input_left[i];
-            let mut sample_r = input_right[i];
+        self.process(input_left, input_right, false);
+    }
+
+    /// Runs only the dynamic bands. The linear-phase EQ renders them at rest, so they
+    /// still need to run as biquads on top of it.
+    pub fn process_dynamic_bands(&mut self, input_left: &mut [f32], input_right: &mut [f32]) {
+        if self.dynamic_bands.iter().any(Option::is_some) {
+            self.process(input_left, input_right, true);
+        }
+    }
 
-            for j in 0..self.num_bands {
-                sample_l = self.bands_left[j].process_sample(sample_l);
-                sample_r = self.bands_right[j].process_sample(sample_r);
+    fn process(&mut self, input_left: &mut [f32], input_right: &mut [f32], dynamic_only: bool) {
+        let mid_side = self.channel_mode == EqChannelMode::MidSide;
+        for (chunk_left, chunk_right) in input_left
+            .chunks_mut(DYNAMIC_UPDATE_INTERVAL)
+            .zip(input_right.chunks_mut(DYNAMIC_UPDATE_INTERVAL))
+        {
+            for (left, right) in chunk_left.iter_mut().zip(chunk_right.iter_mut()) {
+                let (mut sample_l, mut sample_r) = if mid_side {
+                    ((*left + *right) * 0.5, (*left - *right) * 0.5)
+                } else {
+                    (*left, *right)
+                };
+
+                for j in 0..self.num_bands {
+                    if let Some(dynamic) = &mut self.dynamic_bands[j] {
+                        dynamic.detect(sample_l, sample_r);
+                    } else if dynamic_only {
+                        continue;
+                    }
+                    sample_l = self.bands_left[j].process_sample(sample_l);
+                    sample_r = self.bands_right[j].process_sample(sample_r);
+                }
+
+                if mid_side {
+                    *left = sample_l + sample_r;
+                    *right = sample_l - sample_r;
+                } else {
+                    *left = sample_l;
+                    *right = sample_r;
+                }
             }
+            self.update_dynamic_gains();
+        }
+    }
 
-            input_left[i] = sample_l;
-            input_right[i] = sample_r;
+    /// Moves the dynamic bands towards the gain their envelopes call for. This does not
+    /// count as a change of the EQ's response, as the gain stays within the range that
+    /// `peak_gain_db` already accounts for.
+    fn update_dynamic_gains(&mut self) {
+        for (j, dynamic) in self.dynamic_bands.iter_mut().enumerate() {
+            let (Some(dynamic), Some(config)) = (dynamic, self.band_configs[j]) else {
+                continue;
+            };
+            let gain_db = dynamic.target_gain_db(config.gain_db);
+            if gain_db == dynamic.gain_db {
+                continue;
+            }
+            dynamic.gain_db = gain_db;
+            for band in [&mut self.bands_left[j], &mut self.bands_right[j]] {
+                band.glide_coeffs(
+                    config.filter_type,
+                    self.sample_rate,
+                    config.center_freq,
+                    config.q,
+                    gain_db,
+                    DYNAMIC_UPDATE_INTERVAL,
+                );
+            }
         }
     }
 
@@ -185,33 +1159,168 @@ impl StereoParametricEQ {
         for band in self.bands_right.iter_mut() {
             band.reset_state();
         }
+        for dynamic in self.dynamic_bands.iter_mut().flatten() {
+            dynamic.reset();
+        }
     }
 
+    /// The magnitude response of the first channel (left, or mid in mid/side mode).
     #[allow(dead_code)]
     pub fn calculate_frequency_response(&self, sample_rate: f32, frequencies: &[f32]) -> Vec<f32> {
-        let mut response = vec![1.0; frequencies.len()];
-        for (i, &freq) in frequencies.iter().enumerate() {
-            let mut band_response = Complex::new(1.0, 0.0);
-            for band in &self.bands_left {
-                if band.enabled {
-                    let coeffs = band.coeffs;
-                    let omega = 2.0 * std::f32::consts::PI * freq / sample_rate;
-                    let z = Complex::from_polar(1.0, -omega);
-                    let numerator = coeffs.b0 + coeffs.b1 * z.powi(-1) + coeffs.b2 * z.powi(-2);
-                    let denominator = 1.0 + coeffs.a1 * z.powi(-1) + coeffs.a2 * z.powi(-2);
-                    let r = numerator / denominator;
-                    band_response *= r;
-                }
+        channel_frequency_response(&self.bands_left, sample_rate, frequencies)
+    }
+
+    /// The magnitude responses of both channels, in the same order as `process_block`
+    /// sees them.
+    pub fn calculate_channel_frequency_responses(
+        &self,
+        sample_rate: f32,
+        frequencies: &[f32],
+    ) -> [Vec<f32>; 2] {
+        [
+            channel_frequency_response(&self.bands_left, sample_rate, frequencies),
+            channel_frequency_response(&self.bands_right, sample_rate, frequencies),
+        ]
+    }
+
+    /// The magnitude, phase and group delay of both channels at `frequencies`.
+    pub fn calculate_response(&self, sample_rate: f32, frequencies: &[f32]) -> EqResponse {
+        let mut response = EqResponse {
+            frequencies: frequencies.to_vec(),
+            ..EqResponse::default()
+        };
+        for (channel, bands) in [&self.bands_left, &self.bands_right]
+            .into_iter()
+            .enumerate()
+        {
+            for &freq in frequencies {
+                let enabled = bands.iter().filter(|band| band.enabled);
+                let complex = enabled
+                    .clone()
+                    .fold(Complex::new(1.0, 0.0), |response, band| {
+                        response * band.frequency_response(freq, sample_rate)
+                    });
+                let delay_samples: f32 = enabled
+                    .map(|band| band.group_delay(freq, sample_rate))
+                    .sum();
+                response.magnitude_db[channel].push(20.0 * complex.norm().max(1e-9).log10());
+                response.phase_deg[channel].push(complex.arg().to_degrees());
+                response.group_delay_ms[channel].push(delay_samples / sample_rate * 1000.0);
             }
-            response[i] = band_response.norm();
         }
+        response.band_magnitude_db = self
+            .bands_left
+            .iter()
+            .zip(&self.bands_right)
+            .map(
+                |(left, right)| match [left, right].into_iter().find(|band| band.enabled) {
+                    Some(band) => frequencies
+                        .iter()
+                        .map(|&freq| {
+                            let magnitude = band.frequency_response(freq, sample_rate).norm();
+                            20.0 * magnitude.max(1e-9).log10()
+                        })
+                        .collect(),
+                    None => Vec::new(),
+                },
+            )
+            .collect();
         response
     }
+
+    /// The highest gain of either channel's magnitude response in dB, with dynamic bands
+    /// at their full range. This is the attenuation needed ahead of the EQ to keep
+    /// full-scale signals from clipping. Recomputed only when the EQ changed, and without
+    /// allocating.
+    pub fn peak_gain_db(&mut self, sample_rate: f32) -> f32 {
+        if !self.peak_gain_stale {
+            return self.peak_gain_db;
+        }
+
+        let search = &mut self.peak_search;
+        search.set_sample_rate(sample_rate);
+        // Both channels of a band share its configuration, so the full-range sections
+        // are designed once per dynamic band
+        for (((full_range, band), config), dynamic) in search
+            .full_range
+            .iter_mut()
+            .zip(&self.bands_left)
+            .zip(&self.band_configs)
+            .zip(&self.dynamic_bands)
+        {
+            *full_range = match (config, dynamic) {
+                (Some(config), Some(_)) => Some(band.design_sections(
+                    config.filter_type,
+                    sample_rate,
+                    config.center_freq,
+                    config.q,
+                    config.gain_db,
+                )),
+                _ => None,
+            };
+        }
+        for (bands, response) in [&self.bands_left, &self.bands_right]
+            .into_iter()
+            .zip(search.response.iter_mut())
+        {
+            response.fill(Complex::new(1.0, 0.0));
+            for (band, full_range) in bands
+                .iter()
+                .zip(&search.full_range)
+                .filter(|(band, _)| band.enabled)
+            {
+                for (response, &z_inv) in response.iter_mut().zip(&search.z_inv) {
+                    *response *= match full_range {
+                        Some((sections, num_sections)) => sections[..*num_sections]
+                            .iter()
+                            .map(|coeffs| section_response(coeffs, z_inv))
+                            .product::<Complex<f32>>(),
+                        None => band.response_at(z_inv),
+                    };
+                }
+            }
+        }
+
+        let peak_gain = search
+            .response
+            .iter()
+            .flatten()
+            .map(|response| response.norm())
+            .fold(0.0f32, f32::max);
+        self.peak_gain_db = 20.0 * peak_gain.max(1e-9).log10();
+        self.peak_gain_stale = false;
+        self.peak_gain_db
+    }
+
+    /// The result of the last `peak_gain_db` call, which may predate the latest changes.
+    pub fn last_peak_gain_db(&self) -> f32 {
+        self.peak_gain_db
+    }
+}
+
+fn channel_frequency_response(
+    bands: &[BiquadFilter],
+    sample_rate: f32,
+    frequencies: &[f32],
+) -> Vec<f32> {
+    frequencies
+        .iter()
+        .map(|&freq| {
+            bands
+                .iter()
+                .filter(|band| band.enabled)
+                .fold(Complex::new(1.0, 0.0), |response, band| {
+                    response * band.frequency_response(freq, sample_rate)
+                })
+                .norm()
+        })
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use strum::IntoEnumIterator;
 
     const SAMPLE_RATE: f32 = 48000.0;
 
@@ -236,4 +1345,411 @@ mod tests {
             "Filter should process the sample when enabled"
         );
     }
+
+    /// The largest difference between the digital filter and its analog prototype, in
+    /// dB, from 20 Hz to 20 kHz.
+    fn max_deviation_from_prototype_db(
+        design: BiquadDesign,
+        filter_type: FilterType,
+        center_freq: f32,
+        q: f32,
+        gain_db: f32,
+    ) -> f32 {
+        let mut filter = BiquadFilter::new(SAMPLE_RATE);
+        filter.set_design(design);
+        filter.update_coeffs(filter_type, SAMPLE_RATE, center_freq, q, gain_db);
+        let prototype = AnalogPrototype::new(filter_type, q, gain_db);
+
+        (0..200)
+            .map(|i| 20.0 * 1000f32.powf(i as f32 / 199.0))
+            .map(|freq| {
+                let digital_db = 20.0 * filter.frequency_response(freq, SAMPLE_RATE).norm().log10();
+                let analog_db = 10.0
+                    * prototype
+                        .magnitude_squared((freq / center_freq) as f64)
+                        .log10() as f32;
+                (digital_db - analog_db).abs()
+            })
+            .fold(0.0, f32::max)
+    }
+
+    #[test]
+    fn test_matched_design_follows_prototype_up_to_20khz() {
+        // Peaks and shelves are what headphone corrections are made of, so they get the
+        // tighter tolerance
+        for (filter_type, center_freq, q, gain_db, tolerance_db) in [
+            (FilterType::Peak, 1000.0, 1.0, 6.0, 0.75),
+            (FilterType::Peak, 8000.0, 2.0, -8.0, 0.75),
+            (FilterType::Peak, 12000.0, 1.5, 8.0, 0.75),
+            (FilterType::Peak, 16000.0, 0.7, 6.0, 0.75),
+            (FilterType::LowShelf, 105.0, 0.7, 6.0, 0.75),
+            (FilterType::HighShelf, 10000.0, 0.7, -6.0, 0.75),
+            (FilterType::HighShelf, 15000.0, 0.7, 6.0, 0.75),
+            (FilterType::LowPass, 12000.0, 0.707, 0.0, 1.25),
+            (FilterType::HighPass, 5000.0, 0.707, 0.0, 1.25),
+            (FilterType::BandPass, 10000.0, 2.0, 0.0, 1.25),
+        ] {
+            let deviation = max_deviation_from_prototype_db(
+                BiquadDesign::Matched,
+                filter_type,
+                center_freq,
+                q,
+                gain_db,
+            );
+            assert!(
+                deviation < tolerance_db,
+                "{:?} at {} Hz deviates by {} dB",
+                filter_type,
+                center_freq,
+                deviation
+            );
+        }
+    }
+
+    #[test]
+    fn test_bilinear_design_cramps_near_nyquist() {
+        let bilinear = max_deviation_from_prototype_db(
+            BiquadDesign::Bilinear,
+            FilterType::Peak,
+            12000.0,
+            1.5,
+            8.0,
+        );
+        let matched = max_deviation_from_prototype_db(
+            BiquadDesign::Matched,
+            FilterType::Peak,
+            12000.0,
+            1.5,
+            8.0,
+        );
+        assert!(
+            bilinear > 2.0,
+            "Bilinear deviation was only {} dB",
+            bilinear
+        );
+        assert!(matched < bilinear / 4.0);
+    }
+
+    /// The analog magnitude of the fixed-slope filters at `freq / center_freq`.
+    fn slope_magnitude_db(filter_type: FilterType, ratio: f64) -> f64 {
+        let (section_type, section_qs) = filter_type.slope_sections().unwrap();
+        section_qs
+            .iter()
+            .map(|&q| {
+                10.0 * AnalogPrototype::new(section_type, q, 0.0)
+                    .magnitude_squared(ratio)
+                    .log10()
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_added_filter_types_follow_analog_response() {
+        // A high sample rate keeps the bilinear frequency warping out of the comparison
+        let sample_rate = 192000.0;
+        let center_freq = 1000.0;
+        for filter_type in FilterType::iter().skip_while(|t| *t != FilterType::LowShelf1) {
+            let mut filter = BiquadFilter::new(sample_rate);
+            filter.update_coeffs(filter_type, sample_rate, center_freq, 0.7, 6.0);
+
+            for i in 0..50 {
+                let freq = 100.0 * 40f32.powf(i as f32 / 49.0);
+                let ratio = (freq / center_freq) as f64;
+                let analog_db = match filter_type {
+                    FilterType::LowShelf1 | FilterType::HighShelf1 | FilterType::Tilt => {
+                        10.0 * AnalogPrototype::new(filter_type, 0.7, 6.0)
+                            .magnitude_squared(ratio)
+                            .log10()
+                    }
+                    _ => slope_magnitude_db(filter_type, ratio),
+                } as f32;
+                if analog_db < -60.0 {
+                    continue;
+                }
+
+                let digital_db = 20.0 * filter.frequency_response(freq, sample_rate).norm().log10();
+                assert!(
+                    (digital_db - analog_db).abs() < 0.1,
+                    "{:?} at {} Hz: {} dB, expected {} dB",
+                    filter_type,
+                    freq,
+                    digital_db,
+                    analog_db
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_linkwitz_riley_pairs_sum_flat() {
+        for (low_pass, high_pass) in [
+            (
+                FilterType::LinkwitzRileyLowPass24,
+                FilterType::LinkwitzRileyHighPass24,
+            ),
+            (
+                FilterType::LinkwitzRileyLowPass48,
+                FilterType::LinkwitzRileyHighPass48,
+            ),
+        ] {
+            let mut lp = BiquadFilter::new(SAMPLE_RATE);
+            lp.update_coeffs(low_pass, SAMPLE_RATE, 2000.0, 0.7, 0.0);
+            let mut hp = BiquadFilter::new(SAMPLE_RATE);
+            hp.update_coeffs(high_pass, SAMPLE_RATE, 2000.0, 0.7, 0.0);
+
+            for freq in [100.0, 1000.0, 2000.0, 4000.0, 15000.0] {
+                let sum = lp.frequency_response(freq, SAMPLE_RATE)
+                    + hp.frequency_response(freq, SAMPLE_RATE);
+                assert!(
+                    (sum.norm() - 1.0).abs() < 1e-3,
+                    "{:?} + {:?} at {} Hz sums to {}",
+                    low_pass,
+                    high_pass,
+                    freq,
+                    sum.norm()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_cascade_processing_matches_response() {
+        let mut filter = BiquadFilter::new(SAMPLE_RATE);
+        filter.update_coeffs(
+            FilterType::ButterworthLowPass48,
+            SAMPLE_RATE,
+            1000.0,
+            0.7,
+            0.0,
+        );
+        filter.set_enabled(true);
+
+        let freq = 1500.0;
+        let omega = 2.0 * std::f32::consts::PI * freq / SAMPLE_RATE;
+        let output: Vec<f32> = (0..9600)
+            .map(|i| filter.process_sample((omega * i as f32).sin()))
+            .collect();
+        let amplitude = output[4800..].iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        let expected = filter.frequency_response(freq, SAMPLE_RATE).norm();
+        assert!(
+            (amplitude - expected).abs() < 0.02 * expected,
+            "Amplitude {} vs response {}",
+            amplitude,
+            expected
+        );
+    }
+
+    fn cut_band(channel: BandChannel) -> BandConfig {
+        BandConfig {
+            filter_type: FilterType::Peak,
+            center_freq: 1000.0,
+            q: 1.0,
+            gain_db: -12.0,
+            enabled: true,
+            channel,
+            dynamics: None,
+        }
+    }
+
+    #[test]
+    fn test_left_right_mode_only_filters_selected_channel() {
+        let mut eq = StereoParametricEQ::new(1, SAMPLE_RATE);
+        eq.set_channel_mode(EqChannelMode::LeftRight);
+        eq.update_band_coeffs(0, SAMPLE_RATE, &cut_band(BandChannel::First), 0);
+
+        let input: Vec<f32> = (0..256)
+            .map(|i| (i as f32 * 2.0 * std::f32::consts::PI * 1000.0 / SAMPLE_RATE).sin())
+            .collect();
+        let mut left = input.clone();
+        let mut right = input.clone();
+        eq.process_block(&mut left, &mut right);
+
+        assert_eq!(right, input, "Right channel should be untouched");
+        assert_ne!(left, input, "Left channel should be filtered");
+    }
+
+    #[test]
+    fn test_mid_side_mode_side_band_leaves_mono_untouched() {
+        let mut eq = StereoParametricEQ::new(1, SAMPLE_RATE);
+        eq.set_channel_mode(EqChannelMode::MidSide);
+        eq.update_band_coeffs(0, SAMPLE_RATE, &cut_band(BandChannel::Second), 0);
+
+        // A mono signal has no side component, so a side-only band must not change it.
+        let input: Vec<f32> = (0..256).map(|i| (i as f32 * 0.13).sin()).collect();
+        let mut left = input.clone();
+        let mut right = input.clone();
+        eq.process_block(&mut left, &mut right);
+
+        for (i, (&l, &x)) in left.iter().zip(&input).enumerate() {
+            assert!(
+                (l - x).abs() < 1e-6,
+                "Mismatch at index {}: {} vs {}",
+                i,
+                l,
+                x
+            );
+        }
+        assert_eq!(left, right);
+    }
+
+    /// The first samples of the EQ's impulse response, starting from a cleared state.
+    fn impulse_response(eq: &mut StereoParametricEQ) -> Vec<f32> {
+        eq.reset_all_bands_state();
+        let mut left = vec![0.0; 64];
+        left[0] = 1.0;
+        let mut right = left.clone();
+        eq.process_block(&mut left, &mut right);
+        left
+    }
+
+    #[test]
+    fn test_coefficients_glide_to_changed_band() {
+        let mut band = cut_band(BandChannel::Both);
+        let mut eq = StereoParametricEQ::new(1, SAMPLE_RATE);
+        eq.update_band_coeffs(0, SAMPLE_RATE, &band, 0);
+
+        band.center_freq = 4000.0;
+        let mut expected = StereoParametricEQ::new(1, SAMPLE_RATE);
+        expected.update_band_coeffs(0, SAMPLE_RATE, &band, 0);
+        let expected = impulse_response(&mut expected);
+
+        eq.update_band_coeffs(0, SAMPLE_RATE, &band, 128);
+        let mut silence = vec![0.0; 64];
+        eq.process_block(&mut silence.clone(), &mut silence);
+
+        // Repeating an unchanged configuration must not restart the glide, so it ends
+        // after 128 samples in total
+        eq.update_band_coeffs(0, SAMPLE_RATE, &band, 128);
+        assert_ne!(
+            impulse_response(&mut eq),
+            expected,
+            "The glide should still be in progress"
+        );
+        assert_eq!(impulse_response(&mut eq), expected);
+    }
+
+    #[test]
+    fn test_peak_gain_follows_boosts() {
+        let mut eq = StereoParametricEQ::new(2, SAMPLE_RATE);
+        assert!(eq.peak_gain_db(SAMPLE_RATE).abs() < 0.01);
+
+        let mut boost = cut_band(BandChannel::Second);
+        boost.gain_db = 6.0;
+        eq.set_channel_mode(EqChannelMode::LeftRight);
+        eq.update_band_coeffs(1, SAMPLE_RATE, &boost, 0);
+        let peak = eq.peak_gain_db(SAMPLE_RATE);
+        assert!((peak - 6.0).abs() < 0.1, "Peak gain was {} dB", peak);
+
+        boost.enabled = false;
+        eq.update_band_coeffs(1, SAMPLE_RATE, &boost, 0);
+        assert!(eq.peak_gain_db(SAMPLE_RATE).abs() < 0.01);
+    }
+
+    /// The gain of the EQ for a 1 kHz sine of the given amplitude, once the side-chains
+    /// have settled, in dB.
+    fn settled_sine_gain_db(eq: &mut StereoParametricEQ, amplitude: f32) -> f32 {
+        let input: Vec<f32> = (0..SAMPLE_RATE as usize / 2)
+            .map(|i| {
+                amplitude * (i as f32 * 2.0 * std::f32::consts::PI * 1000.0 / SAMPLE_RATE).sin()
+            })
+            .collect();
+        let mut left = input.clone();
+        let mut right = input;
+        eq.process_block(&mut left, &mut right);
+
+        let tail = &left[left.len() - 480..];
+        let peak = tail
+            .iter()
+            .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        20.0 * (peak / amplitude).log10()
+    }
+
+    #[test]
+    fn test_dynamic_band_only_cuts_loud_signals() {
+        let mut band = cut_band(BandChannel::Both);
+        band.dynamics = Some(DynamicConfig {
+            threshold_db: -20.0,
+            ratio: 2.0,
+            attack_ms: 1.0,
+            release_ms: 50.0,
+        });
+
+        let mut eq = StereoParametricEQ::new(1, SAMPLE_RATE);
+        eq.update_band_coeffs(0, SAMPLE_RATE, &band, 0);
+        let quiet = settled_sine_gain_db(&mut eq, 0.01);
+        assert!(quiet.abs() < 0.1, "Quiet signal changed by {} dB", quiet);
+
+        // 20 dB over the threshold at a ratio of 2 calls for a 10 dB cut
+        let loud = settled_sine_gain_db(&mut eq, 1.0);
+        assert!(
+            (loud + 10.0).abs() < 0.5,
+            "Loud signal changed by {} dB",
+            loud
+        );
+
+        // The cut stops at the band's gain
+        band.gain_db = -6.0;
+        eq.update_band_coeffs(0, SAMPLE_RATE, &band, 0);
+        let limited = settled_sine_gain_db(&mut eq, 1.0);
+        assert!(
+            (limited + 6.0).abs() < 0.5,
+            "Loud signal changed by {} dB",
+            limited
+        );
+    }
+
+    #[test]
+    fn test_dynamic_band_rests_flat_but_counts_towards_peak_gain() {
+        let mut boost = cut_band(BandChannel::Both);
+        boost.gain_db = 6.0;
+        boost.dynamics = Some(DynamicConfig::default());
+        let mut eq = StereoParametricEQ::new(1, SAMPLE_RATE);
+        eq.update_band_coeffs(0, SAMPLE_RATE, &boost, 0);
+
+        let response = eq.calculate_frequency_response(SAMPLE_RATE, &[1000.0]);
+        assert!(
+            (response[0] - 1.0).abs() < 1e-4,
+            "Response at rest was {}",
+            response[0]
+        );
+        let peak = eq.peak_gain_db(SAMPLE_RATE);
+        assert!((peak - 6.0).abs() < 0.1, "Peak gain was {} dB", peak);
+    }
+
+    #[test]
+    fn test_response_group_delay_matches_phase_slope() {
+        let mut band = cut_band(BandChannel::First);
+        band.filter_type = FilterType::ButterworthLowPass12;
+        let request = EqResponseRequest {
+            sample_rate: SAMPLE_RATE,
+            channel_mode: EqChannelMode::LeftRight,
+            design: BiquadDesign::Bilinear,
+            bands: vec![band],
+        };
+        let eq = StereoParametricEQ::with_bands(
+            request.sample_rate,
+            request.channel_mode,
+            request.design,
+            &request.bands,
+        );
+
+        let response = eq.calculate_response(SAMPLE_RATE, &[995.0, 1000.0, 1005.0]);
+        assert!((response.magnitude_db[0][1] + 3.01).abs() < 0.05);
+        assert!(response.magnitude_db[1][1].abs() < 1e-4);
+        assert!(response.group_delay_ms[1][1].abs() < 1e-4);
+
+        let phase_step = (response.phase_deg[0][2] - response.phase_deg[0][0]).to_radians();
+        let omega_step = 2.0 * std::f32::consts::PI * 10.0;
+        let expected_ms = -phase_step / omega_step * 1000.0;
+        let delay_ms = response.group_delay_ms[0][1];
+        assert!(
+            (delay_ms - expected_ms).abs() < 0.01,
+            "Group delay was {} ms instead of {} ms",
+            delay_ms,
+            expected_ms
+        );
+
+        assert_eq!(request.compute().frequencies.len(), RESPONSE_POINTS);
+        assert_eq!(response.band_magnitude_db[0], response.magnitude_db[0]);
+    }
 }
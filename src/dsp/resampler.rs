@@ -0,0 +1,207 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/dsp/resampler.rs
+
+// How far the ratio may stray from the nominal one to follow the clocks, 0.2% is far
+// more than any two crystals drift apart
+const MAX_CORRECTION: f64 = 0.002;
+// The correction for a buffer that is off by its whole target fill, and for each second
+// it stays off
+const PROPORTIONAL_GAIN: f64 = 1e-3;
+const INTEGRAL_GAIN: f64 = 1e-4;
+// How quickly the measured fill follows the buffer, per output block
+const FILL_SMOOTHING: f64 = 0.05;
+
+/// Resamples stereo audio from a device to one running on another clock. The rate is
+/// converted with cubic interpolation, and the ratio is nudged to keep the buffer between
+/// the devices at `target_fill` frames, so it neither runs dry nor overflows as the clocks
+/// drift apart. Cubic interpolation is meant for rates that are equal or close; it doesn't
+/// filter before downsampling.
+pub struct DriftResampler {
+    // Input frames per output frame at the nominal rates
+    nominal_ratio: f64,
+    ratio: f64,
+    // The last four input frames per channel. Output falls between the second and third.
+    history: [[f32; 4]; 2],
+    // Position of the next output frame past the second frame in the history
+    phase: f64,
+    target_fill: f64,
+    smoothed_error: f64,
+    integral: f64,
+    output_rate: f64,
+    // Whether the buffer has filled up to the target since it last ran dry
+    primed: bool,
+}
+
+impl DriftResampler {
+    pub fn new(input_rate: f32, output_rate: f32, target_fill: usize) -> Self {
+        let nominal_ratio = input_rate as f64 / output_rate as f64;
+        Self {
+            nominal_ratio,
+            ratio: nominal_ratio,
+            history: [[0.0; 4]; 2],
+            phase: 0.0,
+            target_fill: target_fill.max(1) as f64,
+            smoothed_error: 0.0,
+            integral: 0.0,
+            output_rate: output_rate as f64,
+            primed: false,
+        }
+    }
+
+    /// Input frames consumed per output frame at the moment.
+    pub fn ratio(&self) -> f64 {
+        self.ratio
+    }
+
+    /// Fills `left` and `right` from the input. `fill` is how many input frames are
+    /// waiting in the buffer and `next_frame` takes the oldest one. Until the buffer holds
+    /// `target_fill` frames, and after it runs dry, the output is silent.
+    pub fn process(
+        &mut self,
+        fill: usize,
+        mut next_frame: impl FnMut() -> Option<[f32; 2]>,
+        left: &mut [f32],
+        right: &mut [f32],
+    ) {
+        if !self.primed {
+            if (fill as f64) < self.target_fill {
+                left.fill(0.0);
+                right.fill(0.0);
+                return;
+            }
+            self.primed = true;
+        }
+        self.update_ratio(fill, left.len());
+
+        for (index, (sample_l, sample_r)) in left.iter_mut().zip(right.iter_mut()).enumerate() {
+            while self.phase >= 1.0 {
+                let Some(frame) = next_frame() else {
+                    // Ran dry, wait for the buffer to fill up again
+                    self.primed = false;
+                    self.integral = 0.0;
+                    left[index..].fill(0.0);
+                    right[index..].fill(0.0);
+                    return;
+                };
+                for (history, sample) in self.history.iter_mut().zip(frame) {
+                    history.rotate_left(1);
+                    history[3] = sample;
+                }
+                self.phase -= 1.0;
+            }
+            let t = self.phase as f32;
+            *sample_l = hermite(&self.history[0], t);
+            *sample_r = hermite(&self.history[1], t);
+            self.phase += self.ratio;
+        }
+    }
+
+    fn update_ratio(&mut self, fill: usize, block_len: usize) {
+        let error = (fill as f64 - self.target_fill) / self.target_fill;
+        self.smoothed_error += FILL_SMOOTHING * (error - self.smoothed_error);
+        self.integral += self.smoothed_error * block_len as f64 / self.output_rate;
+        // The integral alone can't push past the limit, so it recovers quickly
+        self.integral = self.integral.clamp(
+            -MAX_CORRECTION / INTEGRAL_GAIN,
+            MAX_CORRECTION / INTEGRAL_GAIN,
+        );
+
+        // A fuller buffer reads faster
+        let correction = (PROPORTIONAL_GAIN * self.smoothed_error + INTEGRAL_GAIN * self.integral)
+            .clamp(-MAX_CORRECTION, MAX_CORRECTION);
+        self.ratio = self.nominal_ratio * (1.0 + correction);
+    }
+}
+
+/// 4-point, 3rd-order Hermite interpolation between `x[1]` and `x[2]`.
+fn hermite(x: &[f32; 4], t: f32) -> f32 {
+    let c1 = 0.5 * (x[2] - x[0]);
+    let c2 = x[0] - 2.5 * x[1] + 2.0 * x[2] - 0.5 * x[3];
+    let c3 = 0.5 * (x[3] - x[0]) + 1.5 * (x[1] - x[2]);
+    ((c3 * t + c2) * t + c1) * t + x[1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::f32::consts::TAU;
+
+    const BLOCK_SIZE: usize = 256;
+
+    fn sine(freq: f32, sample_rate: f32, len: usize) -> VecDeque<[f32; 2]> {
+        (0..len)
+            .map(|i| {
+                let sample = (TAU * freq * i as f32 / sample_rate).sin();
+                [sample, sample]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_converts_rate() {
+        let mut input = sine(1000.0, 44100.0, 44100);
+        let mut resampler = DriftResampler::new(44100.0, 48000.0, 1024);
+        let mut left = vec![0.0; BLOCK_SIZE];
+        let mut right = vec![0.0; BLOCK_SIZE];
+        let mut output = Vec::new();
+        // A buffer kept at its target fill leaves the ratio alone
+        for _ in 0..100 {
+            resampler.process(1024, || input.pop_front(), &mut left, &mut right);
+            output.extend_from_slice(&left);
+        }
+        assert!((resampler.ratio() - 44100.0 / 48000.0).abs() < 1e-9);
+
+        // Output frame i falls at input frame i * ratio, behind by the interpolator's
+        // three frames of history
+        let ratio = resampler.ratio() as f32;
+        for (i, sample) in output.iter().enumerate().skip(BLOCK_SIZE) {
+            let position = i as f32 * ratio - 3.0;
+            let expected = (TAU * 1000.0 * position / 44100.0).sin();
+            assert!(
+                (sample - expected).abs() < 1e-3,
+                "{} vs {}",
+                sample,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn test_follows_buffer_fill() {
+        let mut resampler = DriftResampler::new(48000.0, 48000.0, 1024);
+        let mut left = vec![0.0; BLOCK_SIZE];
+        let mut right = vec![0.0; BLOCK_SIZE];
+
+        // Silent until the buffer reaches its target
+        resampler.process(512, || Some([1.0, 1.0]), &mut left, &mut right);
+        assert!(left.iter().all(|&sample| sample == 0.0));
+
+        // A buffer that keeps filling up gets read faster, up to the limit
+        for _ in 0..2000 {
+            resampler.process(2048, || Some([0.5, 0.5]), &mut left, &mut right);
+        }
+        assert!(resampler.ratio() > 1.0);
+        assert!(resampler.ratio() <= 1.0 + MAX_CORRECTION + 1e-12);
+        assert!(left.iter().all(|&sample| (sample - 0.5).abs() < 1e-6));
+
+        // Running dry mutes until the buffer fills again
+        resampler.process(2048, || None, &mut left, &mut right);
+        assert!(left.iter().all(|&sample| sample == 0.0));
+        resampler.process(16, || Some([0.5, 0.5]), &mut left, &mut right);
+        assert!(left.iter().all(|&sample| sample == 0.0));
+    }
+}
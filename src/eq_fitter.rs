@@ -0,0 +1,750 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/eq_fitter.rs
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::autoeq_parser::BandSetting;
+use crate::dsp::parametric_eq::{BandChannel, BiquadFilter, FilterType};
+
+// Frequency grid used for fitting
+const GRID_POINTS: usize = 192;
+const GRID_MIN_FREQ: f32 = 20.0;
+const GRID_MAX_FREQ: f32 = 20000.0;
+
+// The correction is level-aligned so that this range needs no gain on average
+const NORMALIZATION_RANGE: (f32, f32) = (200.0, 2000.0);
+// Width of the smoothing window applied to the raw correction curve, in octaves
+const SMOOTHING_OCTAVES: f32 = 1.0 / 12.0;
+
+const LOW_SHELF_FREQ: f32 = 105.0;
+const HIGH_SHELF_FREQ: f32 = 10000.0;
+const SHELF_Q: f32 = 0.7;
+
+// Peaks smaller than this are not worth spending a band on
+const MIN_PEAK_GAIN_DB: f32 = 0.2;
+const REFINE_PASSES_PER_BAND: usize = 8;
+const REFINE_PASSES_FINAL: usize = 40;
+
+/// A magnitude response sampled at arbitrary frequencies, such as a headphone
+/// measurement or a target curve.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrequencyResponse {
+    pub frequencies: Vec<f32>,
+    pub magnitudes_db: Vec<f32>,
+}
+
+impl FrequencyResponse {
+    fn from_points(points: &[(f32, f32)]) -> Self {
+        Self {
+            frequencies: points.iter().map(|p| p.0).collect(),
+            magnitudes_db: points.iter().map(|p| p.1).collect(),
+        }
+    }
+
+    /// Interpolates the response linearly over log-frequency, holding the end values
+    /// outside the measured range.
+    pub fn interpolate(&self, freq: f32) -> f32 {
+        let n = self.frequencies.len();
+        if n == 0 {
+            return 0.0;
+        }
+        if freq <= self.frequencies[0] {
+            return self.magnitudes_db[0];
+        }
+        if freq >= self.frequencies[n - 1] {
+            return self.magnitudes_db[n - 1];
+        }
+
+        let upper = self.frequencies.partition_point(|&f| f < freq);
+        let lower = upper - 1;
+        let (f0, f1) = (self.frequencies[lower], self.frequencies[upper]);
+        let t = (freq / f0).ln() / (f1 / f0).ln();
+        self.magnitudes_db[lower] + t * (self.magnitudes_db[upper] - self.magnitudes_db[lower])
+    }
+}
+
+/// Parses a frequency response in any of the common text formats: AutoEQ CSVs
+/// (`frequency,raw,...`), squig.link exports and REW text exports (`*` comment lines,
+/// whitespace separated columns). When a header row is present the `raw` column is
+/// used, otherwise the second column.
+pub fn parse_frequency_response(text: &str) -> Result<FrequencyResponse, Box<dyn Error>> {
+    let mut value_column = 1;
+    let mut points = Vec::new();
+
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with(['*', '#', ';', '/']) {
+            continue;
+        }
+
+        let fields: Vec<&str> = line
+            .split([',', '\t', ';', ' '])
+            .filter(|f| !f.is_empty())
+            .collect();
+        if fields.len() < 2 {
+            continue;
+        }
+
+        let Ok(freq) = fields[0].parse::<f32>() else {
+            // A header row. AutoEQ files carry several columns, of which `raw` is the
+            // unprocessed measurement.
+            if let Some(idx) = fields
+                .iter()
+                .position(|f| f.eq_ignore_ascii_case("raw"))
+                .filter(|_| points.is_empty())
+            {
+                value_column = idx;
+            }
+            continue;
+        };
+        let Some(Ok(db)) = fields.get(value_column).map(|f| f.parse::<f32>()) else {
+            continue;
+        };
+        if freq > 0.0 && db.is_finite() {
+            points.push((freq, db));
+        }
+    }
+
+    points.sort_by(|a, b| a.0.total_cmp(&b.0));
+    points.dedup_by(|a, b| a.0 == b.0);
+    if points.len() < 2 {
+        return Err("Frequency response contains fewer than two data points".into());
+    }
+
+    Ok(FrequencyResponse::from_points(&points))
+}
+
+pub fn parse_frequency_response_csv(path: &Path) -> Result<FrequencyResponse, Box<dyn Error>> {
+    let text = fs::read_to_string(path)?;
+    parse_frequency_response(&text)
+}
+
+/// The response a headphone should be equalized towards.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TargetCurve {
+    HarmanOverEar2018,
+    HarmanInEar2019,
+    DiffuseField,
+    Custom(PathBuf),
+}
+
+impl TargetCurve {
+    pub const BUILT_IN: [TargetCurve; 3] = [
+        TargetCurve::HarmanOverEar2018,
+        TargetCurve::HarmanInEar2019,
+        TargetCurve::DiffuseField,
+    ];
+
+    pub fn name(&self) -> String {
+        match self {
+            TargetCurve::HarmanOverEar2018 => "Harman Over-Ear 2018".to_string(),
+            TargetCurve::HarmanInEar2019 => "Harman In-Ear 2019".to_string(),
+            TargetCurve::DiffuseField => "Diffuse Field".to_string(),
+            TargetCurve::Custom(path) => path
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| "Custom".to_string()),
+        }
+    }
+
+    pub fn load(&self) -> Result<FrequencyResponse, Box<dyn Error>> {
+        match self {
+            TargetCurve::HarmanOverEar2018 => Ok(FrequencyResponse::from_points(HARMAN_OE_2018)),
+            TargetCurve::HarmanInEar2019 => Ok(FrequencyResponse::from_points(HARMAN_IE_2019)),
+            TargetCurve::DiffuseField => Ok(FrequencyResponse::from_points(DIFFUSE_FIELD)),
+            TargetCurve::Custom(path) => parse_frequency_response_csv(path),
+        }
+    }
+}
+
+// The built-in targets are coarse approximations of the published eardrum curves,
+// sampled at a handful of points. They are close enough for a first fit; load the
+// original CSV as a custom target when an exact match matters.
+const HARMAN_OE_2018: &[(f32, f32)] = &[
+    (20.0, 6.5),
+    (30.0, 6.4),
+    (50.0, 6.0),
+    (70.0, 5.3),
+    (100.0, 4.0),
+    (150.0, 2.2),
+    (200.0, 1.0),
+    (300.0, 0.0),
+    (500.0, -0.3),
+    (700.0, 0.0),
+    (1000.0, 0.8),
+    (1500.0, 3.5),
+    (2000.0, 7.5),
+    (2500.0, 9.8),
+    (3000.0, 10.8),
+    (4000.0, 9.5),
+    (5000.0, 7.0),
+    (6000.0, 5.0),
+    (8000.0, 2.0),
+    (10000.0, 0.0),
+    (12000.0, -2.0),
+    (16000.0, -6.0),
+    (20000.0, -10.0),
+];
+
+const HARMAN_IE_2019: &[(f32, f32)] = &[
+    (20.0, 9.5),
+    (30.0, 9.3),
+    (50.0, 8.5),
+    (70.0, 7.3),
+    (100.0, 5.5),
+    (150.0, 3.2),
+    (200.0, 1.8),
+    (300.0, 0.5),
+    (500.0, -0.2),
+    (700.0, -0.2),
+    (1000.0, 0.5),
+    (1500.0, 3.0),
+    (2000.0, 7.0),
+    (2500.0, 9.8),
+    (3000.0, 11.0),
+    (4000.0, 9.8),
+    (5000.0, 7.5),
+    (6000.0, 5.0),
+    (8000.0, 2.5),
+    (10000.0, 1.0),
+    (12000.0, -1.0),
+    (16000.0, -5.0),
+    (20000.0, -10.0),
+];
+
+const DIFFUSE_FIELD: &[(f32, f32)] = &[
+    (20.0, 0.0),
+    (200.0, 0.0),
+    (500.0, 0.3),
+    (700.0, 0.8),
+    (1000.0, 1.5),
+    (1500.0, 4.0),
+    (2000.0, 7.5),
+    (2500.0, 11.5),
+    (3000.0, 13.5),
+    (4000.0, 12.0),
+    (5000.0, 9.5),
+    (6000.0, 7.0),
+    (8000.0, 3.5),
+    (10000.0, 1.0),
+    (12000.0, 0.0),
+    (16000.0, -2.0),
+    (20000.0, -5.0),
+];
+
+/// Limits for the optimiser. The defaults follow the ones AutoEQ uses for its
+/// parametric presets.
+#[derive(Debug, Clone, Copy)]
+pub struct FitConfig {
+    pub num_bands: usize,
+    pub sample_rate: f32,
+    pub max_gain_db: f32,
+    pub min_q: f32,
+    pub max_q: f32,
+    /// Only errors between `min_freq` and `max_freq` are corrected.
+    pub min_freq: f32,
+    pub max_freq: f32,
+    /// Use a low and a high shelf for the broadband tilt before placing peaks.
+    pub use_shelves: bool,
+}
+
+impl Default for FitConfig {
+    fn default() -> Self {
+        Self {
+            num_bands: 10,
+            sample_rate: 48000.0,
+            max_gain_db: 12.0,
+            min_q: 0.18,
+            max_q: 6.0,
+            min_freq: 20.0,
+            max_freq: 16000.0,
+            use_shelves: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EqFit {
+    pub bands: Vec<BandSetting>,
+    /// The negative gain needed to keep the equalized response from exceeding 0 dB.
+    pub preamp_db: f32,
+    /// Remaining weighted RMS deviation from the target after equalization.
+    pub rms_error_db: f32,
+}
+
+/// Optimises up to `config.num_bands` filters so that `measurement` plus the EQ
+/// matches `target`.
+///
+/// The fit is greedy: shelves take out the broadband tilt, then peaks are placed one
+/// at a time at the largest remaining deviation, and every band is refined with a
+/// coordinate descent on the weighted RMS error after each addition.
+pub fn fit_eq(
+    measurement: &FrequencyResponse,
+    target: &FrequencyResponse,
+    config: &FitConfig,
+) -> EqFit {
+    let grid = log_spaced_grid(GRID_MIN_FREQ, GRID_MAX_FREQ, GRID_POINTS);
+    // Keep all filters well below Nyquist, where the biquad designs stop being valid.
+    let max_freq = config.max_freq.min(config.sample_rate * 0.45);
+
+    let raw_correction: Vec<f32> = grid
+        .iter()
+        .map(|&f| target.interpolate(f) - measurement.interpolate(f))
+        .collect();
+    let correction = smooth(&grid, &normalize(&grid, &raw_correction));
+    let weights = grid
+        .iter()
+        .map(|&f| {
+            if f >= config.min_freq && f <= max_freq {
+                1.0
+            } else {
+                0.0
+            }
+        })
+        .collect();
+
+    let mut fitter = Fitter {
+        config: FitConfig {
+            max_freq,
+            ..*config
+        },
+        total_db: vec![0.0; grid.len()],
+        grid,
+        correction,
+        weights,
+        bands: Vec::new(),
+        band_db: Vec::new(),
+    };
+
+    if config.use_shelves && config.num_bands >= 3 {
+        let low_gain = fitter.mean_correction(config.min_freq, LOW_SHELF_FREQ);
+        fitter.add_band(fitter.clamped(FilterType::LowShelf, LOW_SHELF_FREQ, SHELF_Q, low_gain));
+        if max_freq > HIGH_SHELF_FREQ {
+            let high_gain = fitter.mean_correction(HIGH_SHELF_FREQ, max_freq);
+            fitter.add_band(fitter.clamped(
+                FilterType::HighShelf,
+                HIGH_SHELF_FREQ,
+                SHELF_Q,
+                high_gain,
+            ));
+        }
+        fitter.refine(REFINE_PASSES_PER_BAND);
+    }
+
+    while fitter.bands.len() < config.num_bands {
+        let residual = fitter.residual();
+        let Some(peak_idx) = (0..residual.len())
+            .filter(|&i| fitter.weights[i] > 0.0)
+            .max_by(|&a, &b| residual[a].abs().total_cmp(&residual[b].abs()))
+        else {
+            break;
+        };
+        if residual[peak_idx].abs() < MIN_PEAK_GAIN_DB {
+            break;
+        }
+
+        let q = estimate_q(&fitter.grid, &residual, peak_idx);
+        fitter.add_band(fitter.clamped(
+            FilterType::Peak,
+            fitter.grid[peak_idx],
+            q,
+            residual[peak_idx],
+        ));
+        fitter.refine(REFINE_PASSES_PER_BAND);
+    }
+    fitter.refine(REFINE_PASSES_FINAL);
+
+    // Round to the precision AutoEQ publishes, which is also what the editor displays.
+    let mut bands: Vec<BandSetting> = fitter
+        .bands
+        .iter()
+        .filter(|b| b.gain.abs() >= MIN_PEAK_GAIN_DB)
+        .map(|b| BandSetting {
+            enabled: true,
+            filter_type: b.filter_type,
+            frequency: b.frequency.round(),
+            q: (b.q * 100.0).round() / 100.0,
+            gain: (b.gain * 10.0).round() / 10.0,
+            channel: BandChannel::Both,
+            ..BandSetting::default()
+        })
+        .collect();
+    bands.sort_by(|a, b| {
+        band_order(a.filter_type)
+            .cmp(&band_order(b.filter_type))
+            .then(a.frequency.total_cmp(&b.frequency))
+    });
+
+    // The preamp and the error describe the bands that are returned, not the unrounded
+    // ones the optimiser ended up with.
+    let mut total_db = vec![0.0; fitter.grid.len()];
+    for band in &bands {
+        for (total, r) in total_db.iter_mut().zip(fitter.band_response_db(band)) {
+            *total += r;
+        }
+    }
+    let peak_gain_db = total_db.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let preamp_db = -((peak_gain_db.max(0.0) * 10.0).ceil() / 10.0);
+
+    EqFit {
+        bands,
+        preamp_db,
+        rms_error_db: fitter.cost(&total_db).sqrt(),
+    }
+}
+
+struct Fitter {
+    config: FitConfig,
+    grid: Vec<f32>,
+    correction: Vec<f32>,
+    weights: Vec<f32>,
+    bands: Vec<BandSetting>,
+    band_db: Vec<Vec<f32>>,
+    total_db: Vec<f32>,
+}
+
+impl Fitter {
+    fn clamped(&self, filter_type: FilterType, frequency: f32, q: f32, gain: f32) -> BandSetting {
+        let q = match filter_type {
+            FilterType::LowShelf | FilterType::HighShelf => SHELF_Q,
+            _ => q.clamp(self.config.min_q, self.config.max_q),
+        };
+        BandSetting {
+            enabled: true,
+            filter_type,
+            frequency: frequency.clamp(self.config.min_freq, self.config.max_freq),
+            q,
+            gain: gain.clamp(-self.config.max_gain_db, self.config.max_gain_db),
+            channel: BandChannel::Both,
+            ..BandSetting::default()
+        }
+    }
+
+    fn band_response_db(&self, band: &BandSetting) -> Vec<f32> {
+        let sample_rate = self.config.sample_rate;
+        let mut filter = BiquadFilter::new(sample_rate);
+        filter.update_coeffs(
+            band.filter_type,
+            sample_rate,
+            band.frequency,
+            band.q,
+            band.gain,
+        );
+        self.grid
+            .iter()
+            .map(|&f| {
+                20.0 * filter
+                    .frequency_response(f, sample_rate)
+                    .norm()
+                    .max(1e-9)
+                    .log10()
+            })
+            .collect()
+    }
+
+    fn add_band(&mut self, band: BandSetting) {
+        let response = self.band_response_db(&band);
+        for (total, r) in self.total_db.iter_mut().zip(&response) {
+            *total += r;
+        }
+        self.bands.push(band);
+        self.band_db.push(response);
+    }
+
+    fn mean_correction(&self, from: f32, to: f32) -> f32 {
+        let (sum, count) = self
+            .grid
+            .iter()
+            .zip(&self.correction)
+            .filter(|&(&f, _)| f >= from && f <= to)
+            .fold((0.0, 0), |(s, n), (_, &c)| (s + c, n + 1));
+        if count == 0 { 0.0 } else { sum / count as f32 }
+    }
+
+    fn residual(&self) -> Vec<f32> {
+        self.correction
+            .iter()
+            .zip(&self.total_db)
+            .map(|(c, t)| c - t)
+            .collect()
+    }
+
+    /// Weighted mean squared error of an EQ response against the correction curve.
+    fn cost(&self, total_db: &[f32]) -> f32 {
+        let mut sum = 0.0;
+        let mut weight_sum = 0.0;
+        for ((correction, total), weight) in self.correction.iter().zip(total_db).zip(&self.weights)
+        {
+            let err = correction - total;
+            sum += weight * err * err;
+            weight_sum += weight;
+        }
+        if weight_sum > 0.0 {
+            sum / weight_sum
+        } else {
+            0.0
+        }
+    }
+
+    /// Coordinate descent over frequency (log), gain and Q (log) of every band. Step
+    /// sizes halve whenever a full pass brings no improvement.
+    fn refine(&mut self, passes: usize) {
+        let mut freq_step = 0.25f32; // octaves
+        let mut gain_step = 1.0f32; // dB
+        let mut q_step = 0.25f32; // octaves of Q
+
+        let mut best_cost = self.cost(&self.total_db);
+        let mut candidate_total = vec![0.0; self.grid.len()];
+
+        for _ in 0..passes {
+            let mut improved = false;
+            for b in 0..self.bands.len() {
+                let is_shelf = matches!(
+                    self.bands[b].filter_type,
+                    FilterType::LowShelf | FilterType::HighShelf
+                );
+                for param in 0..3 {
+                    if param == 2 && is_shelf {
+                        continue;
+                    }
+                    for direction in [-1.0f32, 1.0] {
+                        let band = self.bands[b].clone();
+                        let (frequency, q, gain) = match param {
+                            0 => (
+                                band.frequency * (direction * freq_step).exp2(),
+                                band.q,
+                                band.gain,
+                            ),
+                            1 => (band.frequency, band.q, band.gain + direction * gain_step),
+                            _ => (
+                                band.frequency,
+                                band.q * (direction * q_step).exp2(),
+                                band.gain,
+                            ),
+                        };
+                        let candidate = self.clamped(band.filter_type, frequency, q, gain);
+                        if candidate == band {
+                            continue;
+                        }
+
+                        let response = self.band_response_db(&candidate);
+                        for i in 0..self.grid.len() {
+                            candidate_total[i] =
+                                self.total_db[i] - self.band_db[b][i] + response[i];
+                        }
+                        let cost = self.cost(&candidate_total);
+                        if cost < best_cost {
+                            best_cost = cost;
+                            self.total_db.copy_from_slice(&candidate_total);
+                            self.bands[b] = candidate;
+                            self.band_db[b] = response;
+                            improved = true;
+                        }
+                    }
+                }
+            }
+
+            if !improved {
+                freq_step *= 0.5;
+                gain_step *= 0.5;
+                q_step *= 0.5;
+                if gain_step < 0.02 {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+fn band_order(filter_type: FilterType) -> u8 {
+    match filter_type {
+        FilterType::LowShelf => 0,
+        FilterType::HighShelf => 2,
+        _ => 1,
+    }
+}
+
+fn log_spaced_grid(min_freq: f32, max_freq: f32, points: usize) -> Vec<f32> {
+    let ratio = (max_freq / min_freq).ln();
+    (0..points)
+        .map(|i| min_freq * (ratio * i as f32 / (points - 1) as f32).exp())
+        .collect()
+}
+
+/// Shifts the curve so its mean over `NORMALIZATION_RANGE` is 0 dB.
+fn normalize(grid: &[f32], curve: &[f32]) -> Vec<f32> {
+    let (lo, hi) = NORMALIZATION_RANGE;
+    let in_range: Vec<f32> = grid
+        .iter()
+        .zip(curve)
+        .filter(|&(&f, _)| f >= lo && f <= hi)
+        .map(|(_, &c)| c)
+        .collect();
+    let offset = if in_range.is_empty() {
+        0.0
+    } else {
+        in_range.iter().sum::<f32>() / in_range.len() as f32
+    };
+    curve.iter().map(|c| c - offset).collect()
+}
+
+fn smooth(grid: &[f32], curve: &[f32]) -> Vec<f32> {
+    let half_window = SMOOTHING_OCTAVES / 2.0;
+    grid.iter()
+        .map(|&center| {
+            let (sum, count) = grid
+                .iter()
+                .zip(curve)
+                .filter(|&(&f, _)| (f / center).log2().abs() <= half_window)
+                .fold((0.0, 0), |(s, n), (_, &c)| (s + c, n + 1));
+            sum / count as f32
+        })
+        .collect()
+}
+
+/// Estimates a peak's Q from the width at which the residual falls to half its height.
+fn estimate_q(grid: &[f32], residual: &[f32], peak_idx: usize) -> f32 {
+    let peak = residual[peak_idx];
+    let above_half = |i: usize| residual[i] * peak.signum() > peak.abs() / 2.0;
+
+    let mut lo = peak_idx;
+    while lo > 0 && above_half(lo - 1) {
+        lo -= 1;
+    }
+    let mut hi = peak_idx;
+    while hi + 1 < grid.len() && above_half(hi + 1) {
+        hi += 1;
+    }
+
+    let bandwidth_octaves = (grid[hi] / grid[lo]).log2().max(1.0 / 12.0);
+    let ratio = bandwidth_octaves.exp2();
+    ratio.sqrt() / (ratio - 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flat_response() -> FrequencyResponse {
+        FrequencyResponse::from_points(&[(20.0, 0.0), (20000.0, 0.0)])
+    }
+
+    #[test]
+    fn test_parse_autoeq_csv_uses_raw_column() {
+        let text = "frequency,smoothed,raw\n20.0,1.0,2.0\n1000.0,3.0,4.0\n";
+        let response = parse_frequency_response(text).unwrap();
+        assert_eq!(response.frequencies, vec![20.0, 1000.0]);
+        assert_eq!(response.magnitudes_db, vec![2.0, 4.0]);
+    }
+
+    #[test]
+    fn test_parse_rew_export() {
+        let text = "* Measurement data\n* Freq(Hz) SPL(dB) Phase(degrees)\n\
+                    1000.0 80.5 -12.0\n20.0\t75.0\t3.0\n";
+        let response = parse_frequency_response(text).unwrap();
+        assert_eq!(response.frequencies, vec![20.0, 1000.0]);
+        assert_eq!(response.magnitudes_db, vec![75.0, 80.5]);
+    }
+
+    #[test]
+    fn test_parse_rejects_empty_input() {
+        assert!(parse_frequency_response("frequency,raw\n").is_err());
+    }
+
+    #[test]
+    fn test_interpolation_is_log_linear() {
+        let response = FrequencyResponse::from_points(&[(100.0, 0.0), (10000.0, 10.0)]);
+        assert!((response.interpolate(1000.0) - 5.0).abs() < 1e-4);
+        assert_eq!(response.interpolate(10.0), 0.0);
+        assert_eq!(response.interpolate(20000.0), 10.0);
+    }
+
+    #[test]
+    fn test_flat_measurement_needs_no_bands() {
+        let fit = fit_eq(&flat_response(), &flat_response(), &FitConfig::default());
+        assert!(fit.bands.is_empty());
+        assert_eq!(fit.preamp_db, 0.0);
+    }
+
+    #[test]
+    fn test_fit_corrects_resonance() {
+        // A headphone with a 6 dB resonance at 3 kHz should get a matching cut.
+        let config = FitConfig::default();
+        let mut resonance = BiquadFilter::new(config.sample_rate);
+        resonance.update_coeffs(FilterType::Peak, config.sample_rate, 3000.0, 4.0, 6.0);
+        let grid = log_spaced_grid(20.0, 20000.0, 400);
+        let points: Vec<(f32, f32)> = grid
+            .iter()
+            .map(|&f| {
+                let gain = resonance.frequency_response(f, config.sample_rate).norm();
+                (f, 20.0 * gain.log10())
+            })
+            .collect();
+        let measurement = FrequencyResponse::from_points(&points);
+
+        let fit = fit_eq(&measurement, &flat_response(), &config);
+
+        assert!(fit.rms_error_db < 0.5, "RMS error {}", fit.rms_error_db);
+        assert!(fit.bands.iter().any(|b| {
+            b.filter_type == FilterType::Peak
+                && (b.frequency - 3000.0).abs() < 300.0
+                && b.gain < -4.0
+        }));
+        assert!(fit.bands.len() <= config.num_bands);
+        assert!(fit.preamp_db <= 0.0);
+    }
+
+    #[test]
+    fn test_preamp_matches_returned_bands() {
+        // A dip needs a boost, so the preamp has to cover the rounded bands' peak.
+        let config = FitConfig::default();
+        let measurement = FrequencyResponse::from_points(&[
+            (20.0, 0.0),
+            (2000.0, 0.0),
+            (4000.0, -8.0),
+            (8000.0, 0.0),
+            (20000.0, 0.0),
+        ]);
+
+        let fit = fit_eq(&measurement, &flat_response(), &config);
+
+        let grid = log_spaced_grid(GRID_MIN_FREQ, GRID_MAX_FREQ, GRID_POINTS);
+        let mut total_db = vec![0.0; grid.len()];
+        for band in &fit.bands {
+            let mut filter = BiquadFilter::new(config.sample_rate);
+            filter.update_coeffs(
+                band.filter_type,
+                config.sample_rate,
+                band.frequency,
+                band.q,
+                band.gain,
+            );
+            for (total, &f) in total_db.iter_mut().zip(&grid) {
+                *total += 20.0
+                    * filter
+                        .frequency_response(f, config.sample_rate)
+                        .norm()
+                        .log10();
+            }
+        }
+        let peak_gain_db = total_db.iter().copied().fold(0.0, f32::max);
+        assert!(peak_gain_db > 0.0);
+        assert!(-fit.preamp_db >= peak_gain_db - 1e-3);
+        assert!(-fit.preamp_db < peak_gain_db + 0.1 + 1e-3);
+    }
+}
@@ -0,0 +1,383 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/head_tracking.rs
+
+//! Head tracking with opentrack's "UDP over network" output. Each packet holds six
+//! little-endian `f64`s: x, y, z in centimetres followed by yaw, pitch and roll in
+//! degrees. Only the rotation is used. Yaw is taken as positive to the right, pitch as
+//! positive looking up and roll as positive tilting towards the right shoulder; trackers
+//! that disagree can be fixed with the axis inversion in opentrack's output options.
+
+use nih_plug::nih_log;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, UdpSocket};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, AtomicU64, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// The port opentrack sends to by default.
+pub const DEFAULT_OPENTRACK_PORT: u16 = 4242;
+
+const PACKET_SIZE: usize = 6 * std::mem::size_of::<f64>();
+// How long the listener waits for a packet before looking at its settings again
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// How long to wait before retrying a port that couldn't be bound
+const BIND_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+/// The orientation of the listener's head, in degrees.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct HeadPose {
+    pub yaw: f32,
+    pub pitch: f32,
+    pub roll: f32,
+}
+
+/// Reads the rotation from an opentrack packet.
+pub fn parse_opentrack_packet(packet: &[u8]) -> Option<HeadPose> {
+    if packet.len() != PACKET_SIZE {
+        return None;
+    }
+    let value = |index: usize| {
+        let bytes = packet[index * 8..(index + 1) * 8].try_into().unwrap();
+        f64::from_le_bytes(bytes)
+    };
+    let (yaw, pitch, roll) = (value(3), value(4), value(5));
+    if !(yaw.is_finite() && pitch.is_finite() && roll.is_finite()) {
+        return None;
+    }
+    Some(HeadPose {
+        yaw: yaw as f32,
+        pitch: pitch as f32,
+        roll: roll as f32,
+    })
+}
+
+/// Where a source at the given azimuth and elevation lies relative to a head with the
+/// given pose. Azimuths are positive to the right, as on the speaker parameters.
+pub fn head_relative_direction(azimuth: f32, elevation: f32, pose: HeadPose) -> (f32, f32) {
+    let (azimuth, elevation) = (azimuth.to_radians(), elevation.to_radians());
+    // x to the right, y up, z to the front
+    let mut x = elevation.cos() * azimuth.sin();
+    let mut y = elevation.sin();
+    let mut z = elevation.cos() * azimuth.cos();
+
+    // Undo the head's rotations in reverse order: yaw, then pitch, then roll
+    let (sin, cos) = pose.yaw.to_radians().sin_cos();
+    (x, z) = (x * cos - z * sin, x * sin + z * cos);
+    let (sin, cos) = pose.pitch.to_radians().sin_cos();
+    (y, z) = (y * cos - z * sin, y * sin + z * cos);
+    let (sin, cos) = pose.roll.to_radians().sin_cos();
+    (x, y) = (x * cos - y * sin, x * sin + y * cos);
+
+    (
+        x.atan2(z).to_degrees(),
+        y.clamp(-1.0, 1.0).asin().to_degrees(),
+    )
+}
+
+/// The latest pose and the listener's settings, shared between the listener thread,
+/// the audio thread and the editor.
+pub struct HeadTrackingState {
+    enabled: AtomicBool,
+    port: AtomicU16,
+    // The port the listener is bound to, or 0
+    bound_port: AtomicU16,
+    // The pose as `f32` bits, and the pose recentring subtracts from it
+    pose: [AtomicU32; 3],
+    center: [AtomicU32; 3],
+    started: Instant,
+    // When the last packet arrived, in milliseconds after `started`, or `u64::MAX`
+    last_packet_ms: AtomicU64,
+    stop: AtomicBool,
+}
+
+impl Default for HeadTrackingState {
+    fn default() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            port: AtomicU16::new(DEFAULT_OPENTRACK_PORT),
+            bound_port: AtomicU16::new(0),
+            pose: Default::default(),
+            center: Default::default(),
+            started: Instant::now(),
+            last_packet_ms: AtomicU64::new(u64::MAX),
+            stop: AtomicBool::new(false),
+        }
+    }
+}
+
+impl HeadTrackingState {
+    /// Starts or stops listening, or moves to another port. The listener thread picks
+    /// the change up within `POLL_INTERVAL`. Port 0 binds to any free port.
+    pub fn set_listening(&self, enabled: bool, port: u16) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        self.port.store(port, Ordering::Relaxed);
+    }
+
+    pub fn bound_port(&self) -> Option<u16> {
+        match self.bound_port.load(Ordering::Relaxed) {
+            0 => None,
+            port => Some(port),
+        }
+    }
+
+    /// The latest pose relative to the recentred one.
+    pub fn pose(&self) -> HeadPose {
+        let [yaw, pitch, roll] = [0, 1, 2].map(|axis| {
+            f32::from_bits(self.pose[axis].load(Ordering::Relaxed))
+                - f32::from_bits(self.center[axis].load(Ordering::Relaxed))
+        });
+        HeadPose { yaw, pitch, roll }
+    }
+
+    /// Makes the current pose the one facing the speakers.
+    pub fn recenter(&self) {
+        for (center, pose) in self.center.iter().zip(&self.pose) {
+            center.store(pose.load(Ordering::Relaxed), Ordering::Relaxed);
+        }
+    }
+
+    pub fn time_since_last_packet(&self) -> Option<Duration> {
+        match self.last_packet_ms.load(Ordering::Relaxed) {
+            u64::MAX => None,
+            ms => Some(
+                self.started
+                    .elapsed()
+                    .saturating_sub(Duration::from_millis(ms)),
+            ),
+        }
+    }
+
+    /// Takes a pose from a source other than the listener, like the OSC server.
+    pub fn set_pose(&self, pose: HeadPose) {
+        for (axis, value) in self.pose.iter().zip([pose.yaw, pose.pitch, pose.roll]) {
+            axis.store(value.to_bits(), Ordering::Relaxed);
+        }
+        self.last_packet_ms
+            .store(self.started.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Runs the thread that receives opentrack packets into a `HeadTrackingState`. The
+/// socket is only open while tracking is enabled. Dropping the tracker stops the thread.
+pub struct HeadTracker {
+    state: Arc<HeadTrackingState>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl HeadTracker {
+    pub fn spawn(state: Arc<HeadTrackingState>) -> std::io::Result<Self> {
+        state.stop.store(false, Ordering::Relaxed);
+        let thread_state = state.clone();
+        let thread = thread::Builder::new()
+            .name("opentrack-listener".to_string())
+            .spawn(move || listen(&thread_state))?;
+        Ok(Self {
+            state,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for HeadTracker {
+    fn drop(&mut self) {
+        self.state.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn listen(state: &HeadTrackingState) {
+    // The socket and the port that was asked for when it was bound
+    let mut socket: Option<(UdpSocket, u16)> = None;
+    let mut failed_bind: Option<(u16, Instant)> = None;
+    let mut packet = [0u8; 64];
+
+    while !state.stop.load(Ordering::Relaxed) {
+        let enabled = state.enabled.load(Ordering::Relaxed);
+        let port = state.port.load(Ordering::Relaxed);
+        if !enabled || socket.as_ref().is_some_and(|(_, bound)| *bound != port) {
+            socket = None;
+            state.bound_port.store(0, Ordering::Relaxed);
+        }
+        if !enabled {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+
+        if socket.is_none() {
+            let retry_pending = failed_bind.is_some_and(|(failed_port, failed_at)| {
+                failed_port == port && failed_at.elapsed() < BIND_RETRY_INTERVAL
+            });
+            if retry_pending {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            // Trackers often run on another machine, so all interfaces are listened on
+            let bound = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port)).and_then(|new_socket| {
+                new_socket.set_read_timeout(Some(POLL_INTERVAL))?;
+                let local_port = new_socket.local_addr()?.port();
+                Ok((new_socket, local_port))
+            });
+            match bound {
+                Ok((new_socket, local_port)) => {
+                    nih_log!("Listening for opentrack packets on UDP port {}", local_port);
+                    state.bound_port.store(local_port, Ordering::Relaxed);
+                    socket = Some((new_socket, port));
+                    failed_bind = None;
+                }
+                Err(e) => {
+                    nih_log!("Failed to listen on UDP port {}: {}", port, e);
+                    failed_bind = Some((port, Instant::now()));
+                    continue;
+                }
+            }
+        }
+
+        let Some((socket, _)) = &socket else {
+            continue;
+        };
+        match socket.recv(&mut packet) {
+            Ok(len) => {
+                if let Some(pose) = parse_opentrack_packet(&packet[..len]) {
+                    state.set_pose(pose);
+                }
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+            Err(e) => {
+                nih_log!("Failed to receive opentrack packet: {}", e);
+                thread::sleep(POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opentrack_packet(pose: HeadPose) -> Vec<u8> {
+        [
+            0.0,
+            0.0,
+            0.0,
+            pose.yaw as f64,
+            pose.pitch as f64,
+            pose.roll as f64,
+        ]
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect()
+    }
+
+    fn assert_direction(actual: (f32, f32), expected: (f32, f32)) {
+        assert!(
+            (actual.0 - expected.0).abs() < 1e-3 && (actual.1 - expected.1).abs() < 1e-3,
+            "Expected {:?}, got {:?}",
+            expected,
+            actual
+        );
+    }
+
+    #[test]
+    fn test_parses_rotation_from_packet() {
+        let pose = HeadPose {
+            yaw: 12.5,
+            pitch: -3.0,
+            roll: 45.0,
+        };
+        assert_eq!(parse_opentrack_packet(&opentrack_packet(pose)), Some(pose));
+        assert_eq!(parse_opentrack_packet(&[0; 40]), None);
+    }
+
+    #[test]
+    fn test_sources_move_against_the_head() {
+        let rest = HeadPose::default();
+        assert_direction(head_relative_direction(-30.0, 0.0, rest), (-30.0, 0.0));
+
+        // Turning right by 30 degrees puts the left speaker further left and the right
+        // one straight ahead
+        let turned = HeadPose { yaw: 30.0, ..rest };
+        assert_direction(head_relative_direction(-30.0, 0.0, turned), (-60.0, 0.0));
+        assert_direction(head_relative_direction(30.0, 0.0, turned), (0.0, 0.0));
+
+        let looking_up = HeadPose {
+            pitch: 20.0,
+            ..rest
+        };
+        assert_direction(head_relative_direction(0.0, 0.0, looking_up), (0.0, -20.0));
+
+        // With the head tilted onto the right shoulder, the source to the right is above
+        let tilted = HeadPose { roll: 90.0, ..rest };
+        let (_, elevation) = head_relative_direction(90.0, 0.0, tilted);
+        assert!(
+            (elevation - 90.0).abs() < 1e-3,
+            "Elevation of {}",
+            elevation
+        );
+    }
+
+    #[test]
+    fn test_receives_replayed_motion() {
+        let state = Arc::new(HeadTrackingState::default());
+        state.set_listening(true, 0);
+        let _tracker = HeadTracker::spawn(state.clone()).unwrap();
+
+        let started = Instant::now();
+        let port = loop {
+            if let Some(port) = state.bound_port() {
+                break port;
+            }
+            assert!(started.elapsed() < Duration::from_secs(5), "Never bound");
+            thread::sleep(Duration::from_millis(5));
+        };
+
+        // A head turning right and nodding, as recorded from opentrack
+        let motion: Vec<HeadPose> = (0..50)
+            .map(|frame| {
+                let t = frame as f32 / 49.0;
+                HeadPose {
+                    yaw: 40.0 * t,
+                    pitch: 10.0 * (t * std::f32::consts::PI).sin(),
+                    roll: -5.0 * t,
+                }
+            })
+            .collect();
+        let sender = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        for pose in &motion {
+            sender
+                .send_to(&opentrack_packet(*pose), (Ipv4Addr::LOCALHOST, port))
+                .unwrap();
+            thread::sleep(Duration::from_millis(2));
+        }
+
+        let last = *motion.last().unwrap();
+        let started = Instant::now();
+        while state.pose() != last {
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "Ended at {:?}",
+                state.pose()
+            );
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert!(state.time_since_last_packet().is_some());
+
+        state.recenter();
+        assert_eq!(state.pose(), HeadPose::default());
+    }
+}
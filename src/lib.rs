@@ -12,37 +12,174 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use crossbeam_channel::Sender;
+use crossbeam_channel::{Receiver, Sender};
 use nih_plug::prelude::*;
 use nih_plug_egui::{EguiState, create_egui_editor, egui, widgets};
 use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 use strum::IntoEnumIterator;
 
 // Make sure our modules are declared
 mod autoeq_parser;
+mod device_profiles;
 mod dsp;
+mod eq_fitter;
+mod head_tracking;
+mod presets;
+mod remote;
+pub mod render;
 mod sofa;
+pub mod standalone;
 mod ui;
 
-use crate::autoeq_parser::BandSetting;
-use crate::dsp::convolution::ConvolutionEngine;
-use crate::dsp::parametric_eq::{BandConfig, FilterType, StereoParametricEQ};
-use crate::sofa::loader::MySofa;
+use crate::autoeq_parser::{AutoEqProfile, BandSetting};
+use crate::device_profiles::{DeviceProfile, DeviceProfiles};
+use crate::dsp::analyzer::{
+    AnalyzerResolution, AnalyzerSettings, AnalyzerTap, AnalyzerTaps, SpectrumAnalyzer,
+};
+use crate::dsp::bypass::MatchedBypass;
+use crate::dsp::convolution::{ConvolutionEngine, HrirSet};
+use crate::dsp::limiter::TruePeakLimiter;
+use crate::dsp::linear_phase_eq::{LinearPhaseEq, LinearPhaseKernel, LinearPhaseRequest};
+use crate::dsp::loudness::LoudnessCompensation;
+use crate::dsp::meter::{LevelMeter, MeterReadings};
+use crate::dsp::parametric_eq::{
+    BandChannel, BandConfig, BiquadDesign, DynamicConfig, EqChannelMode, EqPhaseMode, EqResponse,
+    EqResponseRequest, FilterType, PreampMode, StereoParametricEQ,
+};
+use crate::eq_fitter::{EqFit, FitConfig, TargetCurve};
+use crate::head_tracking::{
+    DEFAULT_OPENTRACK_PORT, HeadPose, HeadTracker, HeadTrackingState, head_relative_direction,
+};
+use crate::presets::{self, Preset};
+#[cfg(unix)]
+use crate::remote::json_rpc::{JsonRpcContext, JsonRpcServer, JsonRpcState, default_socket_path};
+use crate::remote::midi::{MidiDecoder, MidiMapping, MidiMessage};
+use crate::remote::osc::{DEFAULT_OSC_FEEDBACK_PORT, DEFAULT_OSC_PORT, OscServer, OscServerState};
+use crate::remote::params::RemoteParams;
+use crate::remote::worker::{ContextCapture, ContextSlot, RemoteWorker, RemoteWorkerContext};
+use crate::remote::{REMOTE_QUEUE_SIZE, RemoteCommand, RemoteValue};
+use crate::sofa::loader::{MySofa, SofaError};
+use crate::standalone::audio::DeviceMonitor;
+use crate::ui::eq_curve::{EqCurve, EqCurveView};
+use crate::ui::level_meter::{LevelMeters, MeterDisplay};
 use crate::ui::speaker_visualizer::SpeakerVisualizer;
+use crate::ui::spectrum_view::{SpectrumView, tap_color};
 use cpal::traits::{DeviceTrait, HostTrait};
 use egui_file_dialog::FileDialog;
 
+/// The number of active EQ bands in a fresh configuration.
 const NUM_EQ_BANDS: usize = 10;
+/// Hosts require a fixed parameter list, so all `MAX_EQ_BANDS` band slots always exist
+/// as parameters and `eq_band_count` decides how many of them are active. Band `n`
+/// keeps the same parameter IDs no matter how many bands are in use.
+const MAX_EQ_BANDS: usize = 32;
+
+/// The true-peak level the safety limiter holds the output below.
+const LIMITER_CEILING_DB: f32 = -1.0;
+
+/// The loudness level, in phon, at which the output gain's 0 dB is assumed to play.
+const DEFAULT_LOUDNESS_REFERENCE_PHON: f32 = 80.0;
+
+/// The distance HRIRs are fetched at, a typical nearfield monitor distance.
+const HRIR_DISTANCE_M: f32 = 1.2;
+/// The fastest HRIRs are refetched while the speakers or the head move.
+const HRIR_UPDATE_INTERVAL_MS: f32 = 20.0;
+/// How far a speaker has to move relative to the head before its HRIRs are refetched.
+const HRIR_UPDATE_THRESHOLD_DEG: f32 = 0.5;
+/// The fastest the EQ's peak gain is searched again while its bands are moving.
+const PEAK_GAIN_UPDATE_INTERVAL_MS: f32 = 10.0;
+
+fn default_eq_band_count() -> usize {
+    NUM_EQ_BANDS
+}
+
+fn default_loudness_reference() -> f32 {
+    DEFAULT_LOUDNESS_REFERENCE_PHON
+}
+
+fn default_head_tracking_port() -> u16 {
+    DEFAULT_OPENTRACK_PORT
+}
+
+fn default_osc_port() -> u16 {
+    DEFAULT_OSC_PORT
+}
+
+fn default_osc_feedback_port() -> u16 {
+    DEFAULT_OSC_FEEDBACK_PORT
+}
 
 pub enum Task {
     LoadSofa(PathBuf),
-    LoadAutoEq(PathBuf, Arc<Mutex<Option<Vec<BandSetting>>>>),
-    RequestEqResponse(Sender<Vec<f32>>),
+    LoadAutoEq(PathBuf, Arc<Mutex<Option<AutoEqProfile>>>),
+    FitEq(PathBuf, TargetCurve, usize, Arc<Mutex<Option<EqFit>>>),
+    DesignLinearPhaseEq(
+        LinearPhaseRequest,
+        u64,
+        Arc<Mutex<Option<(u64, LinearPhaseKernel)>>>,
+    ),
+    RequestEqResponse(EqResponseRequest, Sender<EqResponse>),
+    FetchHrirs(
+        [(f32, f32); 2],
+        Arc<Mutex<Option<HrirSet>>>,
+        Arc<AtomicBool>,
+    ),
+}
+
+/// What the processing needs from the host running it. nih-plug's contexts provide it
+/// in plugin hosts, and the standalone app's audio host provides it for the devices it
+/// opens itself, so both run the same `setup` and `process_stereo`.
+pub(crate) trait ProcessHost {
+    fn execute_background(&mut self, task: Task);
+    fn set_latency_samples(&mut self, samples: u32);
+}
+
+struct InitContextHost<'a, C>(&'a mut C);
+
+impl<C: InitContext<OpenHeadstagePlugin>> ProcessHost for InitContextHost<'_, C> {
+    fn execute_background(&mut self, task: Task) {
+        self.0.execute(task);
+    }
+
+    fn set_latency_samples(&mut self, samples: u32) {
+        self.0.set_latency_samples(samples);
+    }
+}
+
+struct ProcessContextHost<'a, C>(&'a mut C);
+
+impl<C: ProcessContext<OpenHeadstagePlugin>> ProcessHost for ProcessContextHost<'_, C> {
+    fn execute_background(&mut self, task: Task) {
+        self.0.execute_background(task);
+    }
+
+    fn set_latency_samples(&mut self, samples: u32) {
+        self.0.set_latency_samples(samples);
+    }
+}
+
+/// Fetches the HRIRs of both speakers at the given head-relative azimuths and
+/// elevations, with azimuths positive to the right. libmysofa interpolates between the
+/// nearest measured directions.
+fn fetch_hrirs(sofa: &MySofa, speakers: [(f32, f32); 2]) -> Result<HrirSet, SofaError> {
+    let irs = speaker_irs(sofa, speakers)?;
+    Ok(HrirSet::new(irs.each_ref().map(Vec::as_slice)))
+}
+
+/// The HRIRs `fetch_hrirs` prepares, in `ConvolutionPath` order.
+fn speaker_irs(sofa: &MySofa, speakers: [(f32, f32); 2]) -> Result<[Vec<f32>; 4], SofaError> {
+    // AES69 azimuths are positive to the left
+    let [(azimuth_l, elevation_l), (azimuth_r, elevation_r)] = speakers;
+    let (lsl, lsr) = sofa.get_hrtf_irs(-azimuth_l, elevation_l, HRIR_DISTANCE_M)?;
+    let (rsl, rsr) = sofa.get_hrtf_irs(-azimuth_r, elevation_r, HRIR_DISTANCE_M)?;
+    Ok([lsl, lsr, rsl, rsr])
 }
 
 #[derive(Params)]
@@ -57,6 +194,18 @@ pub struct EqBandParams {
     pub q: FloatParam,
     #[id = "gain"]
     pub gain: FloatParam,
+    #[id = "ch"]
+    pub channel: EnumParam<BandChannel>,
+    #[id = "dyn"]
+    pub dynamic: BoolParam,
+    #[id = "thr"]
+    pub threshold: FloatParam,
+    #[id = "ratio"]
+    pub ratio: FloatParam,
+    #[id = "atk"]
+    pub attack: FloatParam,
+    #[id = "rel"]
+    pub release: FloatParam,
 }
 
 impl Default for EqBandParams {
@@ -94,6 +243,71 @@ impl Default for EqBandParams {
             )
             .with_unit(" dB")
             .with_smoother(SmoothingStyle::Linear(50.0)),
+            channel: EnumParam::new("Channel", BandChannel::Both),
+            dynamic: BoolParam::new("Dynamic", false),
+            threshold: FloatParam::new(
+                "Threshold",
+                -20.0,
+                FloatRange::Linear {
+                    min: -60.0,
+                    max: 0.0,
+                },
+            )
+            .with_unit(" dB"),
+            ratio: FloatParam::new(
+                "Ratio",
+                2.0,
+                FloatRange::Skewed {
+                    min: 1.0,
+                    max: 20.0,
+                    factor: FloatRange::skew_factor(-1.0),
+                },
+            )
+            .with_unit(":1"),
+            attack: FloatParam::new(
+                "Attack",
+                5.0,
+                FloatRange::Skewed {
+                    min: 0.1,
+                    max: 100.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms"),
+            release: FloatParam::new(
+                "Release",
+                100.0,
+                FloatRange::Skewed {
+                    min: 5.0,
+                    max: 1000.0,
+                    factor: FloatRange::skew_factor(-2.0),
+                },
+            )
+            .with_unit(" ms"),
+        }
+    }
+}
+
+impl EqBandParams {
+    fn to_band_setting(&self) -> BandSetting {
+        BandSetting {
+            enabled: self.enabled.value(),
+            filter_type: self.filter_type.value(),
+            frequency: self.frequency.value(),
+            q: self.q.value(),
+            gain: self.gain.value(),
+            channel: self.channel.value(),
+            dynamic: self.dynamic.value(),
+            dynamics: self.dynamic_config(),
+        }
+    }
+
+    fn dynamic_config(&self) -> DynamicConfig {
+        DynamicConfig {
+            threshold_db: self.threshold.value(),
+            ratio: self.ratio.value(),
+            attack_ms: self.attack.value(),
+            release_ms: self.release.value(),
         }
     }
 }
@@ -110,9 +324,35 @@ pub struct OpenHeadstageParams {
     pub audio_host: Arc<RwLock<String>>,
     #[persist = "audio-device"]
     pub audio_device: Arc<RwLock<String>>,
+    // Empty for the host's default input device
+    #[persist = "audio-input-device"]
+    pub audio_input_device: Arc<RwLock<String>>,
+
+    #[persist = "osc-enabled"]
+    pub osc_enabled: Arc<AtomicBool>,
+    #[persist = "osc-port"]
+    pub osc_port: Arc<AtomicU16>,
+    // 0 replies to the port the commands come from
+    #[persist = "osc-feedback-port"]
+    pub osc_feedback_port: Arc<AtomicU16>,
+    // Off listens on the loopback interface only
+    #[persist = "osc-allow-network"]
+    pub osc_allow_network: Arc<AtomicBool>,
+    #[persist = "midi-mappings"]
+    pub midi_mappings: Arc<RwLock<Vec<MidiMapping>>>,
+    #[persist = "device-profiles"]
+    pub device_profiles: Arc<RwLock<DeviceProfiles>>,
+    // The JSON-RPC socket is only opened by the standalone app. An empty path uses the
+    // default one.
+    #[persist = "json-rpc-enabled"]
+    pub json_rpc_enabled: Arc<AtomicBool>,
+    #[persist = "json-rpc-socket"]
+    pub json_rpc_socket: Arc<RwLock<String>>,
 
     #[id = "bypass"]
     pub master_bypass: BoolParam,
+    #[id = "bypass_match"]
+    pub bypass_loudness_match: BoolParam,
 
     #[id = "out_gain"]
     pub output_gain: FloatParam,
@@ -127,9 +367,39 @@ pub struct OpenHeadstageParams {
     #[id = "el_r"]
     pub speaker_elevation_right: FloatParam,
 
+    #[id = "head_tracking"]
+    pub head_tracking: BoolParam,
+    #[persist = "head-tracking-port"]
+    pub head_tracking_port: Arc<AtomicU16>,
+
     #[id = "eq_enable"]
     pub eq_enable: BoolParam,
 
+    #[id = "eq_mode"]
+    pub eq_channel_mode: EnumParam<EqChannelMode>,
+
+    #[id = "eq_design"]
+    pub eq_design: EnumParam<BiquadDesign>,
+
+    #[id = "eq_phase"]
+    pub eq_phase_mode: EnumParam<EqPhaseMode>,
+
+    #[id = "preamp_mode"]
+    pub eq_preamp_mode: EnumParam<PreampMode>,
+    #[id = "preamp"]
+    pub eq_preamp: FloatParam,
+
+    #[id = "limiter"]
+    pub safety_limiter: BoolParam,
+
+    #[id = "loudness"]
+    pub loudness_compensation: BoolParam,
+    #[id = "loudness_ref"]
+    pub loudness_reference: FloatParam,
+
+    #[persist = "eq-band-count"]
+    pub eq_band_count: Arc<AtomicUsize>,
+
     #[nested(array, group = "EQ Bands")]
     pub eq_bands: Vec<EqBandParams>,
 }
@@ -137,7 +407,7 @@ pub struct OpenHeadstageParams {
 impl OpenHeadstageParams {
     fn new(config: StandaloneConfig) -> Self {
         let mut eq_bands = Vec::new();
-        for i in 0..NUM_EQ_BANDS {
+        for i in 0..MAX_EQ_BANDS {
             let band_config = config.eq_bands.get(i).cloned().unwrap_or_default();
             eq_bands.push(EqBandParams {
                 enabled: BoolParam::new("Enabled", band_config.enabled),
@@ -172,6 +442,47 @@ impl OpenHeadstageParams {
                 )
                 .with_unit(" dB")
                 .with_smoother(SmoothingStyle::Linear(50.0)),
+                channel: EnumParam::new("Channel", band_config.channel),
+                dynamic: BoolParam::new("Dynamic", band_config.dynamic),
+                threshold: FloatParam::new(
+                    "Threshold",
+                    band_config.dynamics.threshold_db,
+                    FloatRange::Linear {
+                        min: -60.0,
+                        max: 0.0,
+                    },
+                )
+                .with_unit(" dB"),
+                ratio: FloatParam::new(
+                    "Ratio",
+                    band_config.dynamics.ratio,
+                    FloatRange::Skewed {
+                        min: 1.0,
+                        max: 20.0,
+                        factor: FloatRange::skew_factor(-1.0),
+                    },
+                )
+                .with_unit(":1"),
+                attack: FloatParam::new(
+                    "Attack",
+                    band_config.dynamics.attack_ms,
+                    FloatRange::Skewed {
+                        min: 0.1,
+                        max: 100.0,
+                        factor: FloatRange::skew_factor(-2.0),
+                    },
+                )
+                .with_unit(" ms"),
+                release: FloatParam::new(
+                    "Release",
+                    band_config.dynamics.release_ms,
+                    FloatRange::Skewed {
+                        min: 5.0,
+                        max: 1000.0,
+                        factor: FloatRange::skew_factor(-2.0),
+                    },
+                )
+                .with_unit(" ms"),
             });
         }
 
@@ -180,7 +491,20 @@ impl OpenHeadstageParams {
             sofa_file_path: Arc::new(RwLock::new(config.sofa_file_path)),
             audio_host: Arc::new(RwLock::new(config.audio_host)),
             audio_device: Arc::new(RwLock::new(config.audio_device)),
+            audio_input_device: Arc::new(RwLock::new(config.audio_input_device)),
+            osc_enabled: Arc::new(AtomicBool::new(config.osc_enabled)),
+            osc_port: Arc::new(AtomicU16::new(config.osc_port)),
+            osc_feedback_port: Arc::new(AtomicU16::new(config.osc_feedback_port)),
+            osc_allow_network: Arc::new(AtomicBool::new(config.osc_allow_network)),
+            midi_mappings: Arc::new(RwLock::new(config.midi_mappings)),
+            device_profiles: Arc::new(RwLock::new(config.device_profiles)),
+            json_rpc_enabled: Arc::new(AtomicBool::new(config.json_rpc_enabled)),
+            json_rpc_socket: Arc::new(RwLock::new(config.json_rpc_socket)),
             master_bypass: BoolParam::new("Bypass", config.master_bypass),
+            bypass_loudness_match: BoolParam::new(
+                "Loudness-Matched Bypass",
+                config.bypass_loudness_match,
+            ),
             output_gain: FloatParam::new(
                 "Output Gain",
                 config.output_gain,
@@ -233,49 +557,164 @@ impl OpenHeadstageParams {
             )
             .with_smoother(SmoothingStyle::Linear(50.0))
             .with_unit("°"),
+            head_tracking: BoolParam::new("Head Tracking", config.head_tracking),
+            head_tracking_port: Arc::new(AtomicU16::new(config.head_tracking_port)),
             eq_enable: BoolParam::new("Enable EQ", config.eq_enable),
+            eq_channel_mode: EnumParam::new("EQ Channel Mode", config.eq_channel_mode),
+            eq_design: EnumParam::new("EQ Filter Design", config.eq_design),
+            eq_phase_mode: EnumParam::new("EQ Phase Mode", config.eq_phase_mode),
+            eq_preamp_mode: EnumParam::new("Preamp Mode", config.eq_preamp_mode),
+            eq_preamp: FloatParam::new(
+                "Preamp",
+                config.eq_preamp,
+                FloatRange::Linear {
+                    min: -30.0,
+                    max: 6.0,
+                },
+            )
+            .with_unit(" dB")
+            .with_step_size(0.1),
+            safety_limiter: BoolParam::new("True-Peak Limiter", config.safety_limiter),
+            loudness_compensation: BoolParam::new(
+                "Loudness Compensation",
+                config.loudness_compensation,
+            ),
+            loudness_reference: FloatParam::new(
+                "Reference Level",
+                config.loudness_reference,
+                FloatRange::Linear {
+                    min: 60.0,
+                    max: 90.0,
+                },
+            )
+            .with_unit(" phon")
+            .with_step_size(0.5),
+            eq_band_count: Arc::new(AtomicUsize::new(
+                config.eq_band_count.clamp(1, MAX_EQ_BANDS),
+            )),
             eq_bands,
         }
     }
+
+    /// Moves the smoothers to the parameters' values. nih-plug's wrappers do this when
+    /// they initialize the plugin, the standalone app's audio host does it for the
+    /// parameters it makes from the settings.
+    pub(crate) fn reset_smoothers(&self) {
+        for (_, param, _) in self.param_map() {
+            if let ParamPtr::FloatParam(param) = param {
+                // SAFETY: The parameters outlive the pointers
+                let param = unsafe { &*param };
+                param.smoothed.reset(param.value());
+            }
+        }
+    }
 }
 
 #[derive(PartialEq, Eq, Clone, Copy)]
 enum FileDialogRequest {
     Sofa,
     AutoEq,
+    Measurement,
+    TargetCurve,
+    ImportPreset,
+    ExportPreset,
+}
+
+/// An EQ response computed in the background, recomputed whenever its request changes.
+struct ResponseCurve {
+    request: Option<EqResponseRequest>,
+    response: Option<EqResponse>,
+    sender: Sender<EqResponse>,
+    receiver: Receiver<EqResponse>,
+}
+
+impl ResponseCurve {
+    fn new() -> Self {
+        let (sender, receiver) = crossbeam_channel::unbounded();
+        Self {
+            request: None,
+            response: None,
+            sender,
+            receiver,
+        }
+    }
+
+    /// Sends `request` to the background thread if it differs from the last one, and
+    /// picks up the newest finished response. `None` clears the curve.
+    fn update(
+        &mut self,
+        request: Option<EqResponseRequest>,
+        async_executor: &AsyncExecutor<OpenHeadstagePlugin>,
+    ) {
+        if self.request != request {
+            if let Some(request) = &request {
+                async_executor.execute_background(Task::RequestEqResponse(
+                    request.clone(),
+                    self.sender.clone(),
+                ));
+            } else {
+                self.response = None;
+            }
+            self.request = request;
+        }
+        if let Some(response) = self.receiver.try_iter().last() {
+            // Drop responses that arrive after the curve was cleared
+            if self.request.is_some() {
+                self.response = Some(response);
+            }
+        }
+    }
 }
 
 struct EditorState {
     file_dialog: FileDialog,
     file_dialog_request: Option<FileDialogRequest>,
-    auto_eq_result: Arc<Mutex<Option<Vec<BandSetting>>>>,
-    loaded_eq_settings: Option<Vec<BandSetting>>,
+    auto_eq_result: Arc<Mutex<Option<AutoEqProfile>>>,
+    loaded_eq_settings: Option<AutoEqProfile>,
     show_eq_editor: bool,
     eq_editor_bands: Vec<BandSetting>,
-    #[allow(dead_code)]
-    eq_response: Arc<Mutex<Option<Vec<f32>>>>,
+    eq_editor_band_count: usize,
+    eq_editor_channel_mode: EqChannelMode,
+    fit_target: TargetCurve,
+    eq_fit_result: Arc<Mutex<Option<EqFit>>>,
+    recommended_preamp: Option<f32>,
+    eq_curve_view: EqCurveView,
+    // The curves of the bands being edited and of the loaded AutoEQ profile
+    eq_curve: ResponseCurve,
+    loaded_eq_curve: ResponseCurve,
+    analyzer_settings: AnalyzerSettings,
+    analyzer_shown: [bool; AnalyzerTap::COUNT],
+    input_meter_display: MeterDisplay,
+    output_meter_display: MeterDisplay,
+    remote_params: RemoteParams,
+    // The parameter picked for MIDI learn
+    midi_learn_param: Option<String>,
 
     // State for audio device selection
     available_hosts: Vec<cpal::HostId>,
     available_devices: Vec<String>,
+    available_input_devices: Vec<String>,
     selected_host_id: cpal::HostId,
+    // The name to save the current settings under as a device profile
+    device_profile_name: String,
+
+    // State for the preset browser
+    preset_names: Vec<String>,
+    selected_preset: Option<String>,
+    preset_name: String,
+    // The outcome of the last preset action
+    preset_status: Option<String>,
 }
 
 impl EditorState {
     fn new(
-        auto_eq_result: Arc<Mutex<Option<Vec<BandSetting>>>>,
+        auto_eq_result: Arc<Mutex<Option<AutoEqProfile>>>,
         initial_eq_params: &[EqBandParams],
-        params: &OpenHeadstageParams,
+        params: &Arc<OpenHeadstageParams>,
     ) -> Self {
         let eq_editor_bands = initial_eq_params
             .iter()
-            .map(|p| BandSetting {
-                enabled: p.enabled.value(),
-                filter_type: p.filter_type.value(),
-                frequency: p.frequency.value(),
-                q: p.q.value(),
-                gain: p.gain.value(),
-            })
+            .map(EqBandParams::to_band_setting)
             .collect();
 
         let available_hosts = cpal::available_hosts();
@@ -285,6 +724,7 @@ impl EditorState {
             .unwrap_or_else(|| cpal::default_host().id());
 
         let available_devices = Self::get_output_devices_for_host(&selected_host_id);
+        let available_input_devices = standalone::devices::input_device_names(selected_host_id);
 
         Self {
             file_dialog: FileDialog::new(),
@@ -293,13 +733,88 @@ impl EditorState {
             loaded_eq_settings: None,
             show_eq_editor: false,
             eq_editor_bands,
-            eq_response: Arc::new(Mutex::new(None)),
+            eq_editor_band_count: params.eq_band_count.load(Ordering::Relaxed),
+            eq_editor_channel_mode: params.eq_channel_mode.value(),
+            fit_target: TargetCurve::HarmanOverEar2018,
+            eq_fit_result: Arc::new(Mutex::new(None)),
+            recommended_preamp: None,
+            eq_curve_view: EqCurveView::default(),
+            eq_curve: ResponseCurve::new(),
+            loaded_eq_curve: ResponseCurve::new(),
+            analyzer_settings: AnalyzerSettings::default(),
+            analyzer_shown: [true; AnalyzerTap::COUNT],
+            input_meter_display: MeterDisplay::default(),
+            output_meter_display: MeterDisplay::default(),
+            remote_params: RemoteParams::new(params.clone()),
+            midi_learn_param: None,
             available_hosts,
             available_devices,
+            available_input_devices,
             selected_host_id,
+            device_profile_name: String::new(),
+            preset_names: get_presets_dir()
+                .map(|dir| presets::list_presets(&dir))
+                .unwrap_or_default(),
+            selected_preset: None,
+            preset_name: String::new(),
+            preset_status: None,
+        }
+    }
+
+    /// Lists the saved presets again after one was added, renamed or removed, and selects
+    /// `selected`.
+    fn refresh_presets(&mut self, selected: Option<String>) {
+        self.preset_names = get_presets_dir()
+            .map(|dir| presets::list_presets(&dir))
+            .unwrap_or_default();
+        self.preset_name = selected.clone().unwrap_or_default();
+        self.selected_preset = selected;
+    }
+
+    /// Describes the bands in the EQ editor as they would be applied.
+    fn editor_response_request(&self, sample_rate: f32, design: BiquadDesign) -> EqResponseRequest {
+        EqResponseRequest {
+            sample_rate,
+            channel_mode: self.eq_editor_channel_mode,
+            design,
+            bands: self
+                .eq_editor_bands
+                .iter()
+                .take(self.eq_editor_band_count)
+                .map(BandSetting::to_band_config)
+                .collect(),
         }
     }
 
+    /// Describes the loaded AutoEQ profile as "Apply Loaded EQ" would apply it.
+    fn loaded_response_request(
+        &self,
+        sample_rate: f32,
+        design: BiquadDesign,
+    ) -> Option<EqResponseRequest> {
+        let profile = self.loaded_eq_settings.as_ref()?;
+        let channel_mode = if profile
+            .bands
+            .iter()
+            .any(|band| band.channel != BandChannel::Both)
+        {
+            EqChannelMode::LeftRight
+        } else {
+            EqChannelMode::Linked
+        };
+        Some(EqResponseRequest {
+            sample_rate,
+            channel_mode,
+            design,
+            bands: profile
+                .bands
+                .iter()
+                .take(MAX_EQ_BANDS)
+                .map(BandSetting::to_band_config)
+                .collect(),
+        })
+    }
+
     fn get_output_devices_for_host(host_id: &cpal::HostId) -> Vec<String> {
         cpal::host_from_id(*host_id)
             .ok()
@@ -317,27 +832,527 @@ pub struct OpenHeadstagePlugin {
     params: Arc<OpenHeadstageParams>,
     convolution_engine: ConvolutionEngine,
     sofa_loader: Arc<parking_lot::Mutex<Option<MySofa>>>,
+    // Bumped whenever a SOFA file is loaded, so the HRIRs are fetched again
+    sofa_revision: Arc<AtomicU32>,
+    hrir_result: Arc<Mutex<Option<HrirSet>>>,
+    // Set while HRIRs are being fetched, cleared by the background task
+    hrir_fetching: Arc<AtomicBool>,
+    // The SOFA revision and head-relative speaker directions of the newest request, and
+    // the samples processed since it was made
+    hrir_key: Option<(u32, [f32; 4])>,
+    samples_since_hrir_request: usize,
+    // The listener runs from the first `initialize` on and only opens its socket while
+    // head tracking is enabled
+    head_tracking: Arc<HeadTrackingState>,
+    head_tracker: Option<HeadTracker>,
+    // Runs the audio on the standalone app's own audio host, in place of `process`
+    audio_monitor: Option<DeviceMonitor>,
+    // Like the head tracking listener, the OSC server only opens its socket while it is
+    // enabled. Remote commands are applied by the remote worker.
+    osc_state: Arc<OscServerState>,
+    osc_server: Option<OscServer>,
+    remote_sender: Sender<RemoteCommand>,
+    remote_receiver: Receiver<RemoteCommand>,
+    // Control changes are decoded on the audio thread and applied by the remote worker
+    midi_decoder: MidiDecoder,
+    midi_sender: Sender<MidiMessage>,
+    midi_receiver: Receiver<MidiMessage>,
+    // The parameter the editor is learning a MIDI control for
+    midi_learn: Arc<Mutex<Option<String>>>,
+    // The worker applies the commands with the editor's context, from the first
+    // `initialize` on
+    gui_context: Arc<ContextSlot>,
+    remote_worker: Option<RemoteWorker>,
+    // AutoEQ profiles loaded by remote controls, for the editor to show
+    remote_auto_eq_result: Arc<Mutex<Option<AutoEqProfile>>>,
+    #[cfg(unix)]
+    json_rpc_state: Arc<JsonRpcState>,
+    #[cfg(unix)]
+    json_rpc_server: Option<JsonRpcServer>,
     parametric_eq: StereoParametricEQ,
+    // Samples processed since the EQ's peak gain was last brought up to date
+    samples_since_peak_gain_update: usize,
+    linear_phase_eq: LinearPhaseEq,
+    // Finished kernels, tagged with the serial number of their request
+    linear_phase_result: Arc<Mutex<Option<(u64, LinearPhaseKernel)>>>,
+    // Whether a kernel is being designed, the EQ state (enabled, revision) of the newest
+    // kernel that was requested, and that request's serial number. Results of older
+    // requests, such as ones made before `initialize` changed the sample rate, are
+    // dropped.
+    linear_phase_pending: bool,
+    linear_phase_key: Option<(bool, u64)>,
+    linear_phase_serial: u64,
+    // The phase mode the latency reported to the host is based on
+    eq_phase_mode: EqPhaseMode,
+    preamp_gain: Smoother<f32>,
+    loudness: LoudnessCompensation,
+    limiter: TruePeakLimiter,
     current_sample_rate: f32,
     has_logged_processing_start: AtomicBool,
-    auto_eq_result: Arc<Mutex<Option<Vec<BandSetting>>>>,
+    auto_eq_result: Arc<Mutex<Option<AutoEqProfile>>>,
+    // The distance between the EQ's peak output level and full scale, as `f32` bits
+    eq_headroom_db: Arc<AtomicU32>,
+    // `current_sample_rate` for the editor, as `f32` bits
+    shared_sample_rate: Arc<AtomicU32>,
+    // The latency reported to the host, for remote controls
+    shared_latency_samples: Arc<AtomicU32>,
+    // The audio thread feeds the taps, the editor reads and analyses them
+    analyzer_taps: AnalyzerTaps,
+    spectrum_analyzer: Arc<Mutex<SpectrumAnalyzer>>,
+    bypass: MatchedBypass,
+    // The convolution's input, sized for the largest block in `setup`
+    convolution_input: [Vec<f32>; 2],
+    // Levels at the start and the end of `process`, published to the editor
+    input_meter: LevelMeter,
+    output_meter: LevelMeter,
+    input_levels: Arc<MeterReadings>,
+    output_levels: Arc<MeterReadings>,
 }
 
 impl OpenHeadstagePlugin {
     pub fn new(sample_rate: f32, params: Arc<OpenHeadstageParams>) -> Self {
+        let (analyzer_taps, spectrum_analyzer) = dsp::analyzer::spectrum_analyzer();
+        let (remote_sender, remote_receiver) = crossbeam_channel::bounded(REMOTE_QUEUE_SIZE);
+        let (midi_sender, midi_receiver) = crossbeam_channel::bounded(REMOTE_QUEUE_SIZE);
         Self {
             params,
             convolution_engine: ConvolutionEngine::new(),
             sofa_loader: Arc::new(parking_lot::Mutex::new(None)),
-            parametric_eq: StereoParametricEQ::new(NUM_EQ_BANDS, sample_rate),
+            sofa_revision: Arc::new(AtomicU32::new(0)),
+            hrir_result: Arc::new(Mutex::new(None)),
+            hrir_fetching: Arc::new(AtomicBool::new(false)),
+            hrir_key: None,
+            samples_since_hrir_request: usize::MAX,
+            head_tracking: Arc::new(HeadTrackingState::default()),
+            head_tracker: None,
+            audio_monitor: None,
+            osc_state: Arc::new(OscServerState::default()),
+            osc_server: None,
+            remote_sender,
+            remote_receiver,
+            midi_decoder: MidiDecoder::default(),
+            midi_sender,
+            midi_receiver,
+            midi_learn: Arc::new(Mutex::new(None)),
+            gui_context: Arc::new(ContextSlot::default()),
+            remote_worker: None,
+            remote_auto_eq_result: Arc::new(Mutex::new(None)),
+            #[cfg(unix)]
+            json_rpc_state: Arc::new(JsonRpcState::default()),
+            #[cfg(unix)]
+            json_rpc_server: None,
+            parametric_eq: StereoParametricEQ::new(MAX_EQ_BANDS, sample_rate),
+            samples_since_peak_gain_update: usize::MAX,
+            linear_phase_eq: LinearPhaseEq::new(sample_rate),
+            linear_phase_result: Arc::new(Mutex::new(None)),
+            linear_phase_pending: false,
+            linear_phase_key: None,
+            linear_phase_serial: 0,
+            eq_phase_mode: EqPhaseMode::default(),
+            preamp_gain: Smoother::new(SmoothingStyle::Linear(50.0)),
+            loudness: LoudnessCompensation::new(sample_rate),
+            limiter: TruePeakLimiter::new(sample_rate, LIMITER_CEILING_DB),
             current_sample_rate: sample_rate,
             has_logged_processing_start: AtomicBool::new(false),
             auto_eq_result: Arc::new(Mutex::new(None)),
+            eq_headroom_db: Arc::new(AtomicU32::new(0.0f32.to_bits())),
+            shared_sample_rate: Arc::new(AtomicU32::new(sample_rate.to_bits())),
+            shared_latency_samples: Arc::new(AtomicU32::new(0)),
+            analyzer_taps,
+            spectrum_analyzer: Arc::new(Mutex::new(spectrum_analyzer)),
+            bypass: MatchedBypass::new(sample_rate, 0, 0),
+            convolution_input: [Vec::new(), Vec::new()],
+            input_meter: LevelMeter::new(sample_rate),
+            output_meter: LevelMeter::new(sample_rate),
+            input_levels: Arc::new(MeterReadings::default()),
+            output_levels: Arc::new(MeterReadings::default()),
+        }
+    }
+
+    fn latency_samples(&self) -> u32 {
+        self.limiter.latency_samples() + self.chain_latency_samples() as u32
+    }
+
+    /// The latency of the processing ahead of the bypass crossfade.
+    fn chain_latency_samples(&self) -> usize {
+        match self.eq_phase_mode {
+            EqPhaseMode::MinimumPhase => 0,
+            EqPhaseMode::LinearPhase => self.linear_phase_eq.latency_samples() as usize,
+        }
+    }
+
+    /// Installs a finished linear-phase kernel and starts designing a new one if the EQ
+    /// changed since the last request. Only one design runs at a time; changes made in
+    /// the meantime are picked up when it finishes.
+    fn update_linear_phase_kernel(&mut self, eq_enabled: bool, host: &mut impl ProcessHost) {
+        if let Some((serial, kernel)) = self
+            .linear_phase_result
+            .try_lock()
+            .and_then(|mut result| result.take())
+        {
+            if serial == self.linear_phase_serial {
+                self.linear_phase_eq.set_kernel(kernel);
+                self.linear_phase_pending = false;
+            }
+        }
+
+        let key = (eq_enabled, self.parametric_eq.revision());
+        if self.linear_phase_pending || self.linear_phase_key == Some(key) {
+            return;
+        }
+        let request = if eq_enabled {
+            LinearPhaseRequest::from_eq(&self.parametric_eq, self.current_sample_rate)
+        } else {
+            LinearPhaseRequest::flat(self.current_sample_rate)
+        };
+        self.linear_phase_key = Some(key);
+        self.linear_phase_pending = true;
+        self.linear_phase_serial = self.linear_phase_serial.wrapping_add(1);
+        host.execute_background(Task::DesignLinearPhaseEq(
+            request,
+            self.linear_phase_serial,
+            self.linear_phase_result.clone(),
+        ));
+    }
+
+    /// Crossfades to newly fetched HRIRs and fetches new ones when a speaker moved
+    /// relative to the head. Requests are at least `HRIR_UPDATE_INTERVAL_MS` apart and
+    /// only one fetch runs at a time, so fast head movements are followed in steps that
+    /// the convolution engine crossfades between.
+    fn update_hrirs(
+        &mut self,
+        speakers: [(f32, f32); 2],
+        num_samples: usize,
+        host: &mut impl ProcessHost,
+    ) {
+        if let Some(hrirs) = self
+            .hrir_result
+            .try_lock()
+            .and_then(|mut result| result.take())
+        {
+            self.convolution_engine.crossfade_to(hrirs);
         }
+        self.samples_since_hrir_request =
+            self.samples_since_hrir_request.saturating_add(num_samples);
+
+        let pose = if self.params.head_tracking.value() {
+            self.head_tracking.pose()
+        } else {
+            HeadPose::default()
+        };
+        let speakers =
+            speakers.map(|(azimuth, elevation)| head_relative_direction(azimuth, elevation, pose));
+        let [(azimuth_l, elevation_l), (azimuth_r, elevation_r)] = speakers;
+        let directions = [azimuth_l, elevation_l, azimuth_r, elevation_r];
+        let revision = self.sofa_revision.load(Ordering::Relaxed);
+        let unchanged = self.hrir_key.is_some_and(|(key_revision, key_directions)| {
+            key_revision == revision
+                && key_directions
+                    .iter()
+                    .zip(&directions)
+                    .all(|(old, new)| (old - new).abs() < HRIR_UPDATE_THRESHOLD_DEG)
+        });
+        let interval_samples =
+            (HRIR_UPDATE_INTERVAL_MS / 1000.0 * self.current_sample_rate) as usize;
+        if unchanged
+            || self.hrir_fetching.load(Ordering::Relaxed)
+            || self.samples_since_hrir_request < interval_samples
+        {
+            return;
+        }
+
+        self.hrir_key = Some((revision, directions));
+        self.samples_since_hrir_request = 0;
+        self.hrir_fetching.store(true, Ordering::Relaxed);
+        host.execute_background(Task::FetchHrirs(
+            speakers,
+            self.hrir_result.clone(),
+            self.hrir_fetching.clone(),
+        ));
+    }
+
+    /// Prepares the processing for a sample rate and block size, as `initialize` does in
+    /// plugin hosts, and loads the SOFA file from the settings.
+    pub(crate) fn setup(
+        &mut self,
+        sample_rate: f32,
+        max_buffer_size: usize,
+        host: &mut impl ProcessHost,
+    ) {
+        self.current_sample_rate = sample_rate;
+        self.shared_sample_rate
+            .store(self.current_sample_rate.to_bits(), Ordering::Relaxed);
+        self.parametric_eq = StereoParametricEQ::new(MAX_EQ_BANDS, self.current_sample_rate);
+        self.samples_since_peak_gain_update = usize::MAX;
+        self.convolution_engine = ConvolutionEngine::new();
+        self.convolution_input = [vec![0.0; max_buffer_size], vec![0.0; max_buffer_size]];
+        *self.hrir_result.lock() = None;
+        self.hrir_key = None;
+        self.samples_since_hrir_request = usize::MAX;
+        self.loudness = LoudnessCompensation::new(self.current_sample_rate);
+        self.input_meter = LevelMeter::new(self.current_sample_rate);
+        self.output_meter = LevelMeter::new(self.current_sample_rate);
+        self.limiter = TruePeakLimiter::new(self.current_sample_rate, LIMITER_CEILING_DB);
+        self.linear_phase_eq = LinearPhaseEq::new(self.current_sample_rate);
+        *self.linear_phase_result.lock() = None;
+        self.linear_phase_pending = false;
+        self.linear_phase_key = None;
+        self.eq_phase_mode = self.params.eq_phase_mode.value();
+        self.bypass = MatchedBypass::new(
+            self.current_sample_rate,
+            max_buffer_size,
+            self.linear_phase_eq.latency_samples() as usize,
+        );
+        self.bypass.set_delay(self.chain_latency_samples());
+        self.bypass.reset(self.params.master_bypass.value());
+        // The limiter delays the signal even when it is switched off, so the latency is
+        // the same in both states
+        host.set_latency_samples(self.latency_samples());
+        self.shared_latency_samples
+            .store(self.latency_samples(), Ordering::Relaxed);
+
+        let sofa_path_str = self.params.sofa_file_path.read();
+        if !sofa_path_str.is_empty() {
+            nih_log!("Attempting to load initial SOFA file: {}", sofa_path_str);
+            match MySofa::open(&sofa_path_str, self.current_sample_rate) {
+                Ok(sofa_loader) => {
+                    nih_log!("Successfully loaded SOFA file.");
+                    *self.sofa_loader.lock() = Some(sofa_loader)
+                }
+                Err(e) => nih_log!("Failed to load SOFA file '{}': {:?}", sofa_path_str, e),
+            }
+            self.sofa_revision.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Starts the head tracking listener, which only opens its socket while head tracking
+    /// is enabled. Its port can only be bound once, so only one instance per process
+    /// starts it.
+    pub(crate) fn start_head_tracker(&mut self) {
+        if self.head_tracker.is_none() {
+            match HeadTracker::spawn(self.head_tracking.clone()) {
+                Ok(head_tracker) => self.head_tracker = Some(head_tracker),
+                Err(e) => nih_log!("Failed to start the head tracking listener: {}", e),
+            }
+        }
+    }
+
+    /// Opens or closes the head tracking and OSC sockets to follow the settings.
+    fn update_listeners(&self) {
+        self.head_tracking.set_listening(
+            self.params.head_tracking.value(),
+            self.params.head_tracking_port.load(Ordering::Relaxed),
+        );
+        self.osc_state.set_listening(
+            self.params.osc_enabled.load(Ordering::Relaxed),
+            self.params.osc_port.load(Ordering::Relaxed),
+            self.params.osc_feedback_port.load(Ordering::Relaxed),
+            self.params.osc_allow_network.load(Ordering::Relaxed),
+        );
+    }
+
+    /// An instance for the standalone app's audio host, which runs the audio on the
+    /// devices picked in the editor. It shares the parameters, the SOFA file and what the
+    /// editor shows with this instance, and takes over the analyzer's taps.
+    fn audio_twin(&mut self) -> Self {
+        let mut twin = Self::new(self.current_sample_rate, self.params.clone());
+        twin.sofa_loader = self.sofa_loader.clone();
+        twin.sofa_revision = self.sofa_revision.clone();
+        twin.head_tracking = self.head_tracking.clone();
+        twin.osc_state = self.osc_state.clone();
+        twin.eq_headroom_db = self.eq_headroom_db.clone();
+        twin.shared_sample_rate = self.shared_sample_rate.clone();
+        twin.shared_latency_samples = self.shared_latency_samples.clone();
+        twin.input_levels = self.input_levels.clone();
+        twin.output_levels = self.output_levels.clone();
+        std::mem::swap(&mut twin.analyzer_taps, &mut self.analyzer_taps);
+        twin
+    }
+
+    /// Replaces the parameters, for the headless host when the settings file is saved.
+    /// `setup` has to run again before processing continues.
+    pub(crate) fn set_params(&mut self, params: Arc<OpenHeadstageParams>) {
+        self.params = params;
+    }
+
+    /// Processes a block of stereo audio, at most as long as the block size passed to
+    /// `setup`.
+    pub(crate) fn process_stereo(
+        &mut self,
+        left: &mut [f32],
+        right: &mut [f32],
+        host: &mut impl ProcessHost,
+    ) {
+        let phase_mode = self.params.eq_phase_mode.value();
+        if phase_mode != self.eq_phase_mode {
+            self.eq_phase_mode = phase_mode;
+            self.linear_phase_eq.reset();
+            self.linear_phase_key = None;
+            host.set_latency_samples(self.latency_samples());
+            self.shared_latency_samples
+                .store(self.latency_samples(), Ordering::Relaxed);
+            self.bypass.set_delay(self.chain_latency_samples());
+        }
+
+        let bypassed = self.params.master_bypass.value();
+        self.analyzer_taps.push_mid(AnalyzerTap::Input, left, right);
+        self.input_meter
+            .process_block(left, right, &self.input_levels);
+        self.bypass.capture_input(left, right);
+
+        self.update_listeners();
+        let num_samples = left.len() as u32;
+        let speakers = [
+            (
+                self.params
+                    .speaker_azimuth_left
+                    .smoothed
+                    .next_step(num_samples),
+                self.params
+                    .speaker_elevation_left
+                    .smoothed
+                    .next_step(num_samples),
+            ),
+            (
+                self.params
+                    .speaker_azimuth_right
+                    .smoothed
+                    .next_step(num_samples),
+                self.params
+                    .speaker_elevation_right
+                    .smoothed
+                    .next_step(num_samples),
+            ),
+        ];
+        self.update_hrirs(speakers, num_samples as usize, host);
+
+        // The chain keeps running while bypassed, so the bypassed path can be matched to
+        // its loudness and switching back crossfades into a settled signal
+
+        if self.params.eq_enable.value() {
+            let band_count = self.params.eq_band_count.load(Ordering::Relaxed);
+            self.parametric_eq
+                .set_channel_mode(self.params.eq_channel_mode.value());
+            self.parametric_eq.set_design(self.params.eq_design.value());
+            // Each band is set to where its smoothers will be at the end of this block
+            // and the EQ interpolates its coefficients towards that across the block.
+            // Bands whose values didn't change are skipped by the EQ.
+            let block_len = left.len();
+            for (i, band_params) in self.params.eq_bands.iter().enumerate() {
+                let band_config = BandConfig {
+                    filter_type: band_params.filter_type.value(),
+                    center_freq: band_params.frequency.smoothed.next_step(block_len as u32),
+                    q: band_params.q.smoothed.next_step(block_len as u32),
+                    gain_db: band_params.gain.smoothed.next_step(block_len as u32),
+                    enabled: band_params.enabled.value() && i < band_count,
+                    channel: band_params.channel.value(),
+                    dynamics: band_params
+                        .dynamic
+                        .value()
+                        .then(|| band_params.dynamic_config()),
+                };
+                self.parametric_eq.update_band_coeffs(
+                    i,
+                    self.current_sample_rate,
+                    &band_config,
+                    block_len,
+                );
+            }
+
+            // Searching the response is too costly to repeat for every block while a
+            // band glides, so the preamp follows it in steps
+            self.samples_since_peak_gain_update = self
+                .samples_since_peak_gain_update
+                .saturating_add(block_len);
+            let interval_samples =
+                (PEAK_GAIN_UPDATE_INTERVAL_MS / 1000.0 * self.current_sample_rate) as usize;
+            let peak_gain_db = if self.samples_since_peak_gain_update >= interval_samples {
+                self.samples_since_peak_gain_update = 0;
+                self.parametric_eq.peak_gain_db(self.current_sample_rate)
+            } else {
+                self.parametric_eq.last_peak_gain_db()
+            };
+            let preamp_db = match self.params.eq_preamp_mode.value() {
+                PreampMode::Auto => -peak_gain_db.max(0.0),
+                PreampMode::Manual => self.params.eq_preamp.value(),
+            };
+            self.eq_headroom_db
+                .store((-(peak_gain_db + preamp_db)).to_bits(), Ordering::Relaxed);
+
+            self.preamp_gain
+                .set_target(self.current_sample_rate, util::db_to_gain(preamp_db));
+            for (sample_l, sample_r) in left.iter_mut().zip(right.iter_mut()) {
+                let gain = self.preamp_gain.next();
+                *sample_l *= gain;
+                *sample_r *= gain;
+            }
+
+            match phase_mode {
+                EqPhaseMode::MinimumPhase => self.parametric_eq.process_block(left, right),
+                EqPhaseMode::LinearPhase => self.parametric_eq.process_dynamic_bands(left, right),
+            }
+        } else {
+            self.eq_headroom_db.store(0.0f32.to_bits(), Ordering::Relaxed);
+        }
+
+        if phase_mode == EqPhaseMode::LinearPhase {
+            self.update_linear_phase_kernel(self.params.eq_enable.value(), host);
+            self.linear_phase_eq.process_block(left, right);
+        }
+        self.analyzer_taps
+            .push_mid(AnalyzerTap::PostEq, left, right);
+
+        // The output gain turns the level down from the reference level. Switching the
+        // compensation off designs flat shelves, which the filters glide to.
+        let reference_phon = self.params.loudness_reference.value();
+        let listening_phon = if self.params.loudness_compensation.value() {
+            reference_phon + util::gain_to_db(self.params.output_gain.value())
+        } else {
+            reference_phon
+        };
+        self.loudness
+            .set_levels(listening_phon, reference_phon, left.len());
+        self.loudness.process_block(left, right);
+
+        let [input_l, input_r] = &mut self.convolution_input;
+        if input_l.len() < left.len() {
+            // Only for hosts that pass more than the maximum block size they announced
+            input_l.resize(left.len(), 0.0);
+            input_r.resize(left.len(), 0.0);
+        }
+        let input_l = &mut input_l[..left.len()];
+        let input_r = &mut input_r[..left.len()];
+        input_l.copy_from_slice(left);
+        input_r.copy_from_slice(right);
+        self.convolution_engine
+            .process_block(input_l, input_r, left, right);
+
+        let master_gain = self.params.output_gain.smoothed.next();
+        for sample in left.iter_mut().chain(right.iter_mut()) {
+            *sample *= master_gain;
+        }
+
+        // The limiter also runs during bypass so the plugin's latency stays the same
+        self.bypass.process_block(
+            left,
+            right,
+            bypassed,
+            self.params.bypass_loudness_match.value(),
+        );
+        self.limiter
+            .set_enabled(self.params.safety_limiter.value() && !bypassed);
+        self.limiter.process_block(left, right);
+        self.analyzer_taps.push(AnalyzerTap::OutputLeft, left);
+        self.analyzer_taps.push(AnalyzerTap::OutputRight, right);
+        self.output_meter
+            .process_block(left, right, &self.output_levels);
     }
 }
 
 fn get_config_path() -> Option<PathBuf> {
+    if let Some(path) = standalone::config_path() {
+        return Some(path.to_path_buf());
+    }
     let mut config_path = dirs::config_dir()?;
     config_path.push(OpenHeadstagePlugin::VENDOR);
     config_path.push(OpenHeadstagePlugin::NAME);
@@ -346,6 +1361,24 @@ fn get_config_path() -> Option<PathBuf> {
     Some(config_path)
 }
 
+fn get_presets_dir() -> Option<PathBuf> {
+    let mut presets_dir = dirs::config_dir()?;
+    presets_dir.push(OpenHeadstagePlugin::VENDOR);
+    presets_dir.push(OpenHeadstagePlugin::NAME);
+    presets_dir.push("presets");
+    Some(presets_dir)
+}
+
+#[cfg(unix)]
+fn json_rpc_socket_path(params: &OpenHeadstageParams) -> PathBuf {
+    let path = params.json_rpc_socket.read();
+    if path.is_empty() {
+        default_socket_path()
+    } else {
+        PathBuf::from(path.as_str())
+    }
+}
+
 // Standalone Persistence
 //
 // To handle saving and loading the plugin's state in the standalone version, we use a
@@ -374,41 +1407,99 @@ struct StandaloneConfig {
     sofa_file_path: String,
     audio_host: String,
     audio_device: String,
+    #[serde(default)]
+    audio_input_device: String,
+    #[serde(default)]
+    osc_enabled: bool,
+    #[serde(default = "default_osc_port")]
+    osc_port: u16,
+    #[serde(default = "default_osc_feedback_port")]
+    osc_feedback_port: u16,
+    #[serde(default)]
+    osc_allow_network: bool,
+    #[serde(default)]
+    midi_mappings: Vec<MidiMapping>,
+    #[serde(default)]
+    device_profiles: DeviceProfiles,
+    #[serde(default)]
+    json_rpc_enabled: bool,
+    #[serde(default)]
+    json_rpc_socket: String,
     master_bypass: bool,
+    #[serde(default)]
+    bypass_loudness_match: bool,
     output_gain: f32,
     speaker_azimuth_left: f32,
     speaker_elevation_left: f32,
     speaker_azimuth_right: f32,
     speaker_elevation_right: f32,
+    #[serde(default)]
+    head_tracking: bool,
+    #[serde(default = "default_head_tracking_port")]
+    head_tracking_port: u16,
     eq_enable: bool,
+    #[serde(default)]
+    eq_channel_mode: EqChannelMode,
+    #[serde(default)]
+    eq_design: BiquadDesign,
+    #[serde(default)]
+    eq_phase_mode: EqPhaseMode,
+    #[serde(default)]
+    eq_preamp_mode: PreampMode,
+    #[serde(default)]
+    eq_preamp: f32,
+    #[serde(default)]
+    safety_limiter: bool,
+    #[serde(default)]
+    loudness_compensation: bool,
+    #[serde(default = "default_loudness_reference")]
+    loudness_reference: f32,
+    #[serde(default = "default_eq_band_count")]
+    eq_band_count: usize,
     eq_bands: Vec<BandSetting>,
 }
 
 impl Default for StandaloneConfig {
     fn default() -> Self {
         let default_params = OpenHeadstageParams::new(Self::pre_default());
-        let mut eq_bands = Vec::new();
-        for band in default_params.eq_bands.iter() {
-            eq_bands.push(BandSetting {
-                enabled: band.enabled.value(),
-                filter_type: band.filter_type.value(),
-                frequency: band.frequency.value(),
-                q: band.q.value(),
-                gain: band.gain.value(),
-            });
-        }
+        let eq_bands = default_params
+            .eq_bands
+            .iter()
+            .map(EqBandParams::to_band_setting)
+            .collect();
 
         Self {
             sofa_file_path: default_params.sofa_file_path.read().clone(),
             audio_host: default_params.audio_host.read().clone(),
             audio_device: default_params.audio_device.read().clone(),
+            audio_input_device: default_params.audio_input_device.read().clone(),
+            osc_enabled: default_params.osc_enabled.load(Ordering::Relaxed),
+            osc_port: default_params.osc_port.load(Ordering::Relaxed),
+            osc_feedback_port: default_params.osc_feedback_port.load(Ordering::Relaxed),
+            osc_allow_network: default_params.osc_allow_network.load(Ordering::Relaxed),
+            midi_mappings: default_params.midi_mappings.read().clone(),
+            device_profiles: default_params.device_profiles.read().clone(),
+            json_rpc_enabled: default_params.json_rpc_enabled.load(Ordering::Relaxed),
+            json_rpc_socket: default_params.json_rpc_socket.read().clone(),
             master_bypass: default_params.master_bypass.value(),
+            bypass_loudness_match: default_params.bypass_loudness_match.value(),
             output_gain: default_params.output_gain.value(),
             speaker_azimuth_left: default_params.speaker_azimuth_left.value(),
             speaker_elevation_left: default_params.speaker_elevation_left.value(),
             speaker_azimuth_right: default_params.speaker_azimuth_right.value(),
             speaker_elevation_right: default_params.speaker_elevation_right.value(),
+            head_tracking: default_params.head_tracking.value(),
+            head_tracking_port: default_params.head_tracking_port.load(Ordering::Relaxed),
             eq_enable: default_params.eq_enable.value(),
+            eq_channel_mode: default_params.eq_channel_mode.value(),
+            eq_design: default_params.eq_design.value(),
+            eq_phase_mode: default_params.eq_phase_mode.value(),
+            eq_preamp_mode: default_params.eq_preamp_mode.value(),
+            eq_preamp: default_params.eq_preamp.value(),
+            safety_limiter: default_params.safety_limiter.value(),
+            loudness_compensation: default_params.loudness_compensation.value(),
+            loudness_reference: default_params.loudness_reference.value(),
+            eq_band_count: default_params.eq_band_count.load(Ordering::Relaxed),
             eq_bands,
         }
     }
@@ -424,41 +1515,396 @@ impl StandaloneConfig {
                 .default_output_device()
                 .map(|d| d.name().unwrap_or_default())
                 .unwrap_or_default(),
+            audio_input_device: String::new(),
+            osc_enabled: false,
+            osc_port: DEFAULT_OSC_PORT,
+            osc_feedback_port: DEFAULT_OSC_FEEDBACK_PORT,
+            osc_allow_network: false,
+            midi_mappings: Vec::new(),
+            device_profiles: DeviceProfiles::default(),
+            json_rpc_enabled: false,
+            json_rpc_socket: String::new(),
             master_bypass: false,
+            bypass_loudness_match: false,
             output_gain: util::db_to_gain(0.0),
             speaker_azimuth_left: -30.0,
             speaker_elevation_left: 0.0,
             speaker_azimuth_right: 30.0,
             speaker_elevation_right: 0.0,
+            head_tracking: false,
+            head_tracking_port: DEFAULT_OPENTRACK_PORT,
             eq_enable: false,
+            eq_channel_mode: EqChannelMode::Linked,
+            eq_design: BiquadDesign::Bilinear,
+            eq_phase_mode: EqPhaseMode::MinimumPhase,
+            eq_preamp_mode: PreampMode::Auto,
+            eq_preamp: 0.0,
+            safety_limiter: false,
+            loudness_compensation: false,
+            loudness_reference: DEFAULT_LOUDNESS_REFERENCE_PHON,
+            eq_band_count: NUM_EQ_BANDS,
             eq_bands: (0..NUM_EQ_BANDS).map(|_| BandSetting::default()).collect(),
         }
     }
+
+    /// Applies an AutoEQ profile to the settings, as `apply_auto_eq_profile` does to the
+    /// parameters.
+    fn apply_auto_eq_profile(&mut self, profile: &AutoEqProfile) {
+        let bands = &profile.bands[..profile.bands.len().min(MAX_EQ_BANDS)];
+        self.eq_band_count = bands.len().max(1);
+        self.eq_enable = true;
+        match profile.preamp_db {
+            Some(preamp) => {
+                self.eq_preamp_mode = PreampMode::Manual;
+                self.eq_preamp = preamp;
+            }
+            None => self.eq_preamp_mode = PreampMode::Auto,
+        }
+        if self.eq_channel_mode != EqChannelMode::LeftRight
+            && bands.iter().any(|b| b.channel != BandChannel::Both)
+        {
+            self.eq_channel_mode = EqChannelMode::LeftRight;
+        }
+        if self.eq_bands.len() < bands.len() {
+            self.eq_bands.resize_with(bands.len(), BandSetting::default);
+        }
+        self.eq_bands[..bands.len()].clone_from_slice(bands);
+    }
+
+    /// Applies the profile for an output device, or the default profile, and returns its
+    /// name.
+    fn apply_device_profile(&mut self, device: &str) -> Option<String> {
+        let profile = self.device_profiles.for_device(device)?.clone();
+        if !profile.sofa_file_path.is_empty() {
+            self.sofa_file_path = profile.sofa_file_path;
+        }
+        self.apply_preset(&profile.settings);
+        Some(profile.name)
+    }
+
+    /// Applies a preset's plain values to the settings, for presets that are loaded
+    /// before there are parameters to set. Unknown IDs are skipped.
+    fn apply_preset(&mut self, preset: &Preset) {
+        if let Some(count) = preset.eq_band_count {
+            self.eq_band_count = count.clamp(1, MAX_EQ_BANDS);
+        }
+        let setters = config_setters();
+        for (id, &value) in &preset.params {
+            if let Some(set) = setters.get(id) {
+                set(self, value);
+            }
+        }
+    }
+
+    /// The settings for an EQ band, adding default bands up to it.
+    fn band_mut(&mut self, index: usize) -> &mut BandSetting {
+        if self.eq_bands.len() <= index {
+            self.eq_bands.resize_with(index + 1, BandSetting::default);
+        }
+        &mut self.eq_bands[index]
+    }
 }
 
-fn save_standalone_config(params: &Arc<OpenHeadstageParams>) {
-    let mut bands = Vec::new();
-    for band in &params.eq_bands {
-        bands.push(BandSetting {
-            enabled: band.enabled.value(),
-            filter_type: band.filter_type.value(),
-            frequency: band.frequency.value(),
-            q: band.q.value(),
-            gain: band.gain.value(),
-        });
+type ConfigSetter = Box<dyn Fn(&mut StandaloneConfig, f32) + Send + Sync>;
+
+fn config_setter(set: impl Fn(&mut StandaloneConfig, f32) + Send + Sync + 'static) -> ConfigSetter {
+    Box::new(set)
+}
+
+/// Stores a parameter's plain value in the setting the parameter is saved as, by
+/// parameter ID. The IDs come from the parameters' own map, so they are only spelled
+/// out by the `#[id]` attributes.
+fn config_setters() -> &'static HashMap<String, ConfigSetter> {
+    static SETTERS: OnceLock<HashMap<String, ConfigSetter>> = OnceLock::new();
+    SETTERS.get_or_init(|| {
+        let params = OpenHeadstageParams::new(StandaloneConfig::pre_default());
+        let mut setters: Vec<(ParamPtr, ConfigSetter)> = vec![
+            (
+                params.master_bypass.as_ptr(),
+                config_setter(|c, v| c.master_bypass = v >= 0.5),
+            ),
+            (
+                params.bypass_loudness_match.as_ptr(),
+                config_setter(|c, v| c.bypass_loudness_match = v >= 0.5),
+            ),
+            (
+                params.output_gain.as_ptr(),
+                config_setter(|c, v| c.output_gain = v),
+            ),
+            (
+                params.speaker_azimuth_left.as_ptr(),
+                config_setter(|c, v| c.speaker_azimuth_left = v),
+            ),
+            (
+                params.speaker_elevation_left.as_ptr(),
+                config_setter(|c, v| c.speaker_elevation_left = v),
+            ),
+            (
+                params.speaker_azimuth_right.as_ptr(),
+                config_setter(|c, v| c.speaker_azimuth_right = v),
+            ),
+            (
+                params.speaker_elevation_right.as_ptr(),
+                config_setter(|c, v| c.speaker_elevation_right = v),
+            ),
+            (
+                params.head_tracking.as_ptr(),
+                config_setter(|c, v| c.head_tracking = v >= 0.5),
+            ),
+            (
+                params.eq_enable.as_ptr(),
+                config_setter(|c, v| c.eq_enable = v >= 0.5),
+            ),
+            (
+                params.eq_channel_mode.as_ptr(),
+                config_setter(|c, v| c.eq_channel_mode = variant(v, c.eq_channel_mode)),
+            ),
+            (
+                params.eq_design.as_ptr(),
+                config_setter(|c, v| c.eq_design = variant(v, c.eq_design)),
+            ),
+            (
+                params.eq_phase_mode.as_ptr(),
+                config_setter(|c, v| c.eq_phase_mode = variant(v, c.eq_phase_mode)),
+            ),
+            (
+                params.eq_preamp_mode.as_ptr(),
+                config_setter(|c, v| c.eq_preamp_mode = variant(v, c.eq_preamp_mode)),
+            ),
+            (
+                params.eq_preamp.as_ptr(),
+                config_setter(|c, v| c.eq_preamp = v),
+            ),
+            (
+                params.safety_limiter.as_ptr(),
+                config_setter(|c, v| c.safety_limiter = v >= 0.5),
+            ),
+            (
+                params.loudness_compensation.as_ptr(),
+                config_setter(|c, v| c.loudness_compensation = v >= 0.5),
+            ),
+            (
+                params.loudness_reference.as_ptr(),
+                config_setter(|c, v| c.loudness_reference = v),
+            ),
+        ];
+        for (i, band) in params.eq_bands.iter().enumerate() {
+            let band_setters: [(ParamPtr, ConfigSetter); 11] = [
+                (
+                    band.enabled.as_ptr(),
+                    config_setter(move |c, v| c.band_mut(i).enabled = v >= 0.5),
+                ),
+                (
+                    band.filter_type.as_ptr(),
+                    config_setter(move |c, v| {
+                        let band = c.band_mut(i);
+                        band.filter_type = variant(v, band.filter_type);
+                    }),
+                ),
+                (
+                    band.frequency.as_ptr(),
+                    config_setter(move |c, v| c.band_mut(i).frequency = v),
+                ),
+                (
+                    band.q.as_ptr(),
+                    config_setter(move |c, v| c.band_mut(i).q = v),
+                ),
+                (
+                    band.gain.as_ptr(),
+                    config_setter(move |c, v| c.band_mut(i).gain = v),
+                ),
+                (
+                    band.channel.as_ptr(),
+                    config_setter(move |c, v| {
+                        let band = c.band_mut(i);
+                        band.channel = variant(v, band.channel);
+                    }),
+                ),
+                (
+                    band.dynamic.as_ptr(),
+                    config_setter(move |c, v| c.band_mut(i).dynamic = v >= 0.5),
+                ),
+                (
+                    band.threshold.as_ptr(),
+                    config_setter(move |c, v| c.band_mut(i).dynamics.threshold_db = v),
+                ),
+                (
+                    band.ratio.as_ptr(),
+                    config_setter(move |c, v| c.band_mut(i).dynamics.ratio = v),
+                ),
+                (
+                    band.attack.as_ptr(),
+                    config_setter(move |c, v| c.band_mut(i).dynamics.attack_ms = v),
+                ),
+                (
+                    band.release.as_ptr(),
+                    config_setter(move |c, v| c.band_mut(i).dynamics.release_ms = v),
+                ),
+            ];
+            setters.extend(band_setters);
+        }
+
+        params
+            .param_map()
+            .into_iter()
+            .filter_map(|(id, param, _)| {
+                let index = setters.iter().position(|(ptr, _)| *ptr == param)?;
+                Some((id, setters.swap_remove(index).1))
+            })
+            .collect()
+    })
+}
+
+/// The enum variant with the index `value`, or `current` for indices out of range.
+fn variant<T: Enum>(value: f32, current: T) -> T {
+    let index = value.round();
+    if index >= 0.0 && (index as usize) < T::variants().len() {
+        T::from_index(index as usize)
+    } else {
+        current
+    }
+}
+
+/// Applies an AutoEQ profile to the EQ parameters, as the "Apply Loaded EQ" button does.
+fn apply_auto_eq_profile(
+    profile: &AutoEqProfile,
+    params: &OpenHeadstageParams,
+    setter: &ParamSetter,
+) {
+    let bands = &profile.bands;
+    if bands.len() > MAX_EQ_BANDS {
+        nih_log!(
+            "AutoEQ profile has {} bands, only the first {} are used.",
+            bands.len(),
+            MAX_EQ_BANDS
+        );
     }
+    params
+        .eq_band_count
+        .store(bands.len().clamp(1, MAX_EQ_BANDS), Ordering::Relaxed);
+    setter.set_parameter(&params.eq_enable, true);
+    // Use the preamp AutoEQ calculated when the profile has one
+    match profile.preamp_db {
+        Some(preamp) => {
+            setter.set_parameter(&params.eq_preamp_mode, PreampMode::Manual);
+            setter.set_parameter(&params.eq_preamp, preamp);
+        }
+        None => {
+            setter.set_parameter(&params.eq_preamp_mode, PreampMode::Auto);
+        }
+    }
+    // Profiles address the left and right channels, so scoped bands need the EQ in
+    // left/right mode, also when it was in mid/side
+    if params.eq_channel_mode.value() != EqChannelMode::LeftRight
+        && bands.iter().any(|b| b.channel != BandChannel::Both)
+    {
+        setter.set_parameter(&params.eq_channel_mode, EqChannelMode::LeftRight);
+    }
+    for (i, band_param) in params.eq_bands.iter().enumerate() {
+        if let Some(band_setting) = bands.get(i) {
+            setter.set_parameter(&band_param.enabled, band_setting.enabled);
+            setter.set_parameter(&band_param.filter_type, band_setting.filter_type);
+            setter.set_parameter(&band_param.frequency, band_setting.frequency);
+            setter.set_parameter(&band_param.q, band_setting.q);
+            setter.set_parameter(&band_param.gain, band_setting.gain);
+            setter.set_parameter(&band_param.channel, band_setting.channel);
+            setter.set_parameter(&band_param.dynamic, band_setting.dynamic);
+            let dynamics = band_setting.dynamics;
+            setter.set_parameter(&band_param.threshold, dynamics.threshold_db);
+            setter.set_parameter(&band_param.ratio, dynamics.ratio);
+            setter.set_parameter(&band_param.attack, dynamics.attack_ms);
+            setter.set_parameter(&band_param.release, dynamics.release_ms);
+        }
+    }
+}
+
+/// Applies a preset through the editor's setter. Parameters the preset doesn't have keep
+/// their values.
+fn apply_preset(
+    preset: &Preset,
+    params: &OpenHeadstageParams,
+    remote_params: &RemoteParams,
+    setter: &ParamSetter,
+) {
+    if let Some(eq_band_count) = preset.eq_band_count {
+        params
+            .eq_band_count
+            .store(eq_band_count.clamp(1, MAX_EQ_BANDS), Ordering::Relaxed);
+    }
+    for (id, value) in &preset.params {
+        if !remote_params.set(id, &RemoteValue::Plain(*value), setter) {
+            nih_log!("Preset has an unknown parameter '{}'", id);
+        }
+    }
+}
+
+/// The current parameters as a preset.
+fn current_preset(params: &OpenHeadstageParams, remote_params: &RemoteParams) -> Preset {
+    Preset {
+        params: remote_params
+            .values()
+            .into_iter()
+            .map(|feedback| (feedback.id, feedback.plain))
+            .collect(),
+        eq_band_count: Some(params.eq_band_count.load(Ordering::Relaxed)),
+    }
+}
+
+/// Applies a device profile through the editor's setter and loads its SOFA file.
+fn apply_device_profile(
+    profile: &DeviceProfile,
+    params: &OpenHeadstageParams,
+    remote_params: &RemoteParams,
+    setter: &ParamSetter,
+    async_executor: &AsyncExecutor<OpenHeadstagePlugin>,
+) {
+    apply_preset(&profile.settings, params, remote_params, setter);
+    if !profile.sofa_file_path.is_empty() && *params.sofa_file_path.read() != profile.sofa_file_path
+    {
+        *params.sofa_file_path.write() = profile.sofa_file_path.clone();
+        async_executor.execute_background(Task::LoadSofa(PathBuf::from(&profile.sofa_file_path)));
+    }
+}
+
+fn save_standalone_config(params: &Arc<OpenHeadstageParams>) {
+    let bands = params
+        .eq_bands
+        .iter()
+        .map(EqBandParams::to_band_setting)
+        .collect();
 
     let config = StandaloneConfig {
         sofa_file_path: params.sofa_file_path.read().clone(),
         audio_host: params.audio_host.read().clone(),
         audio_device: params.audio_device.read().clone(),
+        audio_input_device: params.audio_input_device.read().clone(),
+        osc_enabled: params.osc_enabled.load(Ordering::Relaxed),
+        osc_port: params.osc_port.load(Ordering::Relaxed),
+        osc_feedback_port: params.osc_feedback_port.load(Ordering::Relaxed),
+        osc_allow_network: params.osc_allow_network.load(Ordering::Relaxed),
+        midi_mappings: params.midi_mappings.read().clone(),
+        device_profiles: params.device_profiles.read().clone(),
+        json_rpc_enabled: params.json_rpc_enabled.load(Ordering::Relaxed),
+        json_rpc_socket: params.json_rpc_socket.read().clone(),
         master_bypass: params.master_bypass.value(),
+        bypass_loudness_match: params.bypass_loudness_match.value(),
         output_gain: params.output_gain.value(),
         speaker_azimuth_left: params.speaker_azimuth_left.value(),
         speaker_elevation_left: params.speaker_elevation_left.value(),
         speaker_azimuth_right: params.speaker_azimuth_right.value(),
         speaker_elevation_right: params.speaker_elevation_right.value(),
+        head_tracking: params.head_tracking.value(),
+        head_tracking_port: params.head_tracking_port.load(Ordering::Relaxed),
         eq_enable: params.eq_enable.value(),
+        eq_channel_mode: params.eq_channel_mode.value(),
+        eq_design: params.eq_design.value(),
+        eq_phase_mode: params.eq_phase_mode.value(),
+        eq_preamp_mode: params.eq_preamp_mode.value(),
+        eq_preamp: params.eq_preamp.value(),
+        safety_limiter: params.safety_limiter.value(),
+        loudness_compensation: params.loudness_compensation.value(),
+        loudness_reference: params.loudness_reference.value(),
+        eq_band_count: params.eq_band_count.load(Ordering::Relaxed),
         eq_bands: bands,
     };
 
@@ -469,12 +1915,19 @@ fn save_standalone_config(params: &Arc<OpenHeadstageParams>) {
     }
 }
 
+fn load_standalone_config() -> StandaloneConfig {
+    get_config_path()
+        .and_then(|p| fs::read_to_string(p).ok())
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default()
+}
+
 impl Default for OpenHeadstagePlugin {
     fn default() -> Self {
-        let config = get_config_path()
-            .and_then(|p| fs::read_to_string(p).ok())
-            .and_then(|s| serde_json::from_str(&s).ok())
-            .unwrap_or_default();
+        // The standalone app's command line may have changed the saved settings
+        let config = standalone::startup_config()
+            .cloned()
+            .unwrap_or_else(load_standalone_config);
 
         let params = Arc::new(OpenHeadstageParams::new(config));
 
@@ -495,7 +1948,7 @@ impl Plugin for OpenHeadstagePlugin {
         ..AudioIOLayout::const_default()
     }];
 
-    const MIDI_INPUT: MidiConfig = MidiConfig::None;
+    const MIDI_INPUT: MidiConfig = MidiConfig::MidiCCs;
     const SAMPLE_ACCURATE_AUTOMATION: bool = true;
 
     type SysExMessage = ();
@@ -507,16 +1960,27 @@ impl Plugin for OpenHeadstagePlugin {
 
     fn editor(&mut self, async_executor: AsyncExecutor<Self>) -> Option<Box<dyn Editor>> {
         let params = self.params.clone();
+        let eq_headroom_db = self.eq_headroom_db.clone();
+        let shared_sample_rate = self.shared_sample_rate.clone();
+        let spectrum_analyzer = self.spectrum_analyzer.clone();
+        let input_levels = self.input_levels.clone();
+        let output_levels = self.output_levels.clone();
+        #[cfg(unix)]
+        let json_rpc_state = self.json_rpc_state.clone();
+        let head_tracking = self.head_tracking.clone();
+        let osc_state = self.osc_state.clone();
+        let midi_learn = self.midi_learn.clone();
+        let remote_auto_eq_result = self.remote_auto_eq_result.clone();
         let editor_state = EditorState::new(
             self.auto_eq_result.clone(),
             &self.params.eq_bands,
             &self.params,
         );
 
-        create_egui_editor(
+        let editor = create_egui_editor(
             self.params.editor_state.clone(),
             editor_state,
-            |egui_ctx, _| {
+            move |egui_ctx, _| {
                 let mut style = (*egui_ctx.style()).clone();
                 style
                     .text_styles
@@ -537,6 +2001,14 @@ impl Plugin for OpenHeadstagePlugin {
                 egui_ctx.set_style(style);
             },
             move |egui_ctx, setter, state| {
+                if let Some(profile) = remote_auto_eq_result.lock().take() {
+                    state.loaded_eq_settings = Some(profile);
+                }
+                if midi_learn.lock().is_some() {
+                    // Shows when the control is learned
+                    egui_ctx.request_repaint_after(Duration::from_millis(50));
+                }
+
                 // EQ Editor Panel (conditionally shown)
                 if state.show_eq_editor {
                     egui::SidePanel::right("eq_editor_panel")
@@ -544,25 +2016,79 @@ impl Plugin for OpenHeadstagePlugin {
                         .default_width(800.0)
                         .show(egui_ctx, |ui| {
                             ui.heading(egui::RichText::new("Parametric Equalizer").size(22.0));
+                            if let Some(preamp) = state.recommended_preamp {
+                                ui.label(format!("Recommended preamp: {:.1} dB", preamp));
+                            }
+
+                            // The curves of the bands as edited and of the loaded profile,
+                            // computed in the background
+                            let sample_rate =
+                                f32::from_bits(shared_sample_rate.load(Ordering::Relaxed));
+                            let design = params.eq_design.value();
+                            let request = state.editor_response_request(sample_rate, design);
+                            state.eq_curve.update(Some(request), &async_executor);
+                            let loaded_request = state.loaded_response_request(sample_rate, design);
+                            state
+                                .loaded_eq_curve
+                                .update(loaded_request, &async_executor);
 
-                            // Placeholder for the EQ curve visualization
                             ui.add_space(10.0);
-                            ui.group(|ui| {
-                                ui.set_min_size(egui::vec2(ui.available_width(), 294.0));
-                                ui.vertical_centered(|ui| {
-                                    ui.label("EQ Curve Visualization (Future)");
+                            egui::ComboBox::new("eq_curve_view", "Show")
+                                .selected_text(state.eq_curve_view.name())
+                                .show_ui(ui, |ui| {
+                                    for view in EqCurveView::iter() {
+                                        ui.selectable_value(
+                                            &mut state.eq_curve_view,
+                                            view,
+                                            view.name(),
+                                        );
+                                    }
                                 });
+                            let band_count = state.eq_editor_band_count;
+                            ui.add(EqCurve {
+                                response: state.eq_curve.response.as_ref(),
+                                overlay: state.loaded_eq_curve.response.as_ref(),
+                                bands: &mut state.eq_editor_bands[..band_count],
+                                view: state.eq_curve_view,
+                                channel_mode: state.eq_editor_channel_mode,
+                                size: egui::vec2(ui.available_width(), 266.0),
                             });
                             ui.add_space(10.0);
 
-                            // Use remaining space for the scroll area, but leave space for buttons at the bottom
+                            ui.horizontal(|ui| {
+                                ui.label("Bands");
+                                ui.add(
+                                    egui::DragValue::new(&mut state.eq_editor_band_count)
+                                        .range(1..=MAX_EQ_BANDS),
+                                );
+
+                                ui.add_space(20.0);
+
+                                egui::ComboBox::new("eq_channel_mode", "Channels")
+                                    .selected_text(state.eq_editor_channel_mode.name())
+                                    .show_ui(ui, |ui| {
+                                        for mode in EqChannelMode::iter() {
+                                            ui.selectable_value(
+                                                &mut state.eq_editor_channel_mode,
+                                                mode,
+                                                mode.name(),
+                                            );
+                                        }
+                                    });
+                            });
+
+                            // Use remaining space for the scroll area, but leave space for buttons at the bottom
                             let scroll_area_height = ui.available_height() - 60.0; // 60px for buttons and padding
 
                             egui::ScrollArea::vertical()
                                 .max_height(scroll_area_height)
                                 .show(ui, |ui| {
-                                    for (i, band_setting) in
-                                        state.eq_editor_bands.iter_mut().enumerate()
+                                    let channel_mode = state.eq_editor_channel_mode;
+                                    for (i, band_setting) in state
+                                        .eq_editor_bands
+                                        .iter_mut()
+                                        .take(state.eq_editor_band_count)
+                                        .enumerate()
                                     {
                                         ui.group(|ui| {
                                             ui.horizontal(|ui| {
@@ -583,16 +2109,13 @@ impl Plugin for OpenHeadstagePlugin {
                                                     format!("filter_type_{}", i),
                                                     "Type",
                                                 )
-                                                .selected_text(format!(
-                                                    "{:?}",
-                                                    band_setting.filter_type
-                                                ))
+                                                .selected_text(band_setting.filter_type.name())
                                                 .show_ui(ui, |ui| {
                                                     for filter_type in FilterType::iter() {
                                                         ui.selectable_value(
                                                             &mut band_setting.filter_type,
                                                             filter_type,
-                                                            format!("{:?}", filter_type),
+                                                            filter_type.name(),
                                                         );
                                                     }
                                                 });
@@ -634,14 +2157,96 @@ impl Plugin for OpenHeadstagePlugin {
                                                     egui::DragValue::new(&mut band_setting.gain)
                                                         .speed(0.1)
                                                         .suffix(" dB")
-                                                        .range(-24.0..=24.0),
+                                                        .range(-16.0..=16.0),
                                                 );
                                                 if gain_drag.double_clicked()
                                                     || gain_label.double_clicked()
                                                 {
                                                     band_setting.gain = 0.0;
                                                 }
+
+                                                if channel_mode != EqChannelMode::Linked {
+                                                    ui.add_space(20.0);
+
+                                                    egui::ComboBox::new(
+                                                        format!("band_channel_{}", i),
+                                                        "Channel",
+                                                    )
+                                                    .selected_text(
+                                                        band_setting.channel.label(channel_mode),
+                                                    )
+                                                    .show_ui(ui, |ui| {
+                                                        for channel in BandChannel::iter() {
+                                                            ui.selectable_value(
+                                                                &mut band_setting.channel,
+                                                                channel,
+                                                                channel.label(channel_mode),
+                                                            );
+                                                        }
+                                                    });
+                                                }
+
+                                                if band_setting.filter_type.has_gain() {
+                                                    ui.add_space(20.0);
+                                                    ui.toggle_value(
+                                                        &mut band_setting.dynamic,
+                                                        "Dynamic",
+                                                    );
+                                                }
                                             });
+
+                                            if band_setting.dynamic
+                                                && band_setting.filter_type.has_gain()
+                                            {
+                                                let dynamics = &mut band_setting.dynamics;
+                                                ui.horizontal(|ui| {
+                                                    ui.add_space(20.0);
+                                                    ui.label("Threshold");
+                                                    ui.add(
+                                                        egui::DragValue::new(
+                                                            &mut dynamics.threshold_db,
+                                                        )
+                                                        .speed(0.1)
+                                                        .suffix(" dB")
+                                                        .range(-60.0..=0.0),
+                                                    );
+
+                                                    ui.add_space(20.0);
+
+                                                    ui.label("Ratio");
+                                                    ui.add(
+                                                        egui::DragValue::new(&mut dynamics.ratio)
+                                                            .speed(0.05)
+                                                            .suffix(":1")
+                                                            .range(1.0..=20.0)
+                                                            .fixed_decimals(1),
+                                                    );
+
+                                                    ui.add_space(20.0);
+
+                                                    ui.label("Attack");
+                                                    ui.add(
+                                                        egui::DragValue::new(
+                                                            &mut dynamics.attack_ms,
+                                                        )
+                                                        .speed(0.1)
+                                                        .suffix(" ms")
+                                                        .range(0.1..=100.0),
+                                                    );
+
+                                                    ui.add_space(20.0);
+
+                                                    ui.label("Release");
+                                                    ui.add(
+                                                        egui::DragValue::new(
+                                                            &mut dynamics.release_ms,
+                                                        )
+                                                        .speed(1.0)
+                                                        .suffix(" ms")
+                                                        .range(5.0..=1000.0),
+                                                    );
+                                                });
+                                            }
                                         });
                                     }
                                 });
@@ -661,6 +2266,10 @@ impl Plugin for OpenHeadstagePlugin {
                                                 .clicked()
                                             {
                                                 // Apply the temporary settings to the actual params
+                                                params.eq_band_count.store(
+                                                    state.eq_editor_band_count,
+                                                    Ordering::Relaxed,
+                                                );
                                                 for (i, band_setting) in
                                                     state.eq_editor_bands.iter().enumerate()
                                                 {
@@ -712,8 +2321,93 @@ impl Plugin for OpenHeadstagePlugin {
                                                             band_setting.gain,
                                                         );
                                                         setter.end_set_parameter(&band_param.gain);
+
+                                                        setter.begin_set_parameter(
+                                                            &band_param.channel,
+                                                        );
+                                                        setter.set_parameter(
+                                                            &band_param.channel,
+                                                            band_setting.channel,
+                                                        );
+                                                        setter
+                                                            .end_set_parameter(&band_param.channel);
+
+                                                        let dynamics = band_setting.dynamics;
+                                                        setter.begin_set_parameter(
+                                                            &band_param.dynamic,
+                                                        );
+                                                        setter.set_parameter(
+                                                            &band_param.dynamic,
+                                                            band_setting.dynamic,
+                                                        );
+                                                        setter
+                                                            .end_set_parameter(&band_param.dynamic);
+
+                                                        setter.begin_set_parameter(
+                                                            &band_param.threshold,
+                                                        );
+                                                        setter.set_parameter(
+                                                            &band_param.threshold,
+                                                            dynamics.threshold_db,
+                                                        );
+                                                        setter.end_set_parameter(
+                                                            &band_param.threshold,
+                                                        );
+
+                                                        setter
+                                                            .begin_set_parameter(&band_param.ratio);
+                                                        setter.set_parameter(
+                                                            &band_param.ratio,
+                                                            dynamics.ratio,
+                                                        );
+                                                        setter.end_set_parameter(&band_param.ratio);
+
+                                                        setter.begin_set_parameter(
+                                                            &band_param.attack,
+                                                        );
+                                                        setter.set_parameter(
+                                                            &band_param.attack,
+                                                            dynamics.attack_ms,
+                                                        );
+                                                        setter
+                                                            .end_set_parameter(&band_param.attack);
+
+                                                        setter.begin_set_parameter(
+                                                            &band_param.release,
+                                                        );
+                                                        setter.set_parameter(
+                                                            &band_param.release,
+                                                            dynamics.release_ms,
+                                                        );
+                                                        setter
+                                                            .end_set_parameter(&band_param.release);
                                                     }
                                                 }
+                                                setter.begin_set_parameter(&params.eq_channel_mode);
+                                                setter.set_parameter(
+                                                    &params.eq_channel_mode,
+                                                    state.eq_editor_channel_mode,
+                                                );
+                                                setter.end_set_parameter(&params.eq_channel_mode);
+
+                                                // Fitted EQs come with their own preamp
+                                                if let Some(preamp) =
+                                                    state.recommended_preamp.take()
+                                                {
+                                                    setter.begin_set_parameter(
+                                                        &params.eq_preamp_mode,
+                                                    );
+                                                    setter.set_parameter(
+                                                        &params.eq_preamp_mode,
+                                                        PreampMode::Manual,
+                                                    );
+                                                    setter
+                                                        .end_set_parameter(&params.eq_preamp_mode);
+
+                                                    setter.begin_set_parameter(&params.eq_preamp);
+                                                    setter.set_parameter(&params.eq_preamp, preamp);
+                                                    setter.end_set_parameter(&params.eq_preamp);
+                                                }
                                                 state.show_eq_editor = false;
                                             }
                                         },
@@ -745,17 +2439,178 @@ impl Plugin for OpenHeadstagePlugin {
 
                     ui.add_space(10.0);
 
+                    egui::collapsing_header::CollapsingHeader::new(
+                        egui::RichText::new("Presets").size(22.0),
+                    )
+                    .default_open(true)
+                    .show(ui, |ui| {
+                        let Some(presets_dir) = get_presets_dir() else {
+                            ui.label("There is no presets folder.");
+                            return;
+                        };
+
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_salt("preset_browser")
+                                .selected_text(
+                                    state.selected_preset.as_deref().unwrap_or("Choose a preset"),
+                                )
+                                .width(220.0)
+                                .show_ui(ui, |ui| {
+                                    let mut picked = None;
+                                    for name in presets::all_preset_names(&presets_dir) {
+                                        // A saved file with a factory preset's name is what
+                                        // loading that name picks up
+                                        let label = if presets::is_factory_preset(&name)
+                                            && !state.preset_names.contains(&name)
+                                        {
+                                            format!("{} (factory)", name)
+                                        } else {
+                                            name.clone()
+                                        };
+                                        let selected =
+                                            state.selected_preset.as_deref() == Some(name.as_str());
+                                        if ui.selectable_label(selected, label).clicked() {
+                                            picked = Some(name);
+                                        }
+                                    }
+                                    if let Some(name) = picked {
+                                        state.preset_name = name.clone();
+                                        state.selected_preset = Some(name);
+                                    }
+                                });
+
+                            let selected = state.selected_preset.clone();
+                            if ui
+                                .add_enabled(selected.is_some(), egui::Button::new("Load"))
+                                .clicked()
+                            {
+                                if let Some(name) = &selected {
+                                    state.preset_status =
+                                        match presets::load_preset(&presets_dir, name) {
+                                            Ok(preset) => {
+                                                apply_preset(
+                                                    &preset,
+                                                    &params,
+                                                    &state.remote_params,
+                                                    setter,
+                                                );
+                                                Some(format!("Loaded '{}'", name))
+                                            }
+                                            Err(e) => Some(e.to_string()),
+                                        };
+                                }
+                            }
+                            // Factory presets are built in and can only be loaded or exported
+                            let saved = selected
+                                .as_deref()
+                                .is_some_and(|name| state.preset_names.iter().any(|n| n == name));
+                            if ui.add_enabled(saved, egui::Button::new("Delete")).clicked() {
+                                if let Some(name) = &selected {
+                                    match presets::delete_preset(&presets_dir, name) {
+                                        Ok(()) => {
+                                            state.refresh_presets(None);
+                                            state.preset_status = Some(format!("Deleted '{}'", name));
+                                        }
+                                        Err(e) => state.preset_status = Some(e.to_string()),
+                                    }
+                                }
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut state.preset_name)
+                                    .hint_text("Preset name")
+                                    .desired_width(220.0),
+                            );
+                            let name = state.preset_name.trim().to_string();
+                            let editable = !name.is_empty() && !presets::is_factory_preset(&name);
+                            if ui
+                                .add_enabled(editable, egui::Button::new("Save"))
+                                .on_hover_text("Save the current settings, replacing a preset with the same name")
+                                .clicked()
+                            {
+                                let preset = current_preset(&params, &state.remote_params);
+                                match presets::save_preset(&presets_dir, &name, &preset) {
+                                    Ok(()) => {
+                                        state.refresh_presets(Some(name.clone()));
+                                        state.preset_status = Some(format!("Saved '{}'", name));
+                                    }
+                                    Err(e) => state.preset_status = Some(e.to_string()),
+                                }
+                            }
+                            let renamed_from = state
+                                .selected_preset
+                                .clone()
+                                .filter(|selected| state.preset_names.contains(selected));
+                            if ui
+                                .add_enabled(
+                                    editable && renamed_from.is_some(),
+                                    egui::Button::new("Rename"),
+                                )
+                                .on_hover_text("Rename the selected preset")
+                                .clicked()
+                            {
+                                if let Some(from) = renamed_from {
+                                    match presets::rename_preset(&presets_dir, &from, &name) {
+                                        Ok(()) => {
+                                            state.refresh_presets(Some(name.clone()));
+                                            state.preset_status =
+                                                Some(format!("Renamed '{}' to '{}'", from, name));
+                                        }
+                                        Err(e) => state.preset_status = Some(e.to_string()),
+                                    }
+                                }
+                            }
+                        });
+
+                        ui.horizontal(|ui| {
+                            if ui.button("Import").on_hover_text("Add a preset file").clicked() {
+                                state.file_dialog.pick_file();
+                                state.file_dialog_request = Some(FileDialogRequest::ImportPreset);
+                            }
+                            if ui
+                                .add_enabled(
+                                    state.selected_preset.is_some(),
+                                    egui::Button::new("Export"),
+                                )
+                                .on_hover_text("Save the selected preset as a file to share")
+                                .clicked()
+                            {
+                                state.file_dialog.save_file();
+                                state.file_dialog_request = Some(FileDialogRequest::ExportPreset);
+                            }
+                            if let Some(status) = &state.preset_status {
+                                ui.label(status);
+                            }
+                        });
+                    });
+
                     egui::collapsing_header::CollapsingHeader::new(
                         egui::RichText::new("Master Output").size(22.0),
                     )
                     .default_open(true)
                     .show(ui, |ui| {
-                        let mut bypass_state = params.master_bypass.value();
-                        if ui.toggle_value(&mut bypass_state, "Bypass").changed() {
-                            setter.begin_set_parameter(&params.master_bypass);
-                            setter.set_parameter(&params.master_bypass, bypass_state);
-                            setter.end_set_parameter(&params.master_bypass);
-                        }
+                        ui.horizontal(|ui| {
+                            let mut bypass_state = params.master_bypass.value();
+                            if ui.toggle_value(&mut bypass_state, "Bypass").changed() {
+                                setter.begin_set_parameter(&params.master_bypass);
+                                setter.set_parameter(&params.master_bypass, bypass_state);
+                                setter.end_set_parameter(&params.master_bypass);
+                            }
+                            let mut match_state = params.bypass_loudness_match.value();
+                            if ui
+                                .checkbox(&mut match_state, "Match Loudness")
+                                .on_hover_text(
+                                    "Play the bypassed signal at the loudness of the processed one",
+                                )
+                                .changed()
+                            {
+                                setter.begin_set_parameter(&params.bypass_loudness_match);
+                                setter.set_parameter(&params.bypass_loudness_match, match_state);
+                                setter.end_set_parameter(&params.bypass_loudness_match);
+                            }
+                        });
                         if ui.button("Reset to Default").clicked() {
                             let default_config = StandaloneConfig::default();
                             let default_params = OpenHeadstageParams::new(default_config);
@@ -767,6 +2622,13 @@ impl Plugin for OpenHeadstagePlugin {
                             );
                             setter.end_set_parameter(&params.master_bypass);
 
+                            setter.begin_set_parameter(&params.bypass_loudness_match);
+                            setter.set_parameter(
+                                &params.bypass_loudness_match,
+                                default_params.bypass_loudness_match.default_plain_value(),
+                            );
+                            setter.end_set_parameter(&params.bypass_loudness_match);
+
                             setter.begin_set_parameter(&params.output_gain);
                             setter.set_parameter(
                                 &params.output_gain,
@@ -774,6 +2636,20 @@ impl Plugin for OpenHeadstagePlugin {
                             );
                             setter.end_set_parameter(&params.output_gain);
 
+                            setter.begin_set_parameter(&params.loudness_compensation);
+                            setter.set_parameter(
+                                &params.loudness_compensation,
+                                default_params.loudness_compensation.default_plain_value(),
+                            );
+                            setter.end_set_parameter(&params.loudness_compensation);
+
+                            setter.begin_set_parameter(&params.loudness_reference);
+                            setter.set_parameter(
+                                &params.loudness_reference,
+                                default_params.loudness_reference.default_plain_value(),
+                            );
+                            setter.end_set_parameter(&params.loudness_reference);
+
                             setter.begin_set_parameter(&params.speaker_azimuth_left);
                             setter.set_parameter(
                                 &params.speaker_azimuth_left,
@@ -802,6 +2678,17 @@ impl Plugin for OpenHeadstagePlugin {
                             );
                             setter.end_set_parameter(&params.speaker_elevation_right);
 
+                            setter.begin_set_parameter(&params.head_tracking);
+                            setter.set_parameter(
+                                &params.head_tracking,
+                                default_params.head_tracking.default_plain_value(),
+                            );
+                            setter.end_set_parameter(&params.head_tracking);
+                            params.head_tracking_port.store(
+                                default_params.head_tracking_port.load(Ordering::Relaxed),
+                                Ordering::Relaxed,
+                            );
+
                             setter.begin_set_parameter(&params.eq_enable);
                             setter.set_parameter(
                                 &params.eq_enable,
@@ -809,6 +2696,52 @@ impl Plugin for OpenHeadstagePlugin {
                             );
                             setter.end_set_parameter(&params.eq_enable);
 
+                            setter.begin_set_parameter(&params.eq_channel_mode);
+                            setter.set_parameter(
+                                &params.eq_channel_mode,
+                                default_params.eq_channel_mode.default_plain_value(),
+                            );
+                            setter.end_set_parameter(&params.eq_channel_mode);
+
+                            setter.begin_set_parameter(&params.eq_design);
+                            setter.set_parameter(
+                                &params.eq_design,
+                                default_params.eq_design.default_plain_value(),
+                            );
+                            setter.end_set_parameter(&params.eq_design);
+
+                            setter.begin_set_parameter(&params.eq_phase_mode);
+                            setter.set_parameter(
+                                &params.eq_phase_mode,
+                                default_params.eq_phase_mode.default_plain_value(),
+                            );
+                            setter.end_set_parameter(&params.eq_phase_mode);
+
+                            setter.begin_set_parameter(&params.eq_preamp_mode);
+                            setter.set_parameter(
+                                &params.eq_preamp_mode,
+                                default_params.eq_preamp_mode.default_plain_value(),
+                            );
+                            setter.end_set_parameter(&params.eq_preamp_mode);
+
+                            setter.begin_set_parameter(&params.eq_preamp);
+                            setter.set_parameter(
+                                &params.eq_preamp,
+                                default_params.eq_preamp.default_plain_value(),
+                            );
+                            setter.end_set_parameter(&params.eq_preamp);
+
+                            setter.begin_set_parameter(&params.safety_limiter);
+                            setter.set_parameter(
+                                &params.safety_limiter,
+                                default_params.safety_limiter.default_plain_value(),
+                            );
+                            setter.end_set_parameter(&params.safety_limiter);
+
+                            params.eq_band_count.store(
+                                default_params.eq_band_count.load(Ordering::Relaxed),
+                                Ordering::Relaxed,
+                            );
                             for (i, band) in params.eq_bands.iter().enumerate() {
                                 setter.begin_set_parameter(&band.enabled);
                                 setter.set_parameter(
@@ -844,6 +2777,48 @@ impl Plugin for OpenHeadstagePlugin {
                                     default_params.eq_bands[i].gain.default_plain_value(),
                                 );
                                 setter.end_set_parameter(&band.gain);
+
+                                setter.begin_set_parameter(&band.channel);
+                                setter.set_parameter(
+                                    &band.channel,
+                                    default_params.eq_bands[i].channel.default_plain_value(),
+                                );
+                                setter.end_set_parameter(&band.channel);
+
+                                setter.begin_set_parameter(&band.dynamic);
+                                setter.set_parameter(
+                                    &band.dynamic,
+                                    default_params.eq_bands[i].dynamic.default_plain_value(),
+                                );
+                                setter.end_set_parameter(&band.dynamic);
+
+                                setter.begin_set_parameter(&band.threshold);
+                                setter.set_parameter(
+                                    &band.threshold,
+                                    default_params.eq_bands[i].threshold.default_plain_value(),
+                                );
+                                setter.end_set_parameter(&band.threshold);
+
+                                setter.begin_set_parameter(&band.ratio);
+                                setter.set_parameter(
+                                    &band.ratio,
+                                    default_params.eq_bands[i].ratio.default_plain_value(),
+                                );
+                                setter.end_set_parameter(&band.ratio);
+
+                                setter.begin_set_parameter(&band.attack);
+                                setter.set_parameter(
+                                    &band.attack,
+                                    default_params.eq_bands[i].attack.default_plain_value(),
+                                );
+                                setter.end_set_parameter(&band.attack);
+
+                                setter.begin_set_parameter(&band.release);
+                                setter.set_parameter(
+                                    &band.release,
+                                    default_params.eq_bands[i].release.default_plain_value(),
+                                );
+                                setter.end_set_parameter(&band.release);
                             }
                         }
                         if ui.button("Save Settings").clicked() {
@@ -851,6 +2826,42 @@ impl Plugin for OpenHeadstagePlugin {
                         }
                         ui.label("Output Gain");
                         ui.add(widgets::ParamSlider::for_param(&params.output_gain, setter));
+
+                        ui.horizontal(|ui| {
+                            let mut loudness_enabled = params.loudness_compensation.value();
+                            if ui
+                                .toggle_value(&mut loudness_enabled, "Loudness Compensation")
+                                .changed()
+                            {
+                                setter.begin_set_parameter(&params.loudness_compensation);
+                                setter
+                                    .set_parameter(&params.loudness_compensation, loudness_enabled);
+                                setter.end_set_parameter(&params.loudness_compensation);
+                            }
+                            if loudness_enabled {
+                                ui.add(widgets::ParamSlider::for_param(
+                                    &params.loudness_reference,
+                                    setter,
+                                ));
+                            }
+                        });
+
+                        ui.add_space(10.0);
+                        let meter_width = ui.available_width().min(400.0);
+                        ui.add(LevelMeters {
+                            label: "Input",
+                            readings: &input_levels,
+                            display: &mut state.input_meter_display,
+                            width: meter_width,
+                        });
+                        ui.add(LevelMeters {
+                            label: "Output",
+                            readings: &output_levels,
+                            display: &mut state.output_meter_display,
+                            width: meter_width,
+                        });
+                        // The meters move without any input
+                        ui.ctx().request_repaint();
                     });
 
                     egui::collapsing_header::CollapsingHeader::new(
@@ -894,6 +2905,54 @@ impl Plugin for OpenHeadstagePlugin {
                                 ));
                                 ui.end_row();
                             });
+
+                        ui.add_space(10.0);
+                        ui.horizontal(|ui| {
+                            let mut tracking_enabled = params.head_tracking.value();
+                            if ui
+                                .toggle_value(&mut tracking_enabled, "Head Tracking")
+                                .on_hover_text(
+                                    "Keep the speakers in place as the head turns, using opentrack's UDP output",
+                                )
+                                .changed()
+                            {
+                                setter.begin_set_parameter(&params.head_tracking);
+                                setter.set_parameter(&params.head_tracking, tracking_enabled);
+                                setter.end_set_parameter(&params.head_tracking);
+                            }
+                            ui.label("UDP Port");
+                            let mut port = params.head_tracking_port.load(Ordering::Relaxed);
+                            if ui
+                                .add(egui::DragValue::new(&mut port).range(1..=u16::MAX))
+                                .changed()
+                            {
+                                params.head_tracking_port.store(port, Ordering::Relaxed);
+                            }
+                            if ui
+                                .button("Recenter")
+                                .on_hover_text("Take the current head position as facing forward")
+                                .clicked()
+                            {
+                                head_tracking.recenter();
+                            }
+                        });
+                        if params.head_tracking.value() {
+                            let receiving = head_tracking
+                                .time_since_last_packet()
+                                .is_some_and(|age| age < Duration::from_secs(1));
+                            let status = if receiving {
+                                let pose = head_tracking.pose();
+                                format!(
+                                    "Yaw {:.0}°   Pitch {:.0}°   Roll {:.0}°",
+                                    pose.yaw, pose.pitch, pose.roll
+                                )
+                            } else if let Some(port) = head_tracking.bound_port() {
+                                format!("Waiting for opentrack on UDP port {}", port)
+                            } else {
+                                "Not listening".to_string()
+                            };
+                            ui.label(status);
+                        }
                     });
 
                     egui::collapsing_header::CollapsingHeader::new(
@@ -901,13 +2960,9 @@ impl Plugin for OpenHeadstagePlugin {
                     )
                     .default_open(true)
                     .show(ui, |ui| {
-                        ui.label("Audio Output");
-                        ui.label(
-                            egui::RichText::new("Changes require an application restart.")
-                                .size(12.0)
-                                .color(ui.visuals().warn_fg_color),
-                        );
+                        ui.label("Audio Devices");
 
+                        let mut device_changed = false;
                         let selected_host_name = state.selected_host_id.name().to_string();
                         egui::ComboBox::from_label("Host")
                             .selected_text(&selected_host_name)
@@ -925,6 +2980,11 @@ impl Plugin for OpenHeadstagePlugin {
                                             EditorState::get_output_devices_for_host(
                                                 &state.selected_host_id,
                                             );
+                                        state.available_input_devices =
+                                            standalone::devices::input_device_names(
+                                                state.selected_host_id,
+                                            );
+                                        params.audio_input_device.write().clear();
                                         *params.audio_host.write() =
                                             state.selected_host_id.name().to_string();
                                         // Select the default device of the new host
@@ -938,6 +2998,7 @@ impl Plugin for OpenHeadstagePlugin {
                                         } else {
                                             *params.audio_device.write() = String::new();
                                         }
+                                        device_changed = true;
                                     }
                                 }
                             });
@@ -956,9 +3017,304 @@ impl Plugin for OpenHeadstagePlugin {
                                         .changed()
                                     {
                                         *params.audio_device.write() = selected_device_name.clone();
+                                        device_changed = true;
                                     }
                                 }
                             });
+
+                        let mut selected_input_name = params.audio_input_device.read().clone();
+                        let input_text = if selected_input_name.is_empty() {
+                            "Default"
+                        } else {
+                            &selected_input_name
+                        }
+                        .to_string();
+                        egui::ComboBox::from_label("Input")
+                            .selected_text(input_text)
+                            .show_ui(ui, |ui| {
+                                let choices = std::iter::once(String::new())
+                                    .chain(state.available_input_devices.iter().cloned());
+                                for device_name in choices {
+                                    let text = if device_name.is_empty() {
+                                        "Default".to_string()
+                                    } else {
+                                        device_name.clone()
+                                    };
+                                    if ui
+                                        .selectable_value(
+                                            &mut selected_input_name,
+                                            device_name,
+                                            text,
+                                        )
+                                        .changed()
+                                    {
+                                        *params.audio_input_device.write() =
+                                            selected_input_name.clone();
+                                    }
+                                }
+                            });
+                        ui.label(
+                            egui::RichText::new(
+                                "A monitor or loopback input processes everything the system plays.",
+                            )
+                            .size(12.0),
+                        );
+
+                        // The headphones on the new device get their own settings
+                        let device = params.audio_device.read().clone();
+                        let profile = params.device_profiles.read().for_device(&device).cloned();
+                        if device_changed {
+                            if let Some(profile) = &profile {
+                                apply_device_profile(
+                                    profile,
+                                    &params,
+                                    &state.remote_params,
+                                    setter,
+                                    &async_executor,
+                                );
+                            }
+                        }
+
+                        ui.add_space(10.0);
+                        ui.label("Device Profiles");
+                        ui.label(match &profile {
+                            Some(profile) => format!("'{}' is used for this device.", profile.name),
+                            None => "There is no profile for this device.".to_string(),
+                        });
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::TextEdit::singleline(&mut state.device_profile_name)
+                                    .hint_text("Profile name")
+                                    .desired_width(160.0),
+                            );
+                            let name = state.device_profile_name.trim().to_string();
+                            if ui
+                                .add_enabled(
+                                    !name.is_empty() && !device.is_empty(),
+                                    egui::Button::new("Save for This Device"),
+                                )
+                                .on_hover_text(
+                                    "Keep the EQ, SOFA file, speakers and output gain for this output device",
+                                )
+                                .clicked()
+                            {
+                                let profile = DeviceProfile::capture(
+                                    &name,
+                                    &params.sofa_file_path.read(),
+                                    state
+                                        .remote_params
+                                        .values()
+                                        .into_iter()
+                                        .map(|feedback| (feedback.id, feedback.plain)),
+                                    params.eq_band_count.load(Ordering::Relaxed),
+                                );
+                                params.device_profiles.write().save(profile, &device);
+                            }
+                        });
+
+                        let names: Vec<String> = params
+                            .device_profiles
+                            .read()
+                            .profiles
+                            .iter()
+                            .map(|profile| profile.name.clone())
+                            .collect();
+                        let mut default_profile = params.device_profiles.read().default_profile.clone();
+                        egui::ComboBox::from_label("Default Profile")
+                            .selected_text(default_profile.clone().unwrap_or_else(|| "None".to_string()))
+                            .show_ui(ui, |ui| {
+                                let mut changed =
+                                    ui.selectable_value(&mut default_profile, None, "None").changed();
+                                for name in &names {
+                                    changed |= ui
+                                        .selectable_value(
+                                            &mut default_profile,
+                                            Some(name.clone()),
+                                            name,
+                                        )
+                                        .changed();
+                                }
+                                if changed {
+                                    params.device_profiles.write().default_profile =
+                                        default_profile.clone();
+                                }
+                            })
+                            .response
+                            .on_hover_text("Used for devices that don't have a profile");
+
+                        let mut removed = None;
+                        for profile in params.device_profiles.read().profiles.iter() {
+                            ui.horizontal(|ui| {
+                                ui.label(format!("{}: {}", profile.name, profile.devices.join(", ")));
+                                if ui.small_button("Remove").clicked() {
+                                    removed = Some(profile.name.clone());
+                                }
+                            });
+                        }
+                        if let Some(name) = removed {
+                            params.device_profiles.write().remove(&name);
+                        }
+
+                        ui.add_space(10.0);
+                        ui.label("Remote Control");
+                        ui.horizontal(|ui| {
+                            let mut osc_enabled = params.osc_enabled.load(Ordering::Relaxed);
+                            if ui
+                                .checkbox(&mut osc_enabled, "OSC Server")
+                                .on_hover_text(
+                                    "Control the parameters from a phone, tablet or script over OSC",
+                                )
+                                .changed()
+                            {
+                                params.osc_enabled.store(osc_enabled, Ordering::Relaxed);
+                            }
+                            ui.label("Port");
+                            let mut port = params.osc_port.load(Ordering::Relaxed);
+                            if ui
+                                .add(egui::DragValue::new(&mut port).range(1..=u16::MAX))
+                                .changed()
+                            {
+                                params.osc_port.store(port, Ordering::Relaxed);
+                            }
+                            ui.label("Feedback Port");
+                            let mut feedback_port =
+                                params.osc_feedback_port.load(Ordering::Relaxed);
+                            if ui
+                                .add(egui::DragValue::new(&mut feedback_port).range(0..=u16::MAX))
+                                .on_hover_text(
+                                    "Where parameter changes are sent back to. 0 replies to the port the client sends from.",
+                                )
+                                .changed()
+                            {
+                                params
+                                    .osc_feedback_port
+                                    .store(feedback_port, Ordering::Relaxed);
+                            }
+                            let mut allow_network =
+                                params.osc_allow_network.load(Ordering::Relaxed);
+                            if ui
+                                .checkbox(&mut allow_network, "Allow Network Clients")
+                                .on_hover_text(
+                                    "Listen on all network interfaces so a phone or tablet can connect. Anyone on the network can then change the settings and load files.",
+                                )
+                                .changed()
+                            {
+                                params
+                                    .osc_allow_network
+                                    .store(allow_network, Ordering::Relaxed);
+                            }
+                        });
+                        if params.osc_enabled.load(Ordering::Relaxed) {
+                            let status = match osc_state.bound_port() {
+                                Some(port) => format!(
+                                    "Listening on UDP port {}{}, {} clients",
+                                    port,
+                                    if params.osc_allow_network.load(Ordering::Relaxed) {
+                                        ""
+                                    } else {
+                                        " (this computer only)"
+                                    },
+                                    osc_state.client_count()
+                                ),
+                                None => "Not listening".to_string(),
+                            };
+                            ui.label(status);
+                        }
+
+                        ui.horizontal(|ui| {
+                            ui.label("MIDI Learn");
+                            let selected_label = state
+                                .midi_learn_param
+                                .as_deref()
+                                .and_then(|id| state.remote_params.label(id))
+                                .unwrap_or("Choose a parameter")
+                                .to_string();
+                            let mut picked = None;
+                            egui::ComboBox::from_id_salt("midi_learn_param")
+                                .selected_text(selected_label)
+                                .width(220.0)
+                                .show_ui(ui, |ui| {
+                                    for (id, label) in state.remote_params.labels() {
+                                        let selected =
+                                            state.midi_learn_param.as_deref() == Some(id);
+                                        if ui.selectable_label(selected, label).clicked() {
+                                            picked = Some(id.to_string());
+                                        }
+                                    }
+                                });
+                            if picked.is_some() {
+                                state.midi_learn_param = picked;
+                                *midi_learn.lock() = None;
+                            }
+                            let learning = midi_learn.lock().is_some();
+                            if learning {
+                                if ui.button("Cancel").clicked() {
+                                    *midi_learn.lock() = None;
+                                }
+                                ui.label("Move a control on the MIDI controller");
+                            } else if ui
+                                .add_enabled(
+                                    state.midi_learn_param.is_some(),
+                                    egui::Button::new("Learn"),
+                                )
+                                .on_hover_text("Bind the next control that moves to the parameter")
+                                .clicked()
+                            {
+                                *midi_learn.lock() = state.midi_learn_param.clone();
+                            }
+                        });
+                        ui.label(
+                            egui::RichText::new(
+                                "In plugin hosts, MIDI controllers and remote controls take effect once this window has been opened.",
+                            )
+                            .size(12.0)
+                            .color(ui.visuals().warn_fg_color),
+                        );
+                        let mut removed = None;
+                        for (i, mapping) in params.midi_mappings.read().iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                let label = state
+                                    .remote_params
+                                    .label(&mapping.param_id)
+                                    .unwrap_or(&mapping.param_id);
+                                ui.label(format!("{} → {}", mapping.control, label));
+                                if ui.small_button("Remove").clicked() {
+                                    removed = Some(i);
+                                }
+                            });
+                        }
+                        if let Some(i) = removed {
+                            params.midi_mappings.write().remove(i);
+                        }
+
+                        #[cfg(unix)]
+                        {
+                            ui.horizontal(|ui| {
+                                let mut enabled = params.json_rpc_enabled.load(Ordering::Relaxed);
+                                if ui
+                                    .checkbox(&mut enabled, "JSON-RPC Socket")
+                                    .on_hover_text(
+                                        "Let scripts and desktop tools control the app over a Unix domain socket",
+                                    )
+                                    .changed()
+                                {
+                                    params.json_rpc_enabled.store(enabled, Ordering::Relaxed);
+                                    json_rpc_state
+                                        .set_listening(enabled, json_rpc_socket_path(&params));
+                                }
+                                if enabled {
+                                    let status = match json_rpc_state.bound_path() {
+                                        Some(path) => format!(
+                                            "Listening on {}, {} clients",
+                                            path.display(),
+                                            json_rpc_state.client_count()
+                                        ),
+                                        None => "Not listening".to_string(),
+                                    };
+                                    ui.label(status);
+                                }
+                            });
+                        }
                     });
 
                     egui::collapsing_header::CollapsingHeader::new(
@@ -984,6 +3340,78 @@ impl Plugin for OpenHeadstagePlugin {
                             setter.end_set_parameter(&params.eq_enable);
                         }
 
+                        let design = params.eq_design.value();
+                        egui::ComboBox::new("eq_design", "Filter Design")
+                            .selected_text(design.name())
+                            .show_ui(ui, |ui| {
+                                for option in BiquadDesign::iter() {
+                                    if ui
+                                        .selectable_label(design == option, option.name())
+                                        .clicked()
+                                    {
+                                        setter.begin_set_parameter(&params.eq_design);
+                                        setter.set_parameter(&params.eq_design, option);
+                                        setter.end_set_parameter(&params.eq_design);
+                                    }
+                                }
+                            });
+
+                        let phase_mode = params.eq_phase_mode.value();
+                        egui::ComboBox::new("eq_phase_mode", "Phase")
+                            .selected_text(phase_mode.name())
+                            .show_ui(ui, |ui| {
+                                for mode in EqPhaseMode::iter() {
+                                    if ui
+                                        .selectable_label(phase_mode == mode, mode.name())
+                                        .clicked()
+                                    {
+                                        setter.begin_set_parameter(&params.eq_phase_mode);
+                                        setter.set_parameter(&params.eq_phase_mode, mode);
+                                        setter.end_set_parameter(&params.eq_phase_mode);
+                                    }
+                                }
+                            });
+
+                        ui.horizontal(|ui| {
+                            let preamp_mode = params.eq_preamp_mode.value();
+                            egui::ComboBox::new("preamp_mode", "Preamp")
+                                .selected_text(preamp_mode.name())
+                                .show_ui(ui, |ui| {
+                                    for mode in PreampMode::iter() {
+                                        if ui
+                                            .selectable_label(preamp_mode == mode, mode.name())
+                                            .clicked()
+                                        {
+                                            setter.begin_set_parameter(&params.eq_preamp_mode);
+                                            setter.set_parameter(&params.eq_preamp_mode, mode);
+                                            setter.end_set_parameter(&params.eq_preamp_mode);
+                                        }
+                                    }
+                                });
+                            if preamp_mode == PreampMode::Manual {
+                                ui.add(widgets::ParamSlider::for_param(&params.eq_preamp, setter));
+                            }
+                        });
+
+                        let headroom_db = f32::from_bits(eq_headroom_db.load(Ordering::Relaxed));
+                        let headroom_text =
+                            egui::RichText::new(format!("Headroom: {:+.1} dB", headroom_db));
+                        if headroom_db < 0.0 {
+                            ui.label(headroom_text.color(ui.visuals().error_fg_color));
+                        } else {
+                            ui.label(headroom_text);
+                        }
+
+                        let mut limiter_enabled = params.safety_limiter.value();
+                        if ui
+                            .toggle_value(&mut limiter_enabled, "True-Peak Limiter")
+                            .changed()
+                        {
+                            setter.begin_set_parameter(&params.safety_limiter);
+                            setter.set_parameter(&params.safety_limiter, limiter_enabled);
+                            setter.end_set_parameter(&params.safety_limiter);
+                        }
+
                         if ui
                             .add(
                                 egui::Button::new("Edit Parametric EQ")
@@ -997,14 +3425,11 @@ impl Plugin for OpenHeadstagePlugin {
                                 state.eq_editor_bands = params
                                     .eq_bands
                                     .iter()
-                                    .map(|p| BandSetting {
-                                        enabled: p.enabled.value(),
-                                        filter_type: p.filter_type.value(),
-                                        frequency: p.frequency.value(),
-                                        q: p.q.value(),
-                                        gain: p.gain.value(),
-                                    })
+                                    .map(EqBandParams::to_band_setting)
                                     .collect();
+                                state.eq_editor_band_count =
+                                    params.eq_band_count.load(Ordering::Relaxed);
+                                state.eq_editor_channel_mode = params.eq_channel_mode.value();
                             }
                         }
 
@@ -1019,7 +3444,38 @@ impl Plugin for OpenHeadstagePlugin {
                             state.file_dialog_request = Some(FileDialogRequest::AutoEq);
                         }
 
-                        if let Some(bands) = &state.loaded_eq_settings {
+                        egui::ComboBox::from_label("Target Curve")
+                            .selected_text(state.fit_target.name())
+                            .show_ui(ui, |ui| {
+                                for target in TargetCurve::BUILT_IN {
+                                    let name = target.name();
+                                    ui.selectable_value(&mut state.fit_target, target, name);
+                                }
+                                if ui
+                                    .selectable_label(
+                                        matches!(state.fit_target, TargetCurve::Custom(_)),
+                                        "Custom CSV...",
+                                    )
+                                    .clicked()
+                                {
+                                    state.file_dialog.pick_file();
+                                    state.file_dialog_request =
+                                        Some(FileDialogRequest::TargetCurve);
+                                }
+                            });
+
+                        if ui
+                            .add(
+                                egui::Button::new("Fit EQ to Measurement")
+                                    .min_size(egui::vec2(0.0, 20.0)),
+                            )
+                            .clicked()
+                        {
+                            state.file_dialog.pick_file();
+                            state.file_dialog_request = Some(FileDialogRequest::Measurement);
+                        }
+
+                        if let Some(profile) = &state.loaded_eq_settings {
                             if ui
                                 .add(
                                     egui::Button::new("Apply Loaded EQ")
@@ -1027,27 +3483,61 @@ impl Plugin for OpenHeadstagePlugin {
                                 )
                                 .clicked()
                             {
-                                setter.set_parameter(&params.eq_enable, true);
-                                for (i, band_param) in params.eq_bands.iter().enumerate() {
-                                    if let Some(band_setting) = bands.get(i) {
-                                        setter.set_parameter(
-                                            &band_param.enabled,
-                                            band_setting.enabled,
-                                        );
-                                        setter.set_parameter(
-                                            &band_param.filter_type,
-                                            band_setting.filter_type,
-                                        );
-                                        setter.set_parameter(
-                                            &band_param.frequency,
-                                            band_setting.frequency,
+                                apply_auto_eq_profile(profile, &params, setter);
+                            }
+                        }
+                    });
+
+                    egui::collapsing_header::CollapsingHeader::new(
+                        egui::RichText::new("Spectrum Analyzer").size(22.0),
+                    )
+                    .default_open(false)
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            for tap in AnalyzerTap::iter() {
+                                ui.checkbox(
+                                    &mut state.analyzer_shown[tap as usize],
+                                    egui::RichText::new(tap.name()).color(tap_color(tap)),
+                                );
+                            }
+                        });
+                        ui.horizontal(|ui| {
+                            let settings = &mut state.analyzer_settings;
+                            egui::ComboBox::new("analyzer_resolution", "FFT Size")
+                                .selected_text(settings.resolution.name())
+                                .show_ui(ui, |ui| {
+                                    for resolution in AnalyzerResolution::iter() {
+                                        ui.selectable_value(
+                                            &mut settings.resolution,
+                                            resolution,
+                                            resolution.name(),
                                         );
-                                        setter.set_parameter(&band_param.q, band_setting.q);
-                                        setter.set_parameter(&band_param.gain, band_setting.gain);
                                     }
-                                }
+                                });
+                            ui.label("Averaging");
+                            ui.add(
+                                egui::DragValue::new(&mut settings.averaging_ms)
+                                    .range(0.0..=5000.0)
+                                    .speed(10.0)
+                                    .suffix(" ms"),
+                            );
+                            ui.checkbox(&mut settings.peak_hold, "Peak Hold");
+                            if settings.peak_hold && ui.button("Reset Peaks").clicked() {
+                                spectrum_analyzer.lock().reset_peaks();
                             }
-                        }
+                        });
+
+                        let elapsed = ui.input(|input| input.unstable_dt);
+                        let mut analyzer = spectrum_analyzer.lock();
+                        analyzer.update(state.analyzer_settings, elapsed);
+                        ui.add(SpectrumView {
+                            analyzer: &analyzer,
+                            sample_rate: f32::from_bits(shared_sample_rate.load(Ordering::Relaxed)),
+                            shown: state.analyzer_shown,
+                            size: egui::vec2(ui.available_width(), 220.0),
+                        });
+                        // Keep the spectrum moving while it is visible
+                        ui.ctx().request_repaint();
                     });
                 });
 
@@ -1065,26 +3555,91 @@ impl Plugin for OpenHeadstagePlugin {
                                 result_mutex,
                             ));
                         }
+                        Some(FileDialogRequest::Measurement) => {
+                            async_executor.execute_background(Task::FitEq(
+                                path.to_path_buf(),
+                                state.fit_target.clone(),
+                                state.eq_editor_band_count,
+                                state.eq_fit_result.clone(),
+                            ));
+                        }
+                        Some(FileDialogRequest::TargetCurve) => {
+                            state.fit_target = TargetCurve::Custom(path.to_path_buf());
+                        }
+                        Some(FileDialogRequest::ImportPreset) => {
+                            let imported = get_presets_dir()
+                                .ok_or_else(|| "There is no presets folder".to_string())
+                                .and_then(|dir| {
+                                    presets::import_preset(&dir, path).map_err(|e| e.to_string())
+                                });
+                            match imported {
+                                Ok(name) => {
+                                    state.preset_status = Some(format!("Imported '{}'", name));
+                                    state.refresh_presets(Some(name));
+                                }
+                                Err(message) => state.preset_status = Some(message),
+                            }
+                        }
+                        Some(FileDialogRequest::ExportPreset) => {
+                            let mut path = path.to_path_buf();
+                            if path.extension().is_none() {
+                                path.set_extension("json");
+                            }
+                            let exported = match (get_presets_dir(), &state.selected_preset) {
+                                (Some(dir), Some(name)) => {
+                                    presets::export_preset(&dir, name, &path)
+                                        .map_err(|e| e.to_string())
+                                }
+                                _ => Err("There is no preset to export".to_string()),
+                            };
+                            state.preset_status = Some(match exported {
+                                Ok(()) => format!("Exported to {}", path.display()),
+                                Err(message) => message,
+                            });
+                        }
                         None => nih_log!("File dialog picked but no request was made."),
                     }
                     state.file_dialog_request = None;
                 }
 
-                if let Some(bands) = state.auto_eq_result.lock().take() {
-                    state.loaded_eq_settings = Some(bands);
+                if let Some(profile) = state.auto_eq_result.lock().take() {
+                    state.loaded_eq_settings = Some(profile);
+                }
+
+                // A finished fit is opened in the EQ editor so it can be reviewed before
+                // being applied.
+                if let Some(fit) = state.eq_fit_result.lock().take() {
+                    for (i, band_setting) in state.eq_editor_bands.iter_mut().enumerate() {
+                        match fit.bands.get(i) {
+                            Some(fitted) => *band_setting = fitted.clone(),
+                            None => band_setting.enabled = false,
+                        }
+                    }
+                    state.eq_editor_band_count = state.eq_editor_band_count.max(fit.bands.len());
+                    state.recommended_preamp = Some(fit.preamp_db);
+                    state.show_eq_editor = true;
                 }
             },
-        )
+        )?;
+        // Remote controls are applied with the editor's context, also once it is closed
+        Some(Box::new(ContextCapture {
+            editor,
+            context: self.gui_context.clone(),
+        }))
     }
 
     fn task_executor(&mut self) -> Box<dyn Fn(Self::BackgroundTask) + Send> {
-        let sample_rate = self.current_sample_rate;
+        // The executor is made before `initialize` sets the sample rate, and outlives
+        // changes to it
+        let shared_sample_rate = self.shared_sample_rate.clone();
+        let sample_rate = move || f32::from_bits(shared_sample_rate.load(Ordering::Relaxed));
         let sofa_loader = self.sofa_loader.clone();
+        let sofa_revision = self.sofa_revision.clone();
 
         Box::new(move |task| match task {
             Task::LoadSofa(path) => {
                 nih_log!("BACKGROUND: Loading SOFA file from: {:?}", path);
-                match MySofa::open(path.to_string_lossy().as_ref(), sample_rate) {
+                match MySofa::open(path.to_string_lossy().as_ref(), sample_rate()) {
                     Ok(loader) => {
                         nih_log!("BACKGROUND: Successfully loaded SOFA file: {:?}", path);
                         *sofa_loader.lock() = Some(loader);
@@ -1094,17 +3649,18 @@ impl Plugin for OpenHeadstagePlugin {
                         *sofa_loader.lock() = None;
                     }
                 }
+                sofa_revision.fetch_add(1, Ordering::Relaxed);
             }
             Task::LoadAutoEq(path, result_mutex) => {
                 nih_log!("BACKGROUND: Loading AutoEQ profile from: {:?}", path);
-                match autoeq_parser::parse_autoeq_csv(&path) {
-                    Ok(parsed_bands) => {
+                match autoeq_parser::parse_autoeq_file(&path) {
+                    Ok(profile) => {
                         nih_log!(
                             "BACKGROUND: Successfully parsed {} EQ bands from {:?}.",
-                            parsed_bands.len(),
+                            profile.bands.len(),
                             path
                         );
-                        *result_mutex.lock() = Some(parsed_bands);
+                        *result_mutex.lock() = Some(profile);
                     }
                     Err(e) => {
                         nih_log!(
@@ -1115,8 +3671,64 @@ impl Plugin for OpenHeadstagePlugin {
                     }
                 }
             }
-            Task::RequestEqResponse(_) => {
-                nih_log!("BACKGROUND: RequestEqResponse task not implemented yet.");
+            Task::FitEq(measurement_path, target, num_bands, result_mutex) => {
+                nih_log!(
+                    "BACKGROUND: Fitting EQ for {:?} to target '{}'",
+                    measurement_path,
+                    target.name()
+                );
+                let measurement = match eq_fitter::parse_frequency_response_csv(&measurement_path) {
+                    Ok(measurement) => measurement,
+                    Err(e) => {
+                        nih_log!(
+                            "BACKGROUND: Failed to parse measurement '{:?}': {:?}",
+                            measurement_path,
+                            e
+                        );
+                        return;
+                    }
+                };
+                let target_response = match target.load() {
+                    Ok(target_response) => target_response,
+                    Err(e) => {
+                        nih_log!(
+                            "BACKGROUND: Failed to load target curve '{}': {:?}",
+                            target.name(),
+                            e
+                        );
+                        return;
+                    }
+                };
+
+                let config = FitConfig {
+                    num_bands,
+                    sample_rate: sample_rate(),
+                    ..FitConfig::default()
+                };
+                let fit = eq_fitter::fit_eq(&measurement, &target_response, &config);
+                nih_log!(
+                    "BACKGROUND: Fitted {} EQ bands, preamp {:.1} dB, residual error {:.2} dB RMS.",
+                    fit.bands.len(),
+                    fit.preamp_db,
+                    fit.rms_error_db
+                );
+                *result_mutex.lock() = Some(fit);
+            }
+            Task::DesignLinearPhaseEq(request, serial, result_mutex) => {
+                *result_mutex.lock() = Some((serial, request.design()));
+            }
+            Task::RequestEqResponse(request, response_sender) => {
+                // Fails only if the editor was closed in the meantime
+                let _ = response_sender.send(request.compute());
+            }
+            Task::FetchHrirs(speakers, result_mutex, fetching) => {
+                if let Some(sofa) = sofa_loader.lock().as_ref() {
+                    match fetch_hrirs(sofa, speakers) {
+                        Ok(hrirs) => *result_mutex.lock() = Some(hrirs),
+                        Err(e) => nih_log!("BACKGROUND: Failed to fetch HRIRs: {:?}", e),
+                    }
+                }
+                fetching.store(false, Ordering::Relaxed);
             }
         })
     }
@@ -1125,23 +3737,75 @@ impl Plugin for OpenHeadstagePlugin {
         &mut self,
         _audio_io_layout: &AudioIOLayout,
         buffer_config: &BufferConfig,
-        _context: &mut impl InitContext<Self>,
+        context: &mut impl InitContext<Self>,
     ) -> bool {
         nih_log!("Initializing Open Headstage v{}", Self::VERSION);
 
-        self.current_sample_rate = buffer_config.sample_rate;
-        self.parametric_eq = StereoParametricEQ::new(NUM_EQ_BANDS, self.current_sample_rate);
-        self.convolution_engine = ConvolutionEngine::new();
-
-        let sofa_path_str = self.params.sofa_file_path.read();
-        if !sofa_path_str.is_empty() {
-            nih_log!("Attempting to load initial SOFA file: {}", sofa_path_str);
-            match MySofa::open(&sofa_path_str, self.current_sample_rate) {
-                Ok(sofa_loader) => {
-                    nih_log!("Successfully loaded SOFA file.");
-                    *self.sofa_loader.lock() = Some(sofa_loader)
+        if context.plugin_api() == PluginApi::Standalone {
+            // nih-plug's wrapper opens its devices once, so it only hosts the editor and
+            // the app runs the audio on devices it can switch while running
+            if self.audio_monitor.is_none() {
+                let twin = self.audio_twin();
+                match DeviceMonitor::spawn(twin, self.params.clone(), standalone::stream_options())
+                {
+                    Ok(audio_monitor) => self.audio_monitor = Some(audio_monitor),
+                    Err(e) => nih_log!("Failed to start the audio host: {}", e),
                 }
-                Err(e) => nih_log!("Failed to load SOFA file '{}': {:?}", sofa_path_str, e),
+            }
+        } else {
+            self.setup(
+                buffer_config.sample_rate,
+                buffer_config.max_buffer_size as usize,
+                &mut InitContextHost(context),
+            );
+        }
+        self.start_head_tracker();
+        if self.osc_server.is_none() {
+            match OscServer::spawn(
+                self.osc_state.clone(),
+                self.remote_sender.clone(),
+                self.head_tracking.clone(),
+            ) {
+                Ok(osc_server) => self.osc_server = Some(osc_server),
+                Err(e) => nih_log!("Failed to start the OSC server: {}", e),
+            }
+        }
+        self.gui_context.restore();
+        if self.remote_worker.is_none() {
+            let remote_worker_context = RemoteWorkerContext {
+                params: self.params.clone(),
+                gui_context: self.gui_context.clone(),
+                commands: self.remote_receiver.clone(),
+                midi: self.midi_receiver.clone(),
+                midi_learn: self.midi_learn.clone(),
+                osc_state: self.osc_state.clone(),
+                executor: self.task_executor(),
+                loaded_auto_eq: self.remote_auto_eq_result.clone(),
+            };
+            match RemoteWorker::spawn(remote_worker_context) {
+                Ok(remote_worker) => self.remote_worker = Some(remote_worker),
+                Err(e) => nih_log!("Failed to start the remote worker: {}", e),
+            }
+        }
+        #[cfg(unix)]
+        if self.json_rpc_server.is_none() && context.plugin_api() == PluginApi::Standalone {
+            self.json_rpc_state.set_listening(
+                self.params.json_rpc_enabled.load(Ordering::Relaxed),
+                json_rpc_socket_path(&self.params),
+            );
+            let json_rpc_context = JsonRpcContext {
+                params: RemoteParams::new(self.params.clone()),
+                commands: self.remote_sender.clone(),
+                input_levels: self.input_levels.clone(),
+                output_levels: self.output_levels.clone(),
+                latency_samples: self.shared_latency_samples.clone(),
+                sample_rate: self.shared_sample_rate.clone(),
+                eq_band_count: self.params.eq_band_count.clone(),
+                presets_dir: get_presets_dir(),
+            };
+            match JsonRpcServer::spawn(self.json_rpc_state.clone(), json_rpc_context) {
+                Ok(json_rpc_server) => self.json_rpc_server = Some(json_rpc_server),
+                Err(e) => nih_log!("Failed to start the JSON-RPC server: {}", e),
             }
         }
 
@@ -1149,15 +3813,27 @@ impl Plugin for OpenHeadstagePlugin {
         true
     }
 
+    fn deactivate(&mut self) {
+        // The editor's context keeps nih-plug's wrapper, and with it this plugin, alive
+        self.gui_context.release();
+    }
+
     fn reset(&mut self) {
         self.parametric_eq.reset_all_bands_state();
+        self.loudness.reset();
+        self.input_meter.reset();
+        self.output_meter.reset();
+        self.bypass.reset(self.params.master_bypass.value());
+        self.limiter.reset();
+        self.linear_phase_eq.reset();
+        self.preamp_gain.reset(1.0);
     }
 
     fn process(
         &mut self,
         buffer: &mut Buffer,
         _aux: &mut AuxiliaryBuffers,
-        _context: &mut impl ProcessContext<Self>,
+        context: &mut impl ProcessContext<Self>,
     ) -> ProcessStatus {
         if !self
             .has_logged_processing_start
@@ -1166,46 +3842,27 @@ impl Plugin for OpenHeadstagePlugin {
             nih_log!("Audio processing started.");
         }
 
-        if !self.params.master_bypass.value() {
-            let _az_l = self.params.speaker_azimuth_left.smoothed.next();
-            let _el_l = self.params.speaker_elevation_left.smoothed.next();
-            let _az_r = self.params.speaker_azimuth_right.smoothed.next();
-            let _el_r = self.params.speaker_elevation_right.smoothed.next();
-
-            let [left, right] = buffer.as_slice() else {
-                return ProcessStatus::Error("Mismatched channel count");
-            };
-
-            if self.params.eq_enable.value() {
-                for (i, band_params) in self.params.eq_bands.iter().enumerate() {
-                    let band_config = BandConfig {
-                        filter_type: band_params.filter_type.value(),
-                        center_freq: band_params.frequency.smoothed.next(),
-                        q: band_params.q.smoothed.next(),
-                        gain_db: band_params.gain.smoothed.next(),
-                        enabled: band_params.enabled.value(),
-                    };
-                    self.parametric_eq.update_band_coeffs(
-                        i,
-                        self.current_sample_rate,
-                        &band_config,
-                    );
+        while let Some(event) = context.next_event() {
+            if let NoteEvent::MidiCC {
+                channel, cc, value, ..
+            } = event
+            {
+                if let Some(message) = self.midi_decoder.decode(channel, cc, value) {
+                    // Messages are dropped while the queue is full
+                    let _ = self.midi_sender.try_send(message);
                 }
-                self.parametric_eq.process_block(left, right);
             }
+        }
 
-            let input_l = left.to_vec();
-            let input_r = right.to_vec();
-            self.convolution_engine
-                .process_block(&input_l, &input_r, left, right);
-
-            let master_gain = self.params.output_gain.smoothed.next();
-            for mut channel_samples in buffer.iter_samples() {
-                for sample in channel_samples.iter_mut() {
-                    *sample *= master_gain;
-                }
-            }
+        if self.audio_monitor.is_some() {
+            // The standalone app's audio host processes the audio
+            self.update_listeners();
+            return ProcessStatus::Normal;
         }
+        let [left, right] = buffer.as_slice() else {
+            return ProcessStatus::Error("Mismatched channel count");
+        };
+        self.process_stereo(left, right, &mut ProcessContextHost(context));
 
         ProcessStatus::Normal
     }
@@ -1231,3 +3888,51 @@ impl ClapPlugin for OpenHeadstagePlugin {
 
 nih_export_clap!(OpenHeadstagePlugin);
 // nih_export_vst3!(OpenHeadstagePlugin);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    /// Presets and device profiles are applied to the settings in headless mode, so
+    /// every parameter has to be stored there.
+    #[test]
+    fn test_config_applies_every_param() {
+        let mut config = StandaloneConfig::pre_default();
+        let params = OpenHeadstageParams::new(config.clone());
+        // A value away from each parameter's default, so one that isn't applied stands out
+        let expected: BTreeMap<String, f32> = params
+            .param_map()
+            .into_iter()
+            .map(|(id, param, _)| {
+                // SAFETY: `params` outlives the pointers
+                let plain = unsafe {
+                    let normalized = if param.default_normalized_value() < 0.5 {
+                        1.0
+                    } else {
+                        0.0
+                    };
+                    param.preview_plain(normalized)
+                };
+                (id, plain)
+            })
+            .collect();
+        config.apply_preset(&Preset {
+            params: expected.clone(),
+            eq_band_count: None,
+        });
+
+        let applied = OpenHeadstageParams::new(config);
+        for (id, param, _) in applied.param_map() {
+            // SAFETY: See above
+            let value = unsafe { param.unmodulated_plain_value() };
+            assert!(
+                (value - expected[&id]).abs() <= 1e-4 * expected[&id].abs().max(1.0),
+                "'{}' is {} instead of {}",
+                id,
+                value,
+                expected[&id]
+            );
+        }
+    }
+}
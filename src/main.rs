@@ -12,9 +12,8 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use nih_plug::prelude::*;
-use open_headstage::OpenHeadstagePlugin;
+use std::process::ExitCode;
 
-fn main() {
-    nih_export_standalone::<OpenHeadstagePlugin>();
+fn main() -> ExitCode {
+    open_headstage::standalone::run(std::env::args())
 }
@@ -0,0 +1,338 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/presets.rs
+
+//! Presets are snapshots of the parameters, kept as one JSON file per preset in the
+//! presets folder. The file name is the preset's name. The factory presets are built in
+//! and can be loaded by name unless a saved preset has the same name.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, ErrorKind};
+use std::path::{Path, PathBuf};
+
+const PRESET_EXTENSION: &str = "json";
+
+/// The factory presets' names with their speaker azimuth and elevation in degrees
+const FACTORY_LAYOUTS: [(&str, f32, f32); 3] = [
+    ("Stereo Triangle 30°", 30.0, 0.0),
+    ("Wide 45°", 45.0, 0.0),
+    // Desktop monitors sit a little closer together and below the ears
+    ("Near-field", 25.0, -10.0),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Preset {
+    /// Plain values by parameter ID. Parameters that are missing keep their values.
+    pub params: BTreeMap<String, f32>,
+    /// The number of active EQ bands, which isn't a parameter
+    #[serde(default)]
+    pub eq_band_count: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum PresetError {
+    InvalidName(String),
+    NotFound(String),
+    Exists(String),
+    Io(io::Error),
+    Parse(serde_json::Error),
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetError::InvalidName(name) => write!(f, "'{}' is not a valid preset name", name),
+            PresetError::NotFound(name) => write!(f, "There is no preset named '{}'", name),
+            PresetError::Exists(name) => write!(f, "There is already a preset named '{}'", name),
+            PresetError::Io(e) => write!(f, "{}", e),
+            PresetError::Parse(e) => write!(f, "Invalid preset file: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for PresetError {
+    fn from(err: io::Error) -> Self {
+        PresetError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for PresetError {
+    fn from(err: serde_json::Error) -> Self {
+        PresetError::Parse(err)
+    }
+}
+
+/// The file a preset is kept in. Names can't contain path separators or start with a
+/// dot, so presets stay inside `dir`.
+pub fn preset_path(dir: &Path, name: &str) -> Result<PathBuf, PresetError> {
+    let valid = !name.trim().is_empty()
+        && !name.starts_with('.')
+        && !name.contains(['/', '\\'])
+        && !name.chars().any(char::is_control);
+    if !valid {
+        return Err(PresetError::InvalidName(name.to_string()));
+    }
+    Ok(dir.join(format!("{}.{}", name, PRESET_EXTENSION)))
+}
+
+/// The names of the presets in `dir`, sorted. A missing folder has no presets.
+pub fn list_presets(dir: &Path) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == PRESET_EXTENSION))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().to_string()))
+        .collect();
+    names.sort_by_key(|name| name.to_lowercase());
+    names
+}
+
+/// The built-in presets. They only set the speaker layout, so loading one keeps the EQ.
+pub fn factory_presets() -> Vec<(&'static str, Preset)> {
+    FACTORY_LAYOUTS
+        .iter()
+        .map(|&(name, azimuth, elevation)| {
+            let params = [
+                ("az_l", -azimuth),
+                ("el_l", elevation),
+                ("az_r", azimuth),
+                ("el_r", elevation),
+            ];
+            let preset = Preset {
+                params: params
+                    .into_iter()
+                    .map(|(id, value)| (id.to_string(), value))
+                    .collect(),
+                eq_band_count: None,
+            };
+            (name, preset)
+        })
+        .collect()
+}
+
+/// The names of the factory presets followed by those of the presets in `dir`, which is
+/// everything `load_preset` can load. A saved preset that takes a factory preset's name
+/// is listed once.
+pub fn all_preset_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = FACTORY_LAYOUTS
+        .iter()
+        .map(|&(name, ..)| name.to_string())
+        .collect();
+    names.extend(
+        list_presets(dir)
+            .into_iter()
+            .filter(|name| !is_factory_preset(name)),
+    );
+    names
+}
+
+pub fn is_factory_preset(name: &str) -> bool {
+    FACTORY_LAYOUTS
+        .iter()
+        .any(|&(factory_name, ..)| factory_name == name)
+}
+
+/// Loads a saved preset, or the factory preset with that name.
+pub fn load_preset(dir: &Path, name: &str) -> Result<Preset, PresetError> {
+    let text = match fs::read_to_string(preset_path(dir, name)?) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return factory_presets()
+                .into_iter()
+                .find(|(factory_name, _)| *factory_name == name)
+                .map(|(_, preset)| preset)
+                .ok_or_else(|| PresetError::NotFound(name.to_string()));
+        }
+        Err(e) => return Err(e.into()),
+    };
+    Ok(serde_json::from_str(&text)?)
+}
+
+/// Saves a preset, replacing the one with the same name.
+pub fn save_preset(dir: &Path, name: &str, preset: &Preset) -> Result<(), PresetError> {
+    let path = preset_path(dir, name)?;
+    fs::create_dir_all(dir)?;
+    fs::write(path, serde_json::to_string_pretty(preset)?)?;
+    Ok(())
+}
+
+/// Renames a saved preset. The new name can't be taken by another preset.
+pub fn rename_preset(dir: &Path, from: &str, to: &str) -> Result<(), PresetError> {
+    let from_path = preset_path(dir, from)?;
+    let to_path = preset_path(dir, to)?;
+    if !from_path.exists() {
+        return Err(PresetError::NotFound(from.to_string()));
+    }
+    // Changing only the case is fine on file systems that ignore it
+    let same_preset = from.to_lowercase() == to.to_lowercase();
+    if !same_preset && (to_path.exists() || is_factory_preset(to)) {
+        return Err(PresetError::Exists(to.to_string()));
+    }
+    fs::rename(from_path, to_path)?;
+    Ok(())
+}
+
+pub fn delete_preset(dir: &Path, name: &str) -> Result<(), PresetError> {
+    match fs::remove_file(preset_path(dir, name)?) {
+        Err(e) if e.kind() == ErrorKind::NotFound => Err(PresetError::NotFound(name.to_string())),
+        result => Ok(result?),
+    }
+}
+
+/// Copies a preset file into the presets folder, named after the file. A number is added
+/// to names that are taken. Returns the name it was saved under.
+pub fn import_preset(dir: &Path, path: &Path) -> Result<String, PresetError> {
+    let preset: Preset = serde_json::from_str(&fs::read_to_string(path)?)?;
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let taken =
+        |name: &str| is_factory_preset(name) || preset_path(dir, name).is_ok_and(|p| p.exists());
+    let name = std::iter::once(stem.clone())
+        .chain((2..).map(|n| format!("{} {}", stem, n)))
+        .find(|name| !taken(name))
+        .unwrap_or(stem);
+    save_preset(dir, &name, &preset)?;
+    Ok(name)
+}
+
+/// Writes a saved or factory preset to a file of its own, to share it or import it
+/// elsewhere.
+pub fn export_preset(dir: &Path, name: &str, path: &Path) -> Result<(), PresetError> {
+    let preset = load_preset(dir, name)?;
+    fs::write(path, serde_json::to_string_pretty(&preset)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "open-headstage-presets-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_presets_round_trip() {
+        let dir = test_dir("round-trip");
+        assert!(list_presets(&dir).is_empty());
+
+        let preset = Preset {
+            // The output gain is a linear gain, this is about -6 dB
+            params: BTreeMap::from([("out_gain".to_string(), 0.5), ("bypass".to_string(), 1.0)]),
+            eq_band_count: Some(12),
+        };
+        save_preset(&dir, "Late Night", &preset).unwrap();
+        save_preset(&dir, "flat", &Preset::default()).unwrap();
+        fs::write(dir.join("notes.txt"), "not a preset").unwrap();
+
+        assert_eq!(list_presets(&dir), vec!["flat", "Late Night"]);
+        assert_eq!(
+            all_preset_names(&dir),
+            vec![
+                "Stereo Triangle 30°",
+                "Wide 45°",
+                "Near-field",
+                "flat",
+                "Late Night"
+            ]
+        );
+        assert_eq!(load_preset(&dir, "Late Night").unwrap(), preset);
+        assert!(matches!(
+            load_preset(&dir, "missing"),
+            Err(PresetError::NotFound(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_names_stay_inside_the_folder() {
+        let dir = Path::new("/presets");
+        assert_eq!(
+            preset_path(dir, "Harman 2018").unwrap(),
+            Path::new("/presets/Harman 2018.json")
+        );
+        for name in ["", "  ", "../config", "a/b", "a\\b", ".hidden"] {
+            assert!(
+                matches!(preset_path(dir, name), Err(PresetError::InvalidName(_))),
+                "Accepted '{}'",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn test_manage_presets() {
+        let dir = test_dir("manage");
+        save_preset(&dir, "Studio", &Preset::default()).unwrap();
+        save_preset(&dir, "Late Night", &Preset::default()).unwrap();
+
+        rename_preset(&dir, "Studio", "Desk").unwrap();
+        assert_eq!(list_presets(&dir), vec!["Desk", "Late Night"]);
+        assert!(matches!(
+            rename_preset(&dir, "Desk", "Late Night"),
+            Err(PresetError::Exists(_))
+        ));
+        assert!(matches!(
+            rename_preset(&dir, "Desk", "Wide 45°"),
+            Err(PresetError::Exists(_))
+        ));
+        rename_preset(&dir, "Desk", "desk").unwrap();
+        delete_preset(&dir, "desk").unwrap();
+        assert!(matches!(
+            delete_preset(&dir, "desk"),
+            Err(PresetError::NotFound(_))
+        ));
+
+        // Factory presets load by name until a saved preset takes it
+        let wide = load_preset(&dir, "Wide 45°").unwrap();
+        assert_eq!(wide.params["az_l"], -45.0);
+        assert_eq!(wide.params["az_r"], 45.0);
+        assert_eq!(factory_presets().len(), 3);
+
+        // Exported presets import under a free name
+        let file = dir.join("Late Night.json");
+        let exported =
+            std::env::temp_dir().join(format!("open-headstage-export-{}.json", std::process::id()));
+        export_preset(&dir, "Late Night", &exported).unwrap();
+        assert_eq!(
+            fs::read_to_string(&exported).unwrap(),
+            fs::read_to_string(&file).unwrap()
+        );
+        assert_eq!(import_preset(&dir, &file).unwrap(), "Late Night 2");
+        export_preset(&dir, "Near-field", &exported).unwrap();
+        let name = import_preset(&dir, &exported).unwrap();
+        assert_eq!(
+            load_preset(&dir, &name).unwrap(),
+            load_preset(&dir, "Near-field").unwrap()
+        );
+
+        fs::remove_file(&exported).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,735 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/remote/json_rpc.rs
+
+//! A JSON-RPC 2.0 server on a Unix domain socket, for scripts and desktop tools. Every
+//! request, response and notification is one line of JSON. The methods are:
+//!
+//! - `params.list`: all parameters as `{id, name, value, normalized, text}`
+//! - `params.get {id}`: one parameter, like `params.list`
+//! - `params.set {id, value | normalized | text}`: sets a parameter to a plain value,
+//!   to a value from 0 to 1, or from text like "-6 dB"
+//! - `params.subscribe` and `params.unsubscribe`: start and stop `params.changed`
+//!   notifications, which have the same fields as `params.get`
+//! - `presets.list`, `presets.load {name}` and `presets.save {name}`; the list starts
+//!   with the factory presets
+//! - `files.load_sofa {path}` and `files.load_autoeq {path}`
+//! - `meters.get`: the RMS levels and loudness of the input and the output
+//! - `latency.get`: the latency reported to the host, as `{samples, ms}`
+//!
+//! Changes are applied by the remote worker like those of the other remote controls, so
+//! they are queued when the call returns and take effect a moment later.
+
+use crossbeam_channel::{Sender, TrySendError};
+use nih_plug::nih_log;
+use nih_plug::util;
+use parking_lot::Mutex;
+use serde_json::{Value, json};
+use std::fs;
+use std::io::{self, BufRead, BufReader, ErrorKind, Write};
+use std::net::Shutdown;
+use std::os::unix::fs::FileTypeExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::params::{ParamFeedback, RemoteParams};
+use super::{RemoteCommand, RemoteValue};
+use crate::dsp::meter::MeterReadings;
+use crate::presets::{self, Preset, PresetError};
+
+pub const SOCKET_NAME: &str = "open-headstage.sock";
+
+// How often the server accepts connections and sends change notifications
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+// How long to wait before retrying a socket that couldn't be bound
+const BIND_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+// A client that stops reading mustn't hold up the others
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const SERVER_ERROR: i64 = -32000;
+
+/// The socket that is used when no path is set, in the user's runtime directory.
+pub fn default_socket_path() -> PathBuf {
+    dirs::runtime_dir()
+        .unwrap_or_else(std::env::temp_dir)
+        .join(SOCKET_NAME)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<PresetError> for RpcError {
+    fn from(err: PresetError) -> Self {
+        let code = match err {
+            PresetError::InvalidName(_) => INVALID_PARAMS,
+            _ => SERVER_ERROR,
+        };
+        RpcError::new(code, err.to_string())
+    }
+}
+
+/// What the server reads and controls.
+pub struct JsonRpcContext {
+    pub params: RemoteParams,
+    pub commands: Sender<RemoteCommand>,
+    pub input_levels: Arc<MeterReadings>,
+    pub output_levels: Arc<MeterReadings>,
+    /// The latency in samples, and the sample rate as `f32` bits
+    pub latency_samples: Arc<AtomicU32>,
+    pub sample_rate: Arc<AtomicU32>,
+    /// Saved with presets, since it isn't a parameter
+    pub eq_band_count: Arc<AtomicUsize>,
+    pub presets_dir: Option<PathBuf>,
+}
+
+impl JsonRpcContext {
+    fn call(&mut self, method: &str, params: &Value) -> Result<Value, RpcError> {
+        match method {
+            "params.list" => Ok(Value::Array(
+                self.params
+                    .values()
+                    .iter()
+                    .map(|feedback| self.param_json(feedback))
+                    .collect(),
+            )),
+            "params.get" => {
+                let id = string_param(params, "id")?;
+                let feedback = self.params.get(id).ok_or_else(|| unknown_param(id))?;
+                Ok(self.param_json(&feedback))
+            }
+            "params.set" => {
+                let id = string_param(params, "id")?;
+                let value = if let Some(value) = params.get("value") {
+                    match value {
+                        Value::Bool(value) => RemoteValue::Plain(if *value { 1.0 } else { 0.0 }),
+                        value => RemoteValue::Plain(number(value, "value")?),
+                    }
+                } else if let Some(normalized) = params.get("normalized") {
+                    RemoteValue::Normalized(number(normalized, "normalized")?)
+                } else if params.get("text").is_some() {
+                    RemoteValue::Text(string_param(params, "text")?.to_string())
+                } else {
+                    return Err(RpcError::new(
+                        INVALID_PARAMS,
+                        "Expected 'value', 'normalized' or 'text'",
+                    ));
+                };
+                if self.params.get(id).is_none() {
+                    return Err(unknown_param(id));
+                }
+                let normalized = self.params.resolve(id, &value).ok_or_else(|| {
+                    RpcError::new(
+                        INVALID_PARAMS,
+                        format!("Cannot set '{}' to {:?}", id, value),
+                    )
+                })?;
+                self.queue(RemoteCommand::SetParameter {
+                    id: id.to_string(),
+                    value: RemoteValue::Normalized(normalized),
+                })
+            }
+            "presets.list" => Ok(json!(
+                self.presets_dir
+                    .as_deref()
+                    .map(presets::all_preset_names)
+                    .unwrap_or_default()
+            )),
+            "presets.load" => {
+                let preset =
+                    presets::load_preset(self.presets_dir()?, string_param(params, "name")?)?;
+                self.queue(RemoteCommand::LoadPreset(preset))
+            }
+            "presets.save" => {
+                let preset = Preset {
+                    params: self
+                        .params
+                        .values()
+                        .into_iter()
+                        .map(|feedback| (feedback.id, feedback.plain))
+                        .collect(),
+                    eq_band_count: Some(self.eq_band_count.load(Ordering::Relaxed)),
+                };
+                presets::save_preset(self.presets_dir()?, string_param(params, "name")?, &preset)?;
+                Ok(Value::Null)
+            }
+            "files.load_sofa" => {
+                let path = PathBuf::from(string_param(params, "path")?);
+                self.queue(RemoteCommand::LoadSofa(path))
+            }
+            "files.load_autoeq" => {
+                let path = PathBuf::from(string_param(params, "path")?);
+                self.queue(RemoteCommand::LoadAutoEq(path))
+            }
+            "meters.get" => Ok(json!({
+                "input": meter_json(&self.input_levels),
+                "output": meter_json(&self.output_levels),
+            })),
+            "latency.get" => {
+                let samples = self.latency_samples.load(Ordering::Relaxed);
+                let sample_rate = f32::from_bits(self.sample_rate.load(Ordering::Relaxed));
+                Ok(json!({
+                    "samples": samples,
+                    "ms": samples as f32 / sample_rate * 1000.0,
+                }))
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("Unknown method '{}'", method),
+            )),
+        }
+    }
+
+    fn param_json(&self, feedback: &ParamFeedback) -> Value {
+        json!({
+            "id": feedback.id,
+            "name": self.params.label(&feedback.id).unwrap_or(&feedback.id),
+            "value": feedback.plain,
+            "normalized": feedback.normalized,
+            "text": feedback.text,
+        })
+    }
+
+    fn presets_dir(&self) -> Result<&Path, RpcError> {
+        self.presets_dir
+            .as_deref()
+            .ok_or_else(|| RpcError::new(SERVER_ERROR, "There is no presets folder"))
+    }
+
+    fn queue(&self, command: RemoteCommand) -> Result<Value, RpcError> {
+        match self.commands.try_send(command) {
+            Ok(()) => Ok(Value::Null),
+            Err(TrySendError::Full(_)) => Err(RpcError::new(
+                SERVER_ERROR,
+                "Too many changes are waiting to be applied",
+            )),
+            Err(TrySendError::Disconnected(_)) => {
+                Err(RpcError::new(SERVER_ERROR, "The plugin has shut down"))
+            }
+        }
+    }
+}
+
+fn unknown_param(id: &str) -> RpcError {
+    RpcError::new(INVALID_PARAMS, format!("Unknown parameter '{}'", id))
+}
+
+fn string_param<'a>(params: &'a Value, name: &str) -> Result<&'a str, RpcError> {
+    params
+        .get(name)
+        .and_then(Value::as_str)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Expected a string '{}'", name)))
+}
+
+fn number(value: &Value, name: &str) -> Result<f32, RpcError> {
+    value
+        .as_f64()
+        .map(|value| value as f32)
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("Expected a number '{}'", name)))
+}
+
+fn meter_json(readings: &MeterReadings) -> Value {
+    json!({
+        "rms_db": [util::gain_to_db(readings.rms(0)), util::gain_to_db(readings.rms(1))],
+        "momentary_lufs": readings.momentary_lufs(),
+        "short_term_lufs": readings.short_term_lufs(),
+        "integrated_lufs": readings.integrated_lufs(),
+        "clipped": readings.clipped(),
+    })
+}
+
+fn error_response(id: Value, error: RpcError) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": error.code, "message": error.message },
+    })
+}
+
+/// Answers a line with a request or a batch of them. Requests without an ID are
+/// notifications and get no response.
+fn handle_line(
+    context: &Mutex<JsonRpcContext>,
+    line: &str,
+    subscribed: &AtomicBool,
+) -> Option<Value> {
+    let request: Value = match serde_json::from_str(line) {
+        Ok(request) => request,
+        Err(e) => {
+            return Some(error_response(
+                Value::Null,
+                RpcError::new(PARSE_ERROR, e.to_string()),
+            ));
+        }
+    };
+    match request {
+        Value::Array(batch) if !batch.is_empty() => {
+            let responses: Vec<Value> = batch
+                .iter()
+                .filter_map(|request| handle_request(context, request, subscribed))
+                .collect();
+            (!responses.is_empty()).then_some(Value::Array(responses))
+        }
+        request => handle_request(context, &request, subscribed),
+    }
+}
+
+fn handle_request(
+    context: &Mutex<JsonRpcContext>,
+    request: &Value,
+    subscribed: &AtomicBool,
+) -> Option<Value> {
+    let id = request.get("id").cloned();
+    let method = request
+        .get("method")
+        .and_then(Value::as_str)
+        .filter(|_| request.get("jsonrpc").and_then(Value::as_str) == Some("2.0"));
+    let Some(method) = method else {
+        let error = RpcError::new(INVALID_REQUEST, "Expected a JSON-RPC 2.0 request");
+        return Some(error_response(id.unwrap_or(Value::Null), error));
+    };
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+
+    let result = match method {
+        "params.subscribe" => {
+            subscribed.store(true, Ordering::Relaxed);
+            Ok(Value::Null)
+        }
+        "params.unsubscribe" => {
+            subscribed.store(false, Ordering::Relaxed);
+            Ok(Value::Null)
+        }
+        method => context.lock().call(method, &params),
+    };
+    let id = id?;
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => error_response(id, error),
+    })
+}
+
+/// The server's settings and status, shared between the server thread and the editor.
+pub struct JsonRpcState {
+    enabled: AtomicBool,
+    path: Mutex<PathBuf>,
+    // The path of the socket while it is open
+    bound_path: Mutex<Option<PathBuf>>,
+    clients: AtomicUsize,
+    stop: AtomicBool,
+}
+
+impl Default for JsonRpcState {
+    fn default() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            path: Mutex::new(default_socket_path()),
+            bound_path: Mutex::new(None),
+            clients: AtomicUsize::new(0),
+            stop: AtomicBool::new(false),
+        }
+    }
+}
+
+impl JsonRpcState {
+    /// Opens or closes the socket, or moves it. The server thread picks the change up
+    /// within `POLL_INTERVAL`.
+    pub fn set_listening(&self, enabled: bool, path: PathBuf) {
+        *self.path.lock() = path;
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn bound_path(&self) -> Option<PathBuf> {
+        self.bound_path.lock().clone()
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.load(Ordering::Relaxed)
+    }
+}
+
+struct Client {
+    writer: Mutex<UnixStream>,
+    subscribed: AtomicBool,
+}
+
+impl Client {
+    fn send(&self, message: &Value) -> io::Result<()> {
+        let mut line = message.to_string();
+        line.push('\n');
+        self.writer.lock().write_all(line.as_bytes())
+    }
+}
+
+/// Runs the thread that accepts connections. Each client gets a thread of its own.
+/// Dropping the server closes the socket and stops all of them.
+pub struct JsonRpcServer {
+    state: Arc<JsonRpcState>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl JsonRpcServer {
+    pub fn spawn(state: Arc<JsonRpcState>, context: JsonRpcContext) -> io::Result<Self> {
+        state.stop.store(false, Ordering::Relaxed);
+        let thread_state = state.clone();
+        let thread = thread::Builder::new()
+            .name("json-rpc-server".to_string())
+            .spawn(move || serve(&thread_state, context))?;
+        Ok(Self {
+            state,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for JsonRpcServer {
+    fn drop(&mut self) {
+        self.state.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn bind(path: &Path) -> io::Result<UnixListener> {
+    if let Ok(metadata) = fs::symlink_metadata(path) {
+        // Never delete whatever else the path was mistakenly pointed at
+        if !metadata.file_type().is_socket() {
+            return Err(io::Error::new(
+                ErrorKind::AlreadyExists,
+                "the path exists and is not a socket",
+            ));
+        }
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                ErrorKind::AddrInUse,
+                "another instance is listening on it",
+            ));
+        }
+        // Left behind by an instance that didn't shut down cleanly
+        fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    listener.set_nonblocking(true)?;
+    Ok(listener)
+}
+
+fn serve(state: &JsonRpcState, context: JsonRpcContext) {
+    let context = Arc::new(Mutex::new(context));
+    let mut listener: Option<(UnixListener, PathBuf)> = None;
+    let mut failed_bind: Option<(PathBuf, Instant)> = None;
+    let mut clients: Vec<(Arc<Client>, JoinHandle<()>)> = Vec::new();
+
+    while !state.stop.load(Ordering::Relaxed) {
+        let enabled = state.enabled.load(Ordering::Relaxed);
+        let path = state.path.lock().clone();
+        if listener
+            .as_ref()
+            .is_some_and(|(_, bound)| !enabled || *bound != path)
+        {
+            close(state, listener.take(), &mut clients);
+        }
+
+        // The values are compared every time so subscribers only hear about changes
+        // made after they subscribed
+        let changes = context.lock().params.changes(false);
+        if !changes.is_empty() {
+            let context = context.lock();
+            let notifications: Vec<Value> = changes
+                .iter()
+                .map(|feedback| {
+                    json!({
+                        "jsonrpc": "2.0",
+                        "method": "params.changed",
+                        "params": context.param_json(feedback),
+                    })
+                })
+                .collect();
+            for (client, _) in &clients {
+                if client.subscribed.load(Ordering::Relaxed) {
+                    for notification in &notifications {
+                        if client.send(notification).is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        if enabled && listener.is_none() {
+            let retry_pending = failed_bind
+                .as_ref()
+                .is_some_and(|(failed_path, failed_at)| {
+                    *failed_path == path && failed_at.elapsed() < BIND_RETRY_INTERVAL
+                });
+            if !retry_pending {
+                match bind(&path) {
+                    Ok(new_listener) => {
+                        nih_log!("JSON-RPC server listening on {:?}", path);
+                        *state.bound_path.lock() = Some(path.clone());
+                        listener = Some((new_listener, path));
+                        failed_bind = None;
+                    }
+                    Err(e) => {
+                        nih_log!("Failed to open the JSON-RPC socket {:?}: {}", path, e);
+                        failed_bind = Some((path, Instant::now()));
+                    }
+                }
+            }
+        }
+
+        if let Some((listener, _)) = &listener {
+            loop {
+                match listener.accept() {
+                    Ok((stream, _)) => match spawn_client(stream, context.clone()) {
+                        Ok(client) => clients.push(client),
+                        Err(e) => nih_log!("Failed to accept a JSON-RPC client: {}", e),
+                    },
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                    Err(e) => {
+                        nih_log!("Failed to accept a JSON-RPC client: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+        clients.retain(|(_, thread)| !thread.is_finished());
+        state.clients.store(clients.len(), Ordering::Relaxed);
+
+        thread::sleep(POLL_INTERVAL);
+    }
+    close(state, listener, &mut clients);
+}
+
+fn spawn_client(
+    stream: UnixStream,
+    context: Arc<Mutex<JsonRpcContext>>,
+) -> io::Result<(Arc<Client>, JoinHandle<()>)> {
+    // Accepted sockets don't inherit the listener's non-blocking mode everywhere
+    stream.set_nonblocking(false)?;
+    stream.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let reader = stream.try_clone()?;
+    let client = Arc::new(Client {
+        writer: Mutex::new(stream),
+        subscribed: AtomicBool::new(false),
+    });
+    let thread_client = client.clone();
+    let thread = thread::Builder::new()
+        .name("json-rpc-client".to_string())
+        .spawn(move || serve_client(&thread_client, reader, &context))?;
+    Ok((client, thread))
+}
+
+fn serve_client(client: &Client, reader: UnixStream, context: &Mutex<JsonRpcContext>) {
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+    // Ends when the client hangs up or the server shuts the socket down
+    while matches!(reader.read_line(&mut line), Ok(len) if len > 0) {
+        let response = if line.trim().is_empty() {
+            None
+        } else {
+            handle_line(context, &line, &client.subscribed)
+        };
+        line.clear();
+        if response.is_some_and(|response| client.send(&response).is_err()) {
+            break;
+        }
+    }
+}
+
+fn close(
+    state: &JsonRpcState,
+    listener: Option<(UnixListener, PathBuf)>,
+    clients: &mut Vec<(Arc<Client>, JoinHandle<()>)>,
+) {
+    for (client, _) in clients.iter() {
+        let _ = client.writer.lock().shutdown(Shutdown::Both);
+    }
+    for (_, thread) in clients.drain(..) {
+        let _ = thread.join();
+    }
+    if let Some((listener, path)) = listener {
+        drop(listener);
+        let _ = fs::remove_file(path);
+    }
+    *state.bound_path.lock() = None;
+    state.clients.store(0, Ordering::Relaxed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crossbeam_channel::Receiver;
+    use nih_plug::prelude::{ParamPtr, Params};
+
+    struct NoParams;
+
+    unsafe impl Params for NoParams {
+        fn param_map(&self) -> Vec<(String, ParamPtr, String)> {
+            Vec::new()
+        }
+    }
+
+    fn test_context(name: &str) -> (JsonRpcContext, Receiver<RemoteCommand>) {
+        let (commands, receiver) = crossbeam_channel::bounded(16);
+        let presets_dir = std::env::temp_dir().join(format!(
+            "open-headstage-json-rpc-{}-{}",
+            name,
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&presets_dir);
+        let context = JsonRpcContext {
+            params: RemoteParams::new(Arc::new(NoParams)),
+            commands,
+            input_levels: Arc::new(MeterReadings::default()),
+            output_levels: Arc::new(MeterReadings::default()),
+            latency_samples: Arc::new(AtomicU32::new(480)),
+            sample_rate: Arc::new(AtomicU32::new(48000.0f32.to_bits())),
+            eq_band_count: Arc::new(AtomicUsize::new(10)),
+            presets_dir: Some(presets_dir),
+        };
+        (context, receiver)
+    }
+
+    #[test]
+    fn test_handles_requests() {
+        let (context, receiver) = test_context("requests");
+        let presets_dir = context.presets_dir.clone().unwrap();
+        let context = Mutex::new(context);
+        let subscribed = AtomicBool::new(false);
+        let call = |line: &str| handle_line(&context, line, &subscribed);
+
+        assert_eq!(
+            call(r#"{"jsonrpc": "2.0", "id": 1, "method": "latency.get"}"#),
+            Some(json!({ "jsonrpc": "2.0", "id": 1, "result": { "samples": 480, "ms": 10.0 } }))
+        );
+        let error_code = |response: Option<Value>| response.unwrap()["error"]["code"].clone();
+        assert_eq!(error_code(call("{oops")), json!(PARSE_ERROR));
+        assert_eq!(
+            error_code(call(r#"{"id": 2, "method": "latency.get"}"#)),
+            json!(INVALID_REQUEST)
+        );
+        assert_eq!(
+            error_code(call(r#"{"jsonrpc": "2.0", "id": 3, "method": "nope"}"#)),
+            json!(METHOD_NOT_FOUND)
+        );
+        assert_eq!(
+            error_code(call(
+                r#"{"jsonrpc": "2.0", "id": 4, "method": "params.set", "params": {"id": "nope", "value": 1}}"#
+            )),
+            json!(INVALID_PARAMS)
+        );
+
+        // Notifications get no response
+        assert_eq!(
+            call(
+                r#"{"jsonrpc": "2.0", "method": "files.load_sofa", "params": {"path": "/a.sofa"}}"#
+            ),
+            None
+        );
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(RemoteCommand::LoadSofa(PathBuf::from("/a.sofa")))
+        );
+        assert_eq!(
+            call(r#"{"jsonrpc": "2.0", "id": 5, "method": "params.subscribe"}"#),
+            Some(json!({ "jsonrpc": "2.0", "id": 5, "result": null }))
+        );
+        assert!(subscribed.load(Ordering::Relaxed));
+
+        let batch = call(
+            r#"[
+                {"jsonrpc": "2.0", "id": 6, "method": "presets.save", "params": {"name": "Test"}},
+                {"jsonrpc": "2.0", "id": 7, "method": "presets.list"},
+                {"jsonrpc": "2.0", "id": 8, "method": "presets.load", "params": {"name": "Test"}},
+                {"jsonrpc": "2.0", "id": 9, "method": "presets.load", "params": {"name": "../x"}}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            batch[1]["result"],
+            json!(["Stereo Triangle 30°", "Wide 45°", "Near-field", "Test"])
+        );
+        assert_eq!(batch[2]["result"], Value::Null);
+        assert_eq!(batch[3]["error"]["code"], json!(INVALID_PARAMS));
+        assert_eq!(
+            receiver.try_recv(),
+            Ok(RemoteCommand::LoadPreset(Preset {
+                params: Default::default(),
+                eq_band_count: Some(10),
+            }))
+        );
+        fs::remove_dir_all(presets_dir).unwrap();
+    }
+
+    #[test]
+    fn test_bind_keeps_files_that_are_not_sockets() {
+        let path = std::env::temp_dir().join(format!(
+            "open-headstage-json-rpc-file-{}",
+            std::process::id()
+        ));
+        fs::write(&path, "not a socket").unwrap();
+
+        assert!(bind(&path).is_err());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "not a socket");
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_server_over_unix_socket() {
+        let (context, _receiver) = test_context("server");
+        let path = context.presets_dir.clone().unwrap().with_extension("sock");
+        let state = Arc::new(JsonRpcState::default());
+        state.set_listening(true, path.clone());
+        let server = JsonRpcServer::spawn(state.clone(), context).unwrap();
+
+        let started = Instant::now();
+        while state.bound_path().is_none() {
+            assert!(started.elapsed() < Duration::from_secs(5), "Timed out");
+            thread::sleep(Duration::from_millis(5));
+        }
+        let mut stream = UnixStream::connect(&path).unwrap();
+        stream
+            .write_all(b"{\"jsonrpc\": \"2.0\", \"id\": \"a\", \"method\": \"meters.get\"}\n")
+            .unwrap();
+        let mut response = String::new();
+        BufReader::new(&stream).read_line(&mut response).unwrap();
+        let response: Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(response["id"], json!("a"));
+        assert_eq!(response["result"]["output"]["clipped"], json!(false));
+
+        drop(server);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,233 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/remote/midi.rs
+
+//! MIDI control changes bound to parameters. The audio thread decodes CCs and NRPNs
+//! into `MidiMessage`s, and the remote worker learns the bindings the editor asks for and
+//! applies them.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+const DATA_ENTRY_MSB: u8 = 6;
+const DATA_ENTRY_LSB: u8 = 38;
+const NRPN_LSB: u8 = 98;
+const NRPN_MSB: u8 = 99;
+const RPN_LSB: u8 = 100;
+const RPN_MSB: u8 = 101;
+// Selecting parameter number 127/127 deselects the current one
+const NULL_NUMBER: u8 = 127;
+
+/// A controller on a MIDI channel, numbered from 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MidiControl {
+    /// A 7-bit control change
+    Cc { channel: u8, cc: u8 },
+    /// A 14-bit non-registered parameter number
+    Nrpn { channel: u8, number: u16 },
+}
+
+impl fmt::Display for MidiControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MidiControl::Cc { channel, cc } => write!(f, "CC {} (ch. {})", cc, channel + 1),
+            MidiControl::Nrpn { channel, number } => {
+                write!(f, "NRPN {} (ch. {})", number, channel + 1)
+            }
+        }
+    }
+}
+
+/// A controller bound to the parameter with the given nih-plug ID.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MidiMapping {
+    pub control: MidiControl,
+    pub param_id: String,
+}
+
+/// A controller's new position, from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MidiMessage {
+    pub control: MidiControl,
+    pub value: f32,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct ChannelState {
+    // The halves of the selected parameter number, and whether it is an RPN
+    number_msb: Option<u8>,
+    number_lsb: Option<u8>,
+    registered: bool,
+    data_msb: u8,
+}
+
+impl ChannelState {
+    fn selected_nrpn(&self) -> Option<u16> {
+        match (self.number_msb, self.number_lsb) {
+            (Some(msb), Some(lsb)) if !self.registered => Some(((msb as u16) << 7) | lsb as u16),
+            _ => None,
+        }
+    }
+
+    fn select(&mut self, registered: bool, msb: Option<u8>, lsb: Option<u8>) {
+        if registered != self.registered {
+            self.number_msb = None;
+            self.number_lsb = None;
+            self.registered = registered;
+        }
+        if msb.is_some() {
+            self.number_msb = msb;
+        }
+        if lsb.is_some() {
+            self.number_lsb = lsb;
+        }
+        if self.number_msb == Some(NULL_NUMBER) && self.number_lsb == Some(NULL_NUMBER) {
+            self.number_msb = None;
+            self.number_lsb = None;
+        }
+    }
+}
+
+/// Turns a stream of control changes into `MidiMessage`s. NRPNs are assembled from
+/// their parameter number and data entry CCs. RPNs are not used and are swallowed.
+#[derive(Debug, Default)]
+pub struct MidiDecoder {
+    channels: [ChannelState; 16],
+}
+
+impl MidiDecoder {
+    /// Decodes a control change as nih-plug reports it, with the value from 0 to 1.
+    pub fn decode(&mut self, channel: u8, cc: u8, value: f32) -> Option<MidiMessage> {
+        let state = self.channels.get_mut(channel as usize)?;
+        let data = (value.clamp(0.0, 1.0) * 127.0).round() as u8;
+        let nrpn = |number: u16, value: u16| MidiMessage {
+            control: MidiControl::Nrpn { channel, number },
+            value: value as f32 / 16383.0,
+        };
+
+        match cc {
+            NRPN_MSB | NRPN_LSB | RPN_MSB | RPN_LSB => {
+                let registered = cc == RPN_MSB || cc == RPN_LSB;
+                if cc == NRPN_MSB || cc == RPN_MSB {
+                    state.select(registered, Some(data), None);
+                } else {
+                    state.select(registered, None, Some(data));
+                }
+                None
+            }
+            // Controllers that only send the coarse half still move the parameter, the
+            // fine half refines it when it follows
+            DATA_ENTRY_MSB if state.number_msb.is_some() || state.number_lsb.is_some() => {
+                state.data_msb = data;
+                state
+                    .selected_nrpn()
+                    .map(|number| nrpn(number, (data as u16) << 7))
+            }
+            DATA_ENTRY_LSB if state.number_msb.is_some() || state.number_lsb.is_some() => state
+                .selected_nrpn()
+                .map(|number| nrpn(number, ((state.data_msb as u16) << 7) | data as u16)),
+            _ => Some(MidiMessage {
+                control: MidiControl::Cc { channel, cc },
+                value: data as f32 / 127.0,
+            }),
+        }
+    }
+}
+
+/// Binds a controller to a parameter. Both lose the bindings they had before, so a
+/// controller moves one parameter and a parameter follows one controller.
+pub fn learn(mappings: &mut Vec<MidiMapping>, control: MidiControl, param_id: &str) {
+    mappings.retain(|mapping| mapping.control != control && mapping.param_id != param_id);
+    mappings.push(MidiMapping {
+        control,
+        param_id: param_id.to_string(),
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cc(decoder: &mut MidiDecoder, cc: u8, value: u8) -> Option<MidiMessage> {
+        decoder.decode(0, cc, value as f32 / 127.0)
+    }
+
+    #[test]
+    fn test_control_changes_pass_through() {
+        let mut decoder = MidiDecoder::default();
+        let message = decoder.decode(3, 7, 64.0 / 127.0).unwrap();
+        assert_eq!(message.control, MidiControl::Cc { channel: 3, cc: 7 });
+        assert_eq!(message.value, 64.0 / 127.0);
+        // Data entry without a selected parameter number is a plain CC
+        let message = cc(&mut decoder, DATA_ENTRY_MSB, 127).unwrap();
+        assert_eq!(
+            message.control,
+            MidiControl::Cc {
+                channel: 0,
+                cc: DATA_ENTRY_MSB
+            }
+        );
+    }
+
+    #[test]
+    fn test_nrpns_are_assembled_to_14_bits() {
+        let mut decoder = MidiDecoder::default();
+        assert_eq!(cc(&mut decoder, NRPN_MSB, 2), None);
+        assert_eq!(cc(&mut decoder, NRPN_LSB, 5), None);
+        let control = MidiControl::Nrpn {
+            channel: 0,
+            number: 2 * 128 + 5,
+        };
+
+        let coarse = cc(&mut decoder, DATA_ENTRY_MSB, 64).unwrap();
+        assert_eq!(coarse.control, control);
+        assert_eq!(coarse.value, 8192.0 / 16383.0);
+        let fine = cc(&mut decoder, DATA_ENTRY_LSB, 127).unwrap();
+        assert_eq!(fine.value, 8319.0 / 16383.0);
+        assert_eq!(
+            cc(&mut decoder, DATA_ENTRY_LSB, 0).unwrap().value,
+            coarse.value
+        );
+
+        // RPNs and the null parameter number are swallowed
+        cc(&mut decoder, RPN_MSB, 0);
+        cc(&mut decoder, RPN_LSB, 0);
+        assert_eq!(cc(&mut decoder, DATA_ENTRY_MSB, 2), None);
+        cc(&mut decoder, NRPN_MSB, NULL_NUMBER);
+        cc(&mut decoder, NRPN_LSB, NULL_NUMBER);
+        assert!(matches!(
+            cc(&mut decoder, DATA_ENTRY_MSB, 2).unwrap().control,
+            MidiControl::Cc { .. }
+        ));
+    }
+
+    #[test]
+    fn test_learning_replaces_old_bindings() {
+        let mut mappings = Vec::new();
+        let fader = MidiControl::Cc { channel: 0, cc: 7 };
+        let knob = MidiControl::Cc { channel: 0, cc: 10 };
+        learn(&mut mappings, fader, "output_gain");
+        learn(&mut mappings, knob, "bypass");
+        learn(&mut mappings, fader, "bypass");
+
+        assert_eq!(
+            mappings,
+            vec![MidiMapping {
+                control: fader,
+                param_id: "bypass".to_string(),
+            }]
+        );
+    }
+}
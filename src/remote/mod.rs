@@ -0,0 +1,61 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/remote/mod.rs
+
+//! Remote control from outside the editor. Parameters can only be changed through a
+//! `GuiContext`, which tells the host about the change, so remote controls queue
+//! `RemoteCommand`s that the worker applies with the context the editor was opened with.
+//! The standalone app opens its editor at launch. In plugin hosts, remote control starts
+//! once the editor has been opened. Headless mode doesn't start the servers.
+
+use std::path::PathBuf;
+
+use crate::presets::Preset;
+
+#[cfg(unix)]
+pub mod json_rpc;
+pub mod midi;
+pub mod osc;
+pub mod params;
+pub mod worker;
+
+/// How many commands can wait for the worker. Commands that arrive while the queue is
+/// full are dropped.
+pub const REMOTE_QUEUE_SIZE: usize = 1024;
+
+/// A value for a parameter, in one of the forms remote controls send.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemoteValue {
+    /// In the parameter's unit, with booleans as 0 and 1 and enums as their index
+    Plain(f32),
+    Normalized(f32),
+    /// Parsed like text typed into the parameter's field, e.g. "-6 dB"
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RemoteCommand {
+    /// Sets the parameter with the given nih-plug ID
+    SetParameter {
+        id: String,
+        value: RemoteValue,
+    },
+    LoadSofa(PathBuf),
+    /// Loads an AutoEQ profile and applies it
+    LoadAutoEq(PathBuf),
+    LoadPreset(Preset),
+    /// Asks for the values of all parameters
+    Sync,
+}
@@ -0,0 +1,679 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/remote/osc.rs
+
+//! An OSC 1.0 server over UDP. It understands these addresses:
+//!
+//! - `/param/<id> value`: sets a parameter to a plain value, given as a float, an int,
+//!   a boolean or text like "-6 dB"
+//! - `/param/<id>/normalized value`: sets a parameter from 0 to 1, for faders
+//! - `/load/sofa path` and `/load/autoeq path`: load a SOFA file or apply an AutoEQ
+//!   profile
+//! - `/head/ypr yaw pitch roll`: the head pose in degrees, for head trackers that send OSC
+//! - `/sync`: asks for the values of all parameters
+//!
+//! The server only listens on the loopback interface unless clients from the network
+//! are allowed, since anyone who can reach it can change the settings and load files.
+//!
+//! Parameter IDs are the plugin's, with EQ band parameters numbered from 1 (`gain_3`).
+//! Every address that has sent a message gets feedback when a parameter changes, as
+//! `/param/<id>` with the plain value, `/param/<id>/normalized` and `/param/<id>/text`.
+
+use crossbeam_channel::{Sender, TrySendError};
+use nih_plug::nih_log;
+use parking_lot::Mutex;
+use std::io::ErrorKind;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU16, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use super::params::ParamFeedback;
+use super::{RemoteCommand, RemoteValue};
+use crate::head_tracking::{HeadPose, HeadTrackingState};
+
+pub const DEFAULT_OSC_PORT: u16 = 9000;
+pub const DEFAULT_OSC_FEEDBACK_PORT: u16 = 9001;
+
+// The clients that most recently sent a message get feedback
+const MAX_CLIENTS: usize = 8;
+// How long the server waits for a packet before looking at its settings again
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+// How long to wait before retrying a port that couldn't be bound
+const BIND_RETRY_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Debug, PartialEq)]
+pub enum OscError {
+    Truncated,
+    UnterminatedString,
+    UnsupportedType(char),
+    InvalidAddress,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OscArg {
+    Int(i32),
+    Float(f32),
+    Long(i64),
+    Double(f64),
+    String(String),
+    Blob(Vec<u8>),
+    Bool(bool),
+    Nil,
+}
+
+impl OscArg {
+    fn as_f32(&self) -> Option<f32> {
+        match self {
+            OscArg::Int(value) => Some(*value as f32),
+            OscArg::Float(value) => Some(*value),
+            OscArg::Long(value) => Some(*value as f32),
+            OscArg::Double(value) => Some(*value as f32),
+            OscArg::Bool(value) => Some(if *value { 1.0 } else { 0.0 }),
+            OscArg::String(_) | OscArg::Blob(_) | OscArg::Nil => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OscMessage {
+    pub address: String,
+    pub args: Vec<OscArg>,
+}
+
+impl OscMessage {
+    pub fn new(address: impl Into<String>, args: Vec<OscArg>) -> Self {
+        Self {
+            address: address.into(),
+            args,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut packet = Vec::new();
+        write_string(&mut packet, &self.address);
+        let type_tags: String = std::iter::once(',')
+            .chain(self.args.iter().map(|arg| match arg {
+                OscArg::Int(_) => 'i',
+                OscArg::Float(_) => 'f',
+                OscArg::Long(_) => 'h',
+                OscArg::Double(_) => 'd',
+                OscArg::String(_) => 's',
+                OscArg::Blob(_) => 'b',
+                OscArg::Bool(true) => 'T',
+                OscArg::Bool(false) => 'F',
+                OscArg::Nil => 'N',
+            }))
+            .collect();
+        write_string(&mut packet, &type_tags);
+        for arg in &self.args {
+            match arg {
+                OscArg::Int(value) => packet.extend(value.to_be_bytes()),
+                OscArg::Float(value) => packet.extend(value.to_be_bytes()),
+                OscArg::Long(value) => packet.extend(value.to_be_bytes()),
+                OscArg::Double(value) => packet.extend(value.to_be_bytes()),
+                OscArg::String(value) => write_string(&mut packet, value),
+                OscArg::Blob(data) => {
+                    packet.extend((data.len() as i32).to_be_bytes());
+                    packet.extend(data);
+                    packet.resize(packet.len().next_multiple_of(4), 0);
+                }
+                OscArg::Bool(_) | OscArg::Nil => {}
+            }
+        }
+        packet
+    }
+}
+
+/// Writes a string with its terminator and padding to a multiple of four bytes.
+fn write_string(packet: &mut Vec<u8>, value: &str) {
+    packet.extend(value.as_bytes());
+    packet.push(0);
+    packet.resize(packet.len().next_multiple_of(4), 0);
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], OscError> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or(OscError::Truncated)?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn take_array<const N: usize>(&mut self) -> Result<[u8; N], OscError> {
+        Ok(self.take(N)?.try_into().unwrap())
+    }
+
+    fn string(&mut self) -> Result<String, OscError> {
+        let rest = &self.data[self.pos.min(self.data.len())..];
+        let len = rest
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or(OscError::UnterminatedString)?;
+        let value = String::from_utf8_lossy(&rest[..len]).into_owned();
+        self.take((len + 1).next_multiple_of(4))?;
+        Ok(value)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.pos >= self.data.len()
+    }
+}
+
+/// Decodes a packet, flattening bundles into their messages. Time tags are ignored and
+/// everything is applied right away.
+pub fn decode_packet(packet: &[u8]) -> Result<Vec<OscMessage>, OscError> {
+    let mut messages = Vec::new();
+    decode_into(packet, &mut messages)?;
+    Ok(messages)
+}
+
+fn decode_into(packet: &[u8], messages: &mut Vec<OscMessage>) -> Result<(), OscError> {
+    let mut reader = Reader {
+        data: packet,
+        pos: 0,
+    };
+    let address = reader.string()?;
+    if address == "#bundle" {
+        // The time tag
+        reader.take(8)?;
+        while !reader.is_empty() {
+            let len = i32::from_be_bytes(reader.take_array()?);
+            let element = reader.take(usize::try_from(len).map_err(|_| OscError::Truncated)?)?;
+            decode_into(element, messages)?;
+        }
+        return Ok(());
+    }
+    if !address.starts_with('/') {
+        return Err(OscError::InvalidAddress);
+    }
+
+    // Some old senders leave out the type tags of messages without arguments
+    let type_tags = if reader.is_empty() {
+        String::from(",")
+    } else {
+        reader.string()?
+    };
+    let mut args = Vec::new();
+    for tag in type_tags.chars().skip(1) {
+        args.push(match tag {
+            'i' => OscArg::Int(i32::from_be_bytes(reader.take_array()?)),
+            'f' => OscArg::Float(f32::from_be_bytes(reader.take_array()?)),
+            'h' => OscArg::Long(i64::from_be_bytes(reader.take_array()?)),
+            'd' => OscArg::Double(f64::from_be_bytes(reader.take_array()?)),
+            's' | 'S' => OscArg::String(reader.string()?),
+            'b' => {
+                let len = i32::from_be_bytes(reader.take_array()?);
+                let len = usize::try_from(len).map_err(|_| OscError::Truncated)?;
+                let data = reader.take(len.next_multiple_of(4))?[..len].to_vec();
+                OscArg::Blob(data)
+            }
+            'T' => OscArg::Bool(true),
+            'F' => OscArg::Bool(false),
+            'N' | 'I' => OscArg::Nil,
+            other => return Err(OscError::UnsupportedType(other)),
+        });
+    }
+    messages.push(OscMessage { address, args });
+    Ok(())
+}
+
+/// Turns a message into the command it asks for, if it is one.
+pub fn parse_command(message: &OscMessage) -> Option<RemoteCommand> {
+    let first_arg = message.args.first();
+    match message.address.as_str() {
+        "/sync" => return Some(RemoteCommand::Sync),
+        "/load/sofa" | "/load/autoeq" => {
+            let Some(OscArg::String(path)) = first_arg else {
+                return None;
+            };
+            let path = PathBuf::from(path);
+            return Some(if message.address == "/load/sofa" {
+                RemoteCommand::LoadSofa(path)
+            } else {
+                RemoteCommand::LoadAutoEq(path)
+            });
+        }
+        _ => {}
+    }
+
+    let param = message.address.strip_prefix("/param/")?;
+    let (id, value) = match param.strip_suffix("/normalized") {
+        Some(id) => (id, RemoteValue::Normalized(first_arg?.as_f32()?)),
+        None => match first_arg? {
+            OscArg::String(text) => (param, RemoteValue::Text(text.clone())),
+            arg => (param, RemoteValue::Plain(arg.as_f32()?)),
+        },
+    };
+    if id.is_empty() || id.contains('/') {
+        return None;
+    }
+    Some(RemoteCommand::SetParameter {
+        id: id.to_string(),
+        value,
+    })
+}
+
+/// Reads a `/head/ypr` message.
+pub fn parse_head_pose(message: &OscMessage) -> Option<HeadPose> {
+    if message.address != "/head/ypr" {
+        return None;
+    }
+    let [yaw, pitch, roll] = message.args.get(..3)? else {
+        return None;
+    };
+    Some(HeadPose {
+        yaw: yaw.as_f32()?,
+        pitch: pitch.as_f32()?,
+        roll: roll.as_f32()?,
+    })
+}
+
+/// The messages that report a parameter's value.
+pub fn feedback_messages(feedback: &ParamFeedback) -> [OscMessage; 3] {
+    let address = format!("/param/{}", feedback.id);
+    [
+        OscMessage::new(address.clone(), vec![OscArg::Float(feedback.plain)]),
+        OscMessage::new(
+            format!("{}/normalized", address),
+            vec![OscArg::Float(feedback.normalized)],
+        ),
+        OscMessage::new(
+            format!("{}/text", address),
+            vec![OscArg::String(feedback.text.clone())],
+        ),
+    ]
+}
+
+/// The server's settings, its socket and the clients that get feedback, shared between
+/// the server thread, the audio thread, the remote worker and the editor.
+pub struct OscServerState {
+    enabled: AtomicBool,
+    port: AtomicU16,
+    // 0 sends feedback back to the port each client sent from
+    feedback_port: AtomicU16,
+    // Whether to listen on all interfaces rather than the loopback one
+    network: AtomicBool,
+    // The port the server is bound to, or 0
+    bound_port: AtomicU16,
+    socket: Mutex<Option<Arc<UdpSocket>>>,
+    clients: Mutex<Vec<SocketAddr>>,
+    stop: AtomicBool,
+}
+
+impl Default for OscServerState {
+    fn default() -> Self {
+        Self {
+            enabled: AtomicBool::new(false),
+            port: AtomicU16::new(DEFAULT_OSC_PORT),
+            feedback_port: AtomicU16::new(DEFAULT_OSC_FEEDBACK_PORT),
+            network: AtomicBool::new(false),
+            bound_port: AtomicU16::new(0),
+            socket: Mutex::new(None),
+            clients: Mutex::new(Vec::new()),
+            stop: AtomicBool::new(false),
+        }
+    }
+}
+
+impl OscServerState {
+    /// Starts or stops the server, or moves it to other ports. The server thread picks
+    /// the change up within `POLL_INTERVAL`. Port 0 binds to any free port.
+    pub fn set_listening(&self, enabled: bool, port: u16, feedback_port: u16, network: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+        self.port.store(port, Ordering::Relaxed);
+        self.feedback_port.store(feedback_port, Ordering::Relaxed);
+        self.network.store(network, Ordering::Relaxed);
+    }
+
+    pub fn bound_port(&self) -> Option<u16> {
+        match self.bound_port.load(Ordering::Relaxed) {
+            0 => None,
+            port => Some(port),
+        }
+    }
+
+    pub fn client_count(&self) -> usize {
+        self.clients.lock().len()
+    }
+
+    /// Sends the messages to every client. Does nothing while the server isn't running.
+    pub fn send(&self, messages: &[OscMessage]) {
+        let Some(socket) = self.socket.lock().clone() else {
+            return;
+        };
+        let feedback_port = self.feedback_port.load(Ordering::Relaxed);
+        let clients = self.clients.lock().clone();
+        for message in messages {
+            let packet = message.encode();
+            for client in &clients {
+                let mut target = *client;
+                if feedback_port != 0 {
+                    target.set_port(feedback_port);
+                }
+                // Feedback is best-effort, a client that went away shouldn't stop the rest
+                let _ = socket.send_to(&packet, target);
+            }
+        }
+    }
+
+    fn add_client(&self, client: SocketAddr) {
+        let mut clients = self.clients.lock();
+        clients.retain(|known| *known != client);
+        clients.insert(0, client);
+        clients.truncate(MAX_CLIENTS);
+    }
+
+    fn close(&self) {
+        *self.socket.lock() = None;
+        self.clients.lock().clear();
+        self.bound_port.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Runs the thread that receives OSC messages. Head poses go straight to the head
+/// tracking state, everything else is queued for the remote worker. Dropping the server stops
+/// the thread.
+pub struct OscServer {
+    state: Arc<OscServerState>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl OscServer {
+    pub fn spawn(
+        state: Arc<OscServerState>,
+        commands: Sender<RemoteCommand>,
+        head_tracking: Arc<HeadTrackingState>,
+    ) -> std::io::Result<Self> {
+        state.stop.store(false, Ordering::Relaxed);
+        let thread_state = state.clone();
+        let thread = thread::Builder::new()
+            .name("osc-server".to_string())
+            .spawn(move || serve(&thread_state, &commands, &head_tracking))?;
+        Ok(Self {
+            state,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for OscServer {
+    fn drop(&mut self) {
+        self.state.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(
+    state: &OscServerState,
+    commands: &Sender<RemoteCommand>,
+    head_tracking: &HeadTrackingState,
+) {
+    // The socket, and the port and interfaces that were asked for when it was bound
+    let mut socket: Option<(Arc<UdpSocket>, (u16, bool))> = None;
+    let mut failed_bind: Option<((u16, bool), Instant)> = None;
+    let mut packet = [0u8; 65536];
+
+    while !state.stop.load(Ordering::Relaxed) {
+        let enabled = state.enabled.load(Ordering::Relaxed);
+        let port = state.port.load(Ordering::Relaxed);
+        let network = state.network.load(Ordering::Relaxed);
+        if socket.is_some()
+            && (!enabled
+                || socket
+                    .as_ref()
+                    .is_some_and(|(_, bound)| *bound != (port, network)))
+        {
+            socket = None;
+            state.close();
+        }
+        if !enabled {
+            thread::sleep(POLL_INTERVAL);
+            continue;
+        }
+
+        if socket.is_none() {
+            let retry_pending = failed_bind.is_some_and(|(failed, failed_at)| {
+                failed == (port, network) && failed_at.elapsed() < BIND_RETRY_INTERVAL
+            });
+            if retry_pending {
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+            // Control surfaces on a phone or tablet need all interfaces
+            let address = if network {
+                Ipv4Addr::UNSPECIFIED
+            } else {
+                Ipv4Addr::LOCALHOST
+            };
+            let bound = UdpSocket::bind((address, port)).and_then(|new_socket| {
+                new_socket.set_read_timeout(Some(POLL_INTERVAL))?;
+                let local_port = new_socket.local_addr()?.port();
+                Ok((Arc::new(new_socket), local_port))
+            });
+            match bound {
+                Ok((new_socket, local_port)) => {
+                    nih_log!("OSC server listening on {}:{}", address, local_port);
+                    *state.socket.lock() = Some(new_socket.clone());
+                    state.bound_port.store(local_port, Ordering::Relaxed);
+                    socket = Some((new_socket, (port, network)));
+                    failed_bind = None;
+                }
+                Err(e) => {
+                    nih_log!("Failed to start the OSC server on UDP port {}: {}", port, e);
+                    failed_bind = Some(((port, network), Instant::now()));
+                    continue;
+                }
+            }
+        }
+
+        let Some((socket, _)) = &socket else {
+            continue;
+        };
+        let (len, client) = match socket.recv_from(&mut packet) {
+            Ok(received) => received,
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                continue;
+            }
+            Err(e) => {
+                nih_log!("Failed to receive OSC packet: {}", e);
+                thread::sleep(POLL_INTERVAL);
+                continue;
+            }
+        };
+        // Malformed packets and unknown addresses are ignored, as OSC servers do
+        let Ok(messages) = decode_packet(&packet[..len]) else {
+            continue;
+        };
+        state.add_client(client);
+        for message in &messages {
+            if let Some(pose) = parse_head_pose(message) {
+                head_tracking.set_pose(pose);
+            } else if let Some(command) = parse_command(message) {
+                match commands.try_send(command) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        nih_log!("Dropped an OSC command, the remote worker isn't keeping up")
+                    }
+                    Err(TrySendError::Disconnected(_)) => return,
+                }
+            }
+        }
+    }
+    state.close();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wait_for<T>(mut poll: impl FnMut() -> Option<T>) -> T {
+        let started = Instant::now();
+        loop {
+            if let Some(value) = poll() {
+                return value;
+            }
+            assert!(started.elapsed() < Duration::from_secs(5), "Timed out");
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_messages_round_trip() {
+        let message = OscMessage::new(
+            "/param/gain_3",
+            vec![
+                OscArg::Float(-6.5),
+                OscArg::Int(3),
+                OscArg::String("abc".to_string()),
+                OscArg::Bool(true),
+                OscArg::Blob(vec![1, 2, 3, 4, 5]),
+                OscArg::Double(0.25),
+            ],
+        );
+        let packet = message.encode();
+        assert_eq!(packet.len() % 4, 0);
+        assert_eq!(decode_packet(&packet), Ok(vec![message.clone()]));
+
+        // A bundle with the message twice
+        let mut bundle = Vec::new();
+        write_string(&mut bundle, "#bundle");
+        bundle.extend(1u64.to_be_bytes());
+        for _ in 0..2 {
+            bundle.extend((packet.len() as i32).to_be_bytes());
+            bundle.extend(&packet);
+        }
+        assert_eq!(decode_packet(&bundle), Ok(vec![message.clone(), message]));
+
+        assert_eq!(
+            decode_packet(&packet[..packet.len() - 2]),
+            Err(OscError::Truncated)
+        );
+    }
+
+    #[test]
+    fn test_parses_commands() {
+        let command =
+            |address: &str, args: Vec<OscArg>| parse_command(&OscMessage::new(address, args));
+        assert_eq!(
+            command("/param/out_gain", vec![OscArg::Float(0.5)]),
+            Some(RemoteCommand::SetParameter {
+                id: "out_gain".to_string(),
+                value: RemoteValue::Plain(0.5),
+            })
+        );
+        assert_eq!(
+            command("/param/bypass", vec![OscArg::Bool(true)]),
+            Some(RemoteCommand::SetParameter {
+                id: "bypass".to_string(),
+                value: RemoteValue::Plain(1.0),
+            })
+        );
+        assert_eq!(
+            command("/param/gain_3/normalized", vec![OscArg::Int(1)]),
+            Some(RemoteCommand::SetParameter {
+                id: "gain_3".to_string(),
+                value: RemoteValue::Normalized(1.0),
+            })
+        );
+        assert_eq!(
+            command("/param/out_gain", vec![OscArg::String("-6 dB".to_string())]),
+            Some(RemoteCommand::SetParameter {
+                id: "out_gain".to_string(),
+                value: RemoteValue::Text("-6 dB".to_string()),
+            })
+        );
+        assert_eq!(
+            command(
+                "/load/sofa",
+                vec![OscArg::String("/tmp/a.sofa".to_string())]
+            ),
+            Some(RemoteCommand::LoadSofa(PathBuf::from("/tmp/a.sofa")))
+        );
+        assert_eq!(command("/sync", vec![]), Some(RemoteCommand::Sync));
+        assert_eq!(command("/param/out_gain", vec![]), None);
+        assert_eq!(
+            command("/param/gain_3/text", vec![OscArg::Float(1.0)]),
+            None
+        );
+        assert_eq!(command("/unknown", vec![OscArg::Float(1.0)]), None);
+    }
+
+    #[test]
+    fn test_server_over_localhost() {
+        let (sender, receiver) = crossbeam_channel::bounded(16);
+        let state = Arc::new(OscServerState::default());
+        let head_tracking = Arc::new(HeadTrackingState::default());
+        state.set_listening(true, 0, 0, false);
+        let _server = OscServer::spawn(state.clone(), sender, head_tracking.clone()).unwrap();
+        let port = wait_for(|| state.bound_port());
+        // Only this machine can connect unless the network is allowed
+        let local_addr = state.socket.lock().as_ref().unwrap().local_addr().unwrap();
+        assert!(local_addr.ip().is_loopback());
+
+        let client = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let send = |message: OscMessage| {
+            client
+                .send_to(&message.encode(), (Ipv4Addr::LOCALHOST, port))
+                .unwrap();
+        };
+
+        send(OscMessage::new("/param/az_l", vec![OscArg::Float(-45.0)]));
+        assert_eq!(
+            receiver.recv_timeout(Duration::from_secs(5)),
+            Ok(RemoteCommand::SetParameter {
+                id: "az_l".to_string(),
+                value: RemoteValue::Plain(-45.0),
+            })
+        );
+
+        send(OscMessage::new(
+            "/head/ypr",
+            vec![OscArg::Float(20.0), OscArg::Float(-5.0), OscArg::Float(0.0)],
+        ));
+        wait_for(|| (head_tracking.pose().yaw == 20.0).then_some(()));
+        assert_eq!(head_tracking.pose().pitch, -5.0);
+
+        // The client sent from its own port and feedback goes back there
+        assert_eq!(state.client_count(), 1);
+        let feedback = ParamFeedback {
+            id: "az_l".to_string(),
+            plain: -45.0,
+            normalized: 0.25,
+            text: "-45.00°".to_string(),
+        };
+        let messages = feedback_messages(&feedback);
+        state.send(&messages);
+        for expected in messages {
+            let mut packet = [0u8; 1024];
+            let len = client.recv(&mut packet).unwrap();
+            assert_eq!(decode_packet(&packet[..len]), Ok(vec![expected]));
+        }
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/remote/params.rs
+
+use nih_plug::prelude::{ParamPtr, ParamSetter, Params};
+use std::sync::Arc;
+
+use super::RemoteValue;
+
+/// The value of a parameter as reported back to remote controls.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamFeedback {
+    pub id: String,
+    pub plain: f32,
+    pub normalized: f32,
+    pub text: String,
+}
+
+/// Finds parameters by their ID for remote controls and remembers which values were
+/// last reported to them.
+pub struct RemoteParams {
+    // Keeps the parameters the pointers point into alive
+    _params: Arc<dyn Params>,
+    entries: Vec<(String, ParamPtr)>,
+    // The names shown for the parameters, with their group
+    labels: Vec<String>,
+    reported: Vec<Option<f32>>,
+}
+
+impl RemoteParams {
+    pub fn new(params: Arc<dyn Params>) -> Self {
+        let param_map = params.param_map();
+        let labels = param_map
+            .iter()
+            .map(|(_, param, group)| {
+                // SAFETY: The parameters are alive while `params` is
+                let name = unsafe { param.name() };
+                if group.is_empty() {
+                    name.to_string()
+                } else {
+                    format!("{}: {}", group, name)
+                }
+            })
+            .collect();
+        let entries: Vec<(String, ParamPtr)> = param_map
+            .into_iter()
+            .map(|(id, param, _group)| (id, param))
+            .collect();
+        Self {
+            _params: params,
+            reported: vec![None; entries.len()],
+            entries,
+            labels,
+        }
+    }
+
+    /// The normalized value a `RemoteValue` stands for, or `None` for unknown IDs and
+    /// text that doesn't parse.
+    pub fn resolve(&self, id: &str, value: &RemoteValue) -> Option<f32> {
+        let param = self.find(id)?;
+        // SAFETY: The pointers point into `self._params`, which outlives them
+        unsafe {
+            match value {
+                RemoteValue::Plain(plain) => Some(param.preview_normalized(*plain)),
+                RemoteValue::Normalized(normalized) => Some(normalized.clamp(0.0, 1.0)),
+                RemoteValue::Text(text) => param.string_to_normalized_value(text),
+            }
+        }
+    }
+
+    /// Sets a parameter as if it had been changed in the editor. Returns `false` for
+    /// unknown IDs and text that doesn't parse.
+    pub fn set(&self, id: &str, value: &RemoteValue, setter: &ParamSetter) -> bool {
+        let (Some(param), Some(normalized)) = (self.find(id), self.resolve(id, value)) else {
+            return false;
+        };
+        // SAFETY: See `resolve()`
+        unsafe {
+            setter.raw_context.raw_begin_set_parameter(param);
+            setter
+                .raw_context
+                .raw_set_parameter_normalized(param, normalized);
+            setter.raw_context.raw_end_set_parameter(param);
+        }
+        true
+    }
+
+    pub fn get(&self, id: &str) -> Option<ParamFeedback> {
+        let (id, param) = self.entries.iter().find(|(entry_id, _)| entry_id == id)?;
+        Some(feedback(id, *param))
+    }
+
+    /// The values of all parameters.
+    pub fn values(&self) -> Vec<ParamFeedback> {
+        self.entries
+            .iter()
+            .map(|(id, param)| feedback(id, *param))
+            .collect()
+    }
+
+    /// The parameters whose values changed since the last call, or all of them.
+    pub fn changes(&mut self, all: bool) -> Vec<ParamFeedback> {
+        let mut changes = Vec::new();
+        for ((id, param), reported) in self.entries.iter().zip(self.reported.iter_mut()) {
+            // SAFETY: See `resolve()`
+            let normalized = unsafe { param.unmodulated_normalized_value() };
+            if !all && *reported == Some(normalized) {
+                continue;
+            }
+            *reported = Some(normalized);
+            changes.push(feedback(id, *param));
+        }
+        changes
+    }
+
+    /// The IDs of all parameters with the names to show for them.
+    pub fn labels(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries
+            .iter()
+            .zip(&self.labels)
+            .map(|((id, _), label)| (id.as_str(), label.as_str()))
+    }
+
+    pub fn label(&self, id: &str) -> Option<&str> {
+        self.labels()
+            .find(|(entry_id, _)| *entry_id == id)
+            .map(|(_, label)| label)
+    }
+
+    fn find(&self, id: &str) -> Option<ParamPtr> {
+        self.entries
+            .iter()
+            .find(|(entry_id, _)| entry_id == id)
+            .map(|(_, param)| *param)
+    }
+}
+
+fn feedback(id: &str, param: ParamPtr) -> ParamFeedback {
+    // SAFETY: Only called with pointers from a `RemoteParams`, see `resolve()`
+    unsafe {
+        let normalized = param.unmodulated_normalized_value();
+        ParamFeedback {
+            id: id.to_string(),
+            plain: param.unmodulated_plain_value(),
+            normalized,
+            text: param.normalized_value_to_string(normalized, true),
+        }
+    }
+}
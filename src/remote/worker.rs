@@ -0,0 +1,222 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/remote/worker.rs
+
+//! Applies the remote controls' commands and the bound MIDI controls on a thread of its
+//! own, so they keep working while the editor is closed. nih-plug only hands a
+//! `GuiContext` to the editor, so the worker holds on to the one the editor was last
+//! opened with.
+
+use crossbeam_channel::Receiver;
+use nih_plug::nih_log;
+use nih_plug::prelude::{Editor, GuiContext, ParamSetter, ParentWindowHandle};
+use parking_lot::Mutex;
+use std::any::Any;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Weak};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::midi::{self, MidiMessage};
+use super::osc::{OscServerState, feedback_messages};
+use super::params::RemoteParams;
+use super::{RemoteCommand, RemoteValue};
+use crate::autoeq_parser::AutoEqProfile;
+use crate::{OpenHeadstageParams, Task};
+
+// How often queued commands are applied and changes are sent back
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// The `GuiContext` the editor was last opened with. It keeps nih-plug's wrapper alive,
+/// so the plugin only holds a weak reference while it is deactivated, which comes back
+/// if the editor still has the context when the plugin is activated again.
+#[derive(Default)]
+pub struct ContextSlot {
+    context: Mutex<Option<Arc<dyn GuiContext>>>,
+    released: Mutex<Option<Weak<dyn GuiContext>>>,
+}
+
+impl ContextSlot {
+    fn set(&self, context: Arc<dyn GuiContext>) {
+        *self.context.lock() = Some(context);
+    }
+
+    /// Lets go of the context, for `deactivate`.
+    pub fn release(&self) {
+        if let Some(context) = self.context.lock().take() {
+            *self.released.lock() = Some(Arc::downgrade(&context));
+        }
+    }
+
+    /// Takes the released context back if the editor still has it, for `initialize`.
+    pub fn restore(&self) {
+        if let Some(context) = self.released.lock().take().and_then(|weak| weak.upgrade()) {
+            self.context.lock().get_or_insert(context);
+        }
+    }
+
+    fn get(&self) -> Option<Arc<dyn GuiContext>> {
+        self.context.lock().clone()
+    }
+}
+
+/// Passes the editor's `GuiContext` to the worker whenever the editor is opened.
+pub struct ContextCapture {
+    pub editor: Box<dyn Editor>,
+    pub context: Arc<ContextSlot>,
+}
+
+impl Editor for ContextCapture {
+    fn spawn(
+        &self,
+        parent: ParentWindowHandle,
+        context: Arc<dyn GuiContext>,
+    ) -> Box<dyn Any + Send> {
+        self.context.set(context.clone());
+        self.editor.spawn(parent, context)
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.editor.size()
+    }
+
+    fn set_scale_factor(&self, factor: f32) -> bool {
+        self.editor.set_scale_factor(factor)
+    }
+
+    fn param_value_changed(&self, id: &str, normalized_value: f32) {
+        self.editor.param_value_changed(id, normalized_value)
+    }
+
+    fn param_modulation_changed(&self, id: &str, modulation_offset: f32) {
+        self.editor.param_modulation_changed(id, modulation_offset)
+    }
+
+    fn param_values_changed(&self) {
+        self.editor.param_values_changed()
+    }
+}
+
+/// What the worker applies its commands to and takes them from.
+pub struct RemoteWorkerContext {
+    pub params: Arc<OpenHeadstageParams>,
+    pub gui_context: Arc<ContextSlot>,
+    pub commands: Receiver<RemoteCommand>,
+    pub midi: Receiver<MidiMessage>,
+    /// The parameter the next MIDI control binds to, while the editor is learning one
+    pub midi_learn: Arc<Mutex<Option<String>>>,
+    pub osc_state: Arc<OscServerState>,
+    /// Runs the file loads on the worker's thread
+    pub executor: Box<dyn Fn(Task) + Send>,
+    /// AutoEQ profiles the worker applied, for the editor to show
+    pub loaded_auto_eq: Arc<Mutex<Option<AutoEqProfile>>>,
+}
+
+/// Runs the thread that applies the remote controls' commands. Dropping the worker stops
+/// the thread.
+pub struct RemoteWorker {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RemoteWorker {
+    pub fn spawn(context: RemoteWorkerContext) -> std::io::Result<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = thread::Builder::new()
+            .name("remote-worker".to_string())
+            .spawn(move || run(&context, &thread_stop))?;
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for RemoteWorker {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn run(context: &RemoteWorkerContext, stop: &AtomicBool) {
+    let mut remote_params = RemoteParams::new(context.params.clone());
+    let auto_eq_result = Arc::new(Mutex::new(None));
+
+    while !stop.load(Ordering::Relaxed) {
+        thread::sleep(POLL_INTERVAL);
+        let Some(gui_context) = context.gui_context.get() else {
+            // Commands wait for the editor to be opened. Controls that moved in the
+            // meantime are stale and would jump the parameters around.
+            context.midi.try_iter().for_each(drop);
+            continue;
+        };
+        let setter = ParamSetter::new(&*gui_context);
+        let params = &context.params;
+
+        // Apply what remote controls sent since the last time and report the parameters
+        // that changed back to them
+        let mut sync = false;
+        for command in context.commands.try_iter() {
+            match command {
+                RemoteCommand::SetParameter { id, value } => {
+                    if !remote_params.set(&id, &value, &setter) {
+                        nih_log!("Remote control: cannot set '{}' to {:?}", id, value);
+                    }
+                }
+                RemoteCommand::LoadSofa(path) => {
+                    *params.sofa_file_path.write() = path.to_string_lossy().to_string();
+                    (context.executor)(Task::LoadSofa(path));
+                }
+                RemoteCommand::LoadAutoEq(path) => {
+                    (context.executor)(Task::LoadAutoEq(path, auto_eq_result.clone()));
+                    if let Some(profile) = auto_eq_result.lock().take() {
+                        crate::apply_auto_eq_profile(&profile, params, &setter);
+                        *context.loaded_auto_eq.lock() = Some(profile);
+                    }
+                }
+                RemoteCommand::LoadPreset(preset) => {
+                    crate::apply_preset(&preset, params, &remote_params, &setter);
+                }
+                RemoteCommand::Sync => sync = true,
+            }
+        }
+        for message in context.midi.try_iter() {
+            if let Some(param_id) = context.midi_learn.lock().take() {
+                midi::learn(
+                    &mut params.midi_mappings.write(),
+                    message.control,
+                    &param_id,
+                );
+            }
+            let mappings = params.midi_mappings.read();
+            for mapping in mappings.iter().filter(|m| m.control == message.control) {
+                remote_params.set(
+                    &mapping.param_id,
+                    &RemoteValue::Normalized(message.value),
+                    &setter,
+                );
+            }
+        }
+        let changes = remote_params.changes(sync);
+        if !changes.is_empty() {
+            let messages: Vec<_> = changes.iter().flat_map(feedback_messages).collect();
+            context.osc_state.send(&messages);
+        }
+    }
+}
@@ -0,0 +1,593 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/render.rs
+
+//! Offline rendering for the `open-headstage-render` tool. Audio files run through the
+//! same parametric EQ and convolution engine as the plugin, as fast as they can, and
+//! the binaural result is written to a WAV file.
+
+use crate::autoeq_parser::{AutoEqProfile, parse_autoeq_file};
+use crate::dsp::convolution::{BLOCK_SIZE, ConvolutionEngine};
+use crate::dsp::parametric_eq::{BandChannel, BiquadDesign, EqChannelMode, StereoParametricEQ};
+use crate::sofa::loader::{MySofa, SofaError};
+use nih_plug::util;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const USAGE: &str = "\
+Usage: open-headstage-render [OPTIONS] <INPUT> <OUTPUT>
+
+Renders a WAV or FLAC file through the EQ and the speaker simulation and writes the
+binaural result to a WAV file.
+
+Options:
+  --sofa <FILE>     SOFA file to take the HRIRs from
+  --ir <FILE>       WAV file with the impulse responses instead: 4 channels in the order
+                    left speaker to left ear, left to right ear, right speaker to left
+                    ear, right to right ear, or 2 channels for the left speaker that
+                    are mirrored for the right one
+  --eq <FILE>       AutoEQ CSV or Equalizer APO profile
+  --preamp <DB>     Gain ahead of the EQ [default: the profile's, or the EQ's peak cut]
+  --left <AZ[,EL]>  Left speaker azimuth and elevation in degrees [default: -30,0]
+  --right <AZ[,EL]> Right speaker azimuth and elevation in degrees [default: 30,0]
+  --gain <DB>       Output gain [default: 0]
+  --bits <BITS>     16, 24 or 32 (floating point) bit output [default: 24]
+  -h, --help        Print this help";
+
+/// Where the impulse responses come from.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ImpulseResponses {
+    Sofa(PathBuf),
+    Wav(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitDepth {
+    Int16,
+    Int24,
+    Float32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RenderOptions {
+    pub input: PathBuf,
+    pub output: PathBuf,
+    pub impulse_responses: ImpulseResponses,
+    pub eq_profile: Option<PathBuf>,
+    /// Overrides the profile's preamp
+    pub preamp_db: Option<f32>,
+    /// Azimuth and elevation of the left and right speakers in degrees, with azimuths
+    /// positive to the right
+    pub speakers: [(f32, f32); 2],
+    pub output_gain_db: f32,
+    pub bit_depth: BitDepth,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RenderSummary {
+    pub sample_rate: u32,
+    /// Includes the tail of the impulse responses
+    pub frames: usize,
+    pub peak_db: f32,
+}
+
+#[derive(Debug)]
+pub enum RenderError {
+    Usage(String),
+    Io(io::Error),
+    Wav(hound::Error),
+    Flac(claxon::Error),
+    Sofa(SofaError),
+    EqProfile(String),
+    Unsupported(String),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RenderError::Usage(message) => write!(f, "{}", message),
+            RenderError::Io(e) => write!(f, "{}", e),
+            RenderError::Wav(e) => write!(f, "WAV error: {}", e),
+            RenderError::Flac(e) => write!(f, "FLAC error: {}", e),
+            RenderError::Sofa(e) => write!(f, "SOFA error: {:?}", e),
+            RenderError::EqProfile(e) => write!(f, "Failed to load the EQ profile: {}", e),
+            RenderError::Unsupported(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<io::Error> for RenderError {
+    fn from(err: io::Error) -> Self {
+        RenderError::Io(err)
+    }
+}
+
+impl From<hound::Error> for RenderError {
+    fn from(err: hound::Error) -> Self {
+        RenderError::Wav(err)
+    }
+}
+
+impl From<claxon::Error> for RenderError {
+    fn from(err: claxon::Error) -> Self {
+        RenderError::Flac(err)
+    }
+}
+
+impl From<SofaError> for RenderError {
+    fn from(err: SofaError) -> Self {
+        RenderError::Sofa(err)
+    }
+}
+
+impl RenderOptions {
+    /// Parses the command line, without the program name. `Ok(None)` asks for the help.
+    pub fn from_args(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Option<RenderOptions>, RenderError> {
+        let usage = |message: String| RenderError::Usage(message);
+        let mut args = args.into_iter();
+        let mut files = Vec::new();
+        let mut impulse_responses = None;
+        let mut eq_profile = None;
+        let mut preamp_db = None;
+        let mut speakers = [(-30.0, 0.0), (30.0, 0.0)];
+        let mut output_gain_db = 0.0;
+        let mut bit_depth = BitDepth::Int24;
+
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") && arg != "-h" {
+                files.push(PathBuf::from(arg));
+                continue;
+            }
+            if arg == "-h" || arg == "--help" {
+                return Ok(None);
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| usage(format!("{} needs a value", arg)))?;
+            let number = |value: &str| {
+                value
+                    .trim()
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|number| number.is_finite())
+                    .ok_or_else(|| usage(format!("Invalid value for {}: '{}'", arg, value)))
+            };
+            match arg.as_str() {
+                "--sofa" | "--ir" => {
+                    if impulse_responses.is_some() {
+                        return Err(usage("Only one of --sofa and --ir can be given".into()));
+                    }
+                    let path = PathBuf::from(value);
+                    impulse_responses = Some(if arg == "--sofa" {
+                        ImpulseResponses::Sofa(path)
+                    } else {
+                        ImpulseResponses::Wav(path)
+                    });
+                }
+                "--eq" => eq_profile = Some(PathBuf::from(value)),
+                "--preamp" => preamp_db = Some(number(&value)?),
+                "--left" | "--right" => {
+                    let (azimuth, elevation) = match value.split_once(',') {
+                        Some((azimuth, elevation)) => (number(azimuth)?, number(elevation)?),
+                        None => (number(&value)?, 0.0),
+                    };
+                    speakers[(arg == "--right") as usize] = (azimuth, elevation);
+                }
+                "--gain" => output_gain_db = number(&value)?,
+                "--bits" => {
+                    bit_depth = match value.as_str() {
+                        "16" => BitDepth::Int16,
+                        "24" => BitDepth::Int24,
+                        "32" => BitDepth::Float32,
+                        _ => return Err(usage(format!("Unsupported bit depth: {}", value))),
+                    }
+                }
+                _ => return Err(usage(format!("Unknown option: {}", arg))),
+            }
+        }
+
+        let [input, output]: [PathBuf; 2] = files
+            .try_into()
+            .map_err(|_| usage("Expected an input and an output file".into()))?;
+        let impulse_responses =
+            impulse_responses.ok_or_else(|| usage("Either --sofa or --ir is needed".into()))?;
+        if preamp_db.is_some() && eq_profile.is_none() {
+            return Err(usage("--preamp needs an EQ profile".into()));
+        }
+        Ok(Some(RenderOptions {
+            input,
+            output,
+            impulse_responses,
+            eq_profile,
+            preamp_db,
+            speakers,
+            output_gain_db,
+            bit_depth,
+        }))
+    }
+}
+
+/// Channels of audio read from a file, and their sample rate.
+struct Audio {
+    sample_rate: u32,
+    channels: Vec<Vec<f32>>,
+}
+
+fn deinterleave(samples: Vec<f32>, channel_count: usize) -> Vec<Vec<f32>> {
+    let mut channels = vec![Vec::with_capacity(samples.len() / channel_count); channel_count];
+    for frame in samples.chunks_exact(channel_count) {
+        for (channel, &sample) in channels.iter_mut().zip(frame) {
+            channel.push(sample);
+        }
+    }
+    channels
+}
+
+fn read_wav(path: &Path) -> Result<Audio, RenderError> {
+    let reader = hound::WavReader::open(path)?;
+    let spec = reader.spec();
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.into_samples::<f32>().collect::<Result<_, _>>()?,
+        hound::SampleFormat::Int => {
+            let scale = 1.0 / (1u64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .into_samples::<i32>()
+                .map(|sample| sample.map(|sample| sample as f32 * scale))
+                .collect::<Result<_, _>>()?
+        }
+    };
+    Ok(Audio {
+        sample_rate: spec.sample_rate,
+        channels: deinterleave(samples, spec.channels as usize),
+    })
+}
+
+fn read_flac(path: &Path) -> Result<Audio, RenderError> {
+    let mut reader = claxon::FlacReader::open(path)?;
+    let info = reader.streaminfo();
+    let scale = 1.0 / (1u64 << (info.bits_per_sample - 1)) as f32;
+    let samples = reader
+        .samples()
+        .map(|sample| sample.map(|sample| sample as f32 * scale))
+        .collect::<Result<_, _>>()?;
+    Ok(Audio {
+        sample_rate: info.sample_rate,
+        channels: deinterleave(samples, info.channels as usize),
+    })
+}
+
+/// Reads a WAV or FLAC file, going by its extension.
+fn read_audio(path: &Path) -> Result<Audio, RenderError> {
+    let is_flac = path
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("flac"));
+    if is_flac {
+        read_flac(path)
+    } else {
+        read_wav(path)
+    }
+}
+
+fn write_wav(
+    path: &Path,
+    sample_rate: u32,
+    bit_depth: BitDepth,
+    [left, right]: &[Vec<f32>; 2],
+) -> Result<(), RenderError> {
+    let (bits_per_sample, sample_format) = match bit_depth {
+        BitDepth::Int16 => (16, hound::SampleFormat::Int),
+        BitDepth::Int24 => (24, hound::SampleFormat::Int),
+        BitDepth::Float32 => (32, hound::SampleFormat::Float),
+    };
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate,
+        bits_per_sample,
+        sample_format,
+    };
+    let mut writer = hound::WavWriter::create(path, spec)?;
+    let full_scale = ((1u64 << (bits_per_sample - 1)) - 1) as f32;
+    for (&l, &r) in left.iter().zip(right) {
+        for sample in [l, r] {
+            match sample_format {
+                hound::SampleFormat::Float => writer.write_sample(sample)?,
+                hound::SampleFormat::Int => {
+                    writer.write_sample((sample.clamp(-1.0, 1.0) * full_scale).round() as i32)?
+                }
+            }
+        }
+    }
+    writer.finalize()?;
+    Ok(())
+}
+
+/// Impulse responses from a WAV file, in `ConvolutionPath` order. A stereo file holds
+/// the left speaker's, and the right speaker is its mirror image.
+fn wav_irs(path: &Path, sample_rate: u32) -> Result<[Vec<f32>; 4], RenderError> {
+    let audio = read_wav(path)?;
+    if audio.sample_rate != sample_rate {
+        return Err(RenderError::Unsupported(format!(
+            "The impulse responses are at {} Hz but the input is at {} Hz",
+            audio.sample_rate, sample_rate
+        )));
+    }
+    match <[Vec<f32>; 4]>::try_from(audio.channels) {
+        Ok(irs) => Ok(irs),
+        Err(channels) => match <[Vec<f32>; 2]>::try_from(channels) {
+            Ok([same_side, other_side]) => {
+                Ok([same_side.clone(), other_side.clone(), other_side, same_side])
+            }
+            Err(channels) => Err(RenderError::Unsupported(format!(
+                "Impulse response files need 2 or 4 channels, not {}",
+                channels.len()
+            ))),
+        },
+    }
+}
+
+fn load_irs(
+    source: &ImpulseResponses,
+    sample_rate: u32,
+    speakers: [(f32, f32); 2],
+) -> Result<[Vec<f32>; 4], RenderError> {
+    match source {
+        ImpulseResponses::Sofa(path) => {
+            // libmysofa resamples the HRIRs to the input's rate
+            let sofa = MySofa::open(&path.to_string_lossy(), sample_rate as f32)?;
+            Ok(crate::speaker_irs(&sofa, speakers)?)
+        }
+        ImpulseResponses::Wav(path) => wav_irs(path, sample_rate),
+    }
+}
+
+/// The plugin's EQ set up for a profile, the way applying it in the editor does.
+fn profile_eq(profile: &AutoEqProfile, sample_rate: f32) -> StereoParametricEQ {
+    let channel_mode = if profile
+        .bands
+        .iter()
+        .any(|band| band.channel != BandChannel::Both)
+    {
+        EqChannelMode::LeftRight
+    } else {
+        EqChannelMode::Linked
+    };
+    let bands: Vec<_> = profile
+        .bands
+        .iter()
+        .map(|band| band.to_band_config())
+        .collect();
+    StereoParametricEQ::with_bands(sample_rate, channel_mode, BiquadDesign::default(), &bands)
+}
+
+/// The EQ followed by the convolution, without the plugin's smoothing since nothing
+/// changes during a render.
+pub struct RenderChain {
+    eq: Option<StereoParametricEQ>,
+    preamp_gain: f32,
+    convolution_engine: ConvolutionEngine,
+    tail_len: usize,
+    output_gain: f32,
+}
+
+impl RenderChain {
+    /// Takes the impulse responses in `ConvolutionPath` order.
+    pub fn new(irs: &[Vec<f32>; 4], output_gain_db: f32) -> Self {
+        let mut convolution_engine = ConvolutionEngine::new();
+        convolution_engine.set_irs(irs.each_ref().map(Vec::as_slice));
+        Self {
+            eq: None,
+            preamp_gain: 1.0,
+            convolution_engine,
+            tail_len: irs
+                .iter()
+                .map(Vec::len)
+                .max()
+                .unwrap_or(0)
+                .saturating_sub(1),
+            output_gain: util::db_to_gain(output_gain_db),
+        }
+    }
+
+    /// Puts an EQ in front of the convolution. Without a preamp, the EQ's peak gain is
+    /// cut like the plugin's automatic preamp does.
+    pub fn set_eq(&mut self, mut eq: StereoParametricEQ, sample_rate: f32, preamp_db: Option<f32>) {
+        let preamp_db = preamp_db.unwrap_or_else(|| -eq.peak_gain_db(sample_rate).max(0.0));
+        self.preamp_gain = util::db_to_gain(preamp_db);
+        self.eq = Some(eq);
+    }
+
+    /// Renders a whole signal, followed by the impulse responses' tail.
+    pub fn process(&mut self, left: &[f32], right: &[f32]) -> [Vec<f32>; 2] {
+        let frames = left.len() + self.tail_len;
+        let mut output = [Vec::with_capacity(frames), Vec::with_capacity(frames)];
+        // The convolution engine passes whole blocks through without delay
+        let mut block_l = [0.0; BLOCK_SIZE];
+        let mut block_r = [0.0; BLOCK_SIZE];
+        let mut out_l = [0.0; BLOCK_SIZE];
+        let mut out_r = [0.0; BLOCK_SIZE];
+        for start in (0..frames).step_by(BLOCK_SIZE) {
+            for (i, (sample_l, sample_r)) in block_l.iter_mut().zip(&mut block_r).enumerate() {
+                let index = start + i;
+                *sample_l = left.get(index).map_or(0.0, |s| s * self.preamp_gain);
+                *sample_r = right.get(index).map_or(0.0, |s| s * self.preamp_gain);
+            }
+            if let Some(eq) = &mut self.eq {
+                eq.process_block(&mut block_l, &mut block_r);
+            }
+            self.convolution_engine
+                .process_block(&block_l, &block_r, &mut out_l, &mut out_r);
+
+            let len = BLOCK_SIZE.min(frames - start);
+            output[0].extend(out_l[..len].iter().map(|s| s * self.output_gain));
+            output[1].extend(out_r[..len].iter().map(|s| s * self.output_gain));
+        }
+        output
+    }
+}
+
+/// Renders the input file to the output file.
+pub fn render(options: &RenderOptions) -> Result<RenderSummary, RenderError> {
+    let audio = read_audio(&options.input)?;
+    let sample_rate = audio.sample_rate;
+    let [left, right] = match <[Vec<f32>; 1]>::try_from(audio.channels) {
+        Ok([mono]) => [mono.clone(), mono],
+        Err(channels) => <[Vec<f32>; 2]>::try_from(channels).map_err(|channels| {
+            RenderError::Unsupported(format!(
+                "Only mono and stereo input is supported, not {} channels",
+                channels.len()
+            ))
+        })?,
+    };
+
+    let irs = load_irs(&options.impulse_responses, sample_rate, options.speakers)?;
+    let mut chain = RenderChain::new(&irs, options.output_gain_db);
+    if let Some(path) = &options.eq_profile {
+        let profile = parse_autoeq_file(path).map_err(|e| RenderError::EqProfile(e.to_string()))?;
+        let eq = profile_eq(&profile, sample_rate as f32);
+        chain.set_eq(
+            eq,
+            sample_rate as f32,
+            options.preamp_db.or(profile.preamp_db),
+        );
+    }
+
+    let output = chain.process(&left, &right);
+    write_wav(&options.output, sample_rate, options.bit_depth, &output)?;
+
+    let peak = output
+        .iter()
+        .flatten()
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    Ok(RenderSummary {
+        sample_rate,
+        frames: output[0].len(),
+        peak_db: util::gain_to_db(peak),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = RenderOptions::from_args(args(
+            "in.flac out.wav --sofa kemar.sofa --eq eq.txt --left -45,10 --right 45 --bits 32",
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(options.input, Path::new("in.flac"));
+        assert_eq!(options.output, Path::new("out.wav"));
+        assert_eq!(
+            options.impulse_responses,
+            ImpulseResponses::Sofa("kemar.sofa".into())
+        );
+        assert_eq!(options.eq_profile, Some("eq.txt".into()));
+        assert_eq!(options.speakers, [(-45.0, 10.0), (45.0, 0.0)]);
+        assert_eq!(options.bit_depth, BitDepth::Float32);
+        assert_eq!(options.preamp_db, None);
+
+        assert!(RenderOptions::from_args(args("--help")).unwrap().is_none());
+        for line in [
+            "in.wav out.wav",
+            "in.wav --sofa a.sofa",
+            "in.wav out.wav --sofa a.sofa --ir b.wav",
+            "in.wav out.wav --sofa a.sofa --gain loud",
+            "in.wav out.wav --sofa a.sofa --bits 8",
+            "in.wav out.wav --sofa",
+            "in.wav out.wav --sofa a.sofa --preamp -3",
+        ] {
+            assert!(
+                matches!(
+                    RenderOptions::from_args(args(line)),
+                    Err(RenderError::Usage(_))
+                ),
+                "Accepted '{}'",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_chain_renders_without_delay_and_keeps_the_tail() {
+        // The left speaker reaches the right ear a sample later, the right speaker only
+        // reaches the right ear
+        let irs = [vec![1.0], vec![0.0, 0.5], vec![0.0], vec![1.0]];
+        let mut chain = RenderChain::new(&irs, -6.0);
+        let gain = util::db_to_gain(-6.0);
+
+        let frames = BLOCK_SIZE + 100;
+        let mut left = vec![0.0; frames];
+        let mut right = vec![0.0; frames];
+        left[frames - 1] = 1.0;
+        right[10] = 1.0;
+        let [out_l, out_r] = chain.process(&left, &right);
+
+        assert_eq!(out_l.len(), frames + 1);
+        assert_eq!(out_r.len(), frames + 1);
+        let expected_l: Vec<f32> = (0..=frames)
+            .map(|i| if i == frames - 1 { gain } else { 0.0 })
+            .collect();
+        let expected_r: Vec<f32> = (0..=frames)
+            .map(|i| match i {
+                10 => gain,
+                _ if i == frames => 0.5 * gain,
+                _ => 0.0,
+            })
+            .collect();
+        for (actual, expected) in [(&out_l, &expected_l), (&out_r, &expected_r)] {
+            for (i, (a, e)) in actual.iter().zip(expected).enumerate() {
+                assert!((a - e).abs() < 1e-5, "Sample {}: {} != {}", i, a, e);
+            }
+        }
+    }
+
+    #[test]
+    fn test_stereo_irs_are_mirrored() {
+        let dir =
+            std::env::temp_dir().join(format!("open-headstage-render-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("irs.wav");
+        write_wav(
+            &path,
+            48000,
+            BitDepth::Float32,
+            &[vec![1.0, 0.5], vec![0.25, 0.0]],
+        )
+        .unwrap();
+
+        assert_eq!(
+            wav_irs(&path, 48000).unwrap(),
+            [
+                vec![1.0, 0.5],
+                vec![0.25, 0.0],
+                vec![0.25, 0.0],
+                vec![1.0, 0.5]
+            ]
+        );
+        assert!(matches!(
+            wav_irs(&path, 44100),
+            Err(RenderError::Unsupported(_))
+        ));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
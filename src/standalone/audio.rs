@@ -0,0 +1,579 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/standalone/audio.rs
+
+//! The standalone app's audio host. It runs the plugin on cpal's devices through the same
+//! `setup` and `process_stereo` that nih-plug's wrappers drive, and opens the streams
+//! again, setting the plugin up for the new sample rate, when another device is picked or
+//! the one in use goes away.
+
+use super::devices;
+use crate::dsp::resampler::DriftResampler;
+use crate::{OpenHeadstageParams, OpenHeadstagePlugin, ProcessHost, StandaloneConfig, Task};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use crossbeam_channel::Sender;
+use nih_plug::nih_log;
+use nih_plug::prelude::Plugin;
+use parking_lot::Mutex;
+use ringbuf::{HeapProducer, HeapRb};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How much input can wait for the output, in frames
+const INPUT_BUFFER_FRAMES: usize = 16384;
+/// The buffer size to plan the latency for when the host picks it, in frames
+const DEFAULT_BUFFER_SIZE: u32 = 1024;
+/// The longest block the plugin is set up for. Longer device buffers are processed in
+/// several blocks.
+const MAX_BLOCK_SIZE: u32 = 8192;
+/// Rates to try when neither device's default rate suits the other
+const COMMON_SAMPLE_RATES: [u32; 5] = [48000, 44100, 96000, 88200, 192000];
+/// How many background tasks can wait for the worker
+const TASK_QUEUE_SIZE: usize = 64;
+/// How often the devices are checked
+pub(super) const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the editor app looks for another device pick
+const SELECTION_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The stream settings given on the command line.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) struct StreamOptions {
+    pub buffer_size: Option<u32>,
+    pub sample_rate: Option<u32>,
+}
+
+/// The host and devices to run on, by name. An empty input device is the default one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DeviceSelection {
+    host: String,
+    output: String,
+    input: String,
+}
+
+impl DeviceSelection {
+    pub fn from_config(config: &StandaloneConfig) -> Self {
+        Self {
+            host: config.audio_host.clone(),
+            output: config.audio_device.clone(),
+            input: config.audio_input_device.clone(),
+        }
+    }
+
+    /// The devices picked in the editor.
+    pub fn from_params(params: &OpenHeadstageParams) -> Self {
+        Self {
+            host: params.audio_host.read().clone(),
+            output: params.audio_device.read().clone(),
+            input: params.audio_input_device.read().clone(),
+        }
+    }
+}
+
+/// The cpal host called `name`, ignoring case.
+pub(super) fn host_id(name: &str) -> Option<cpal::HostId> {
+    cpal::available_hosts()
+        .into_iter()
+        .find(|id| id.name().eq_ignore_ascii_case(name))
+}
+
+/// The host called `name`, or the default one if there is none.
+fn find_host(name: &str) -> Result<cpal::Host, String> {
+    match host_id(name) {
+        Some(id) => cpal::host_from_id(id).map_err(|e| e.to_string()),
+        None => Ok(cpal::default_host()),
+    }
+}
+
+/// Hands the plugin's background tasks to the host's worker thread. Like nih-plug's
+/// wrappers, a full queue drops the task.
+#[derive(Clone)]
+struct TaskQueue(Sender<Task>);
+
+impl ProcessHost for TaskQueue {
+    fn execute_background(&mut self, task: Task) {
+        let _ = self.0.try_send(task);
+    }
+
+    // There is no plugin host to compensate for the latency
+    fn set_latency_samples(&mut self, _samples: u32) {}
+}
+
+/// Open streams and what they were opened with.
+struct Streams {
+    _input_stream: cpal::Stream,
+    _output_stream: cpal::Stream,
+    output_device: String,
+    /// Whether both streams use the selected devices rather than the fallbacks
+    preferred_devices: bool,
+    /// Set by the stream callbacks when a device goes away
+    device_lost: Arc<AtomicBool>,
+}
+
+/// Runs a plugin on cpal's devices. cpal's streams can't move between threads, so the
+/// host stays on the thread that made it.
+pub(crate) struct AudioHost {
+    plugin: Arc<Mutex<OpenHeadstagePlugin>>,
+    tasks: TaskQueue,
+    options: StreamOptions,
+    devices: DeviceSelection,
+    streams: Option<Streams>,
+    /// The sample rate and block size the plugin is set up for
+    setup: Option<(f32, usize)>,
+}
+
+impl AudioHost {
+    /// Takes over a plugin whose parameters are set up, and starts the thread that runs
+    /// its background tasks. The streams are opened by `start`.
+    pub fn new(
+        mut plugin: OpenHeadstagePlugin,
+        options: StreamOptions,
+        devices: DeviceSelection,
+    ) -> std::io::Result<Self> {
+        let (sender, receiver) = crossbeam_channel::bounded(TASK_QUEUE_SIZE);
+        let executor = plugin.task_executor();
+        // Stops once the host and its streams let go of the queue
+        thread::Builder::new()
+            .name("audio-host-tasks".to_string())
+            .spawn(move || {
+                for task in receiver {
+                    executor(task);
+                }
+            })?;
+        Ok(Self {
+            plugin: Arc::new(Mutex::new(plugin)),
+            tasks: TaskQueue(sender),
+            options,
+            devices,
+            streams: None,
+            setup: None,
+        })
+    }
+
+    /// The devices the host runs on, or tries to.
+    pub fn devices(&self) -> &DeviceSelection {
+        &self.devices
+    }
+
+    /// The output device the streams are open on.
+    pub fn output_device(&self) -> Option<&str> {
+        self.streams
+            .as_ref()
+            .map(|streams| streams.output_device.as_str())
+    }
+
+    /// Replaces the plugin's parameters and sets it up again, for settings that were
+    /// saved while it runs.
+    pub fn set_params(&mut self, params: Arc<OpenHeadstageParams>) {
+        params.reset_smoothers();
+        let mut plugin = self.plugin.lock();
+        plugin.set_params(params);
+        if let Some((sample_rate, block_size)) = self.setup {
+            plugin.setup(sample_rate, block_size, &mut self.tasks);
+            plugin.reset();
+        }
+    }
+
+    /// Opens the streams for `devices` in place of the ones that are open, and sets the
+    /// plugin up for them. A device that is gone falls back to the default one, unless
+    /// `strict` asks for an error. Returns what is being processed.
+    pub fn start(&mut self, devices: DeviceSelection, strict: bool) -> Result<String, String> {
+        self.devices = devices;
+        // The old streams have to let go of the devices first
+        self.streams = None;
+        let (streams, description) = self.open(strict)?;
+        self.streams = Some(streams);
+        Ok(description)
+    }
+
+    /// Opens the streams again when a device went away, when they couldn't be opened,
+    /// and when the selected devices are back after falling back to the defaults.
+    /// Returns the outcome if it did.
+    pub fn poll(&mut self) -> Option<Result<String, String>> {
+        let restart = match &self.streams {
+            Some(streams) if streams.device_lost.load(Ordering::Relaxed) => true,
+            Some(streams) if !streams.preferred_devices => {
+                find_host(&self.devices.host).is_ok_and(|host| {
+                    devices::find_output_device(&host, &self.devices.output).is_some()
+                        && devices::find_input_device(&host, &self.devices.input).is_some()
+                })
+            }
+            Some(_) => false,
+            None => true,
+        };
+        restart.then(|| self.start(self.devices.clone(), false))
+    }
+
+    fn open(&mut self, strict: bool) -> Result<(Streams, String), String> {
+        let host = find_host(&self.devices.host)?;
+        let (output_name, input_name) = (&self.devices.output, &self.devices.input);
+        let (output_device, preferred_output) =
+            match devices::find_output_device(&host, output_name) {
+                Some(device) => (device, true),
+                None if strict => {
+                    return Err(format!("There is no output device named '{}'", output_name));
+                }
+                None => (
+                    host.default_output_device()
+                        .ok_or("There is no output device")?,
+                    output_name.is_empty(),
+                ),
+            };
+        let (input_device, preferred_input) = match devices::find_input_device(&host, input_name) {
+            Some(device) => (device, true),
+            None if strict => {
+                return Err(format!("There is no input device named '{}'", input_name));
+            }
+            None => (
+                host.default_input_device()
+                    .ok_or("There is no input device")?,
+                false,
+            ),
+        };
+        let output_device_name = output_device.name().unwrap_or_default();
+        let input_device_name = input_device.name().unwrap_or_default();
+
+        let output_config = output_device
+            .default_output_config()
+            .map_err(|e| e.to_string())?;
+        let input_config = input_device
+            .default_input_config()
+            .map_err(|e| e.to_string())?;
+        let ranges =
+            |configs: Result<Vec<cpal::SupportedStreamConfigRange>, _>| -> Vec<(u32, u32)> {
+                configs
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|range| (range.min_sample_rate().0, range.max_sample_rate().0))
+                    .collect()
+            };
+        let sample_rate = negotiate_sample_rate(
+            self.options.sample_rate,
+            (
+                input_config.sample_rate().0,
+                &ranges(
+                    input_device
+                        .supported_input_configs()
+                        .map(Iterator::collect),
+                ),
+            ),
+            (
+                output_config.sample_rate().0,
+                &ranges(
+                    output_device
+                        .supported_output_configs()
+                        .map(Iterator::collect),
+                ),
+            ),
+        )
+        .ok_or_else(|| {
+            let devices = format!("'{}' and '{}'", input_device_name, output_device_name);
+            match self.options.sample_rate {
+                Some(rate) => format!("{} don't both support {} Hz", devices, rate),
+                None => format!(
+                    "{} have no sample rate in common. Set both to the same rate, or pick \
+                     another input device.",
+                    devices
+                ),
+            }
+        })?;
+        let stream_config = |channels, sample_rate| cpal::StreamConfig {
+            channels,
+            sample_rate: cpal::SampleRate(sample_rate),
+            buffer_size: self
+                .options
+                .buffer_size
+                .map_or(cpal::BufferSize::Default, cpal::BufferSize::Fixed),
+        };
+
+        // The plugin's buffers are sized for the longest buffer the device hands out
+        let block_size = match (self.options.buffer_size, output_config.buffer_size()) {
+            (Some(buffer_size), _) => buffer_size,
+            (None, cpal::SupportedBufferSize::Range { max, .. }) => *max,
+            (None, cpal::SupportedBufferSize::Unknown) => MAX_BLOCK_SIZE,
+        }
+        .clamp(1, MAX_BLOCK_SIZE) as usize;
+        {
+            let mut plugin = self.plugin.lock();
+            plugin.setup(sample_rate as f32, block_size, &mut self.tasks);
+            plugin.reset();
+        }
+        self.setup = Some((sample_rate as f32, block_size));
+
+        // The input and output clocks drift apart even at the same rate. Keeping two buffers
+        // in between leaves room for either side's callbacks to run late.
+        let target_fill = 2 * self.options.buffer_size.unwrap_or(DEFAULT_BUFFER_SIZE) as usize;
+        let mut resampler =
+            DriftResampler::new(sample_rate as f32, sample_rate as f32, target_fill);
+
+        let device_lost = Arc::new(AtomicBool::new(false));
+        let error_callback = |stream: &'static str| {
+            let device_lost = device_lost.clone();
+            move |e: cpal::StreamError| {
+                eprintln!("{} stream error: {}", stream, e);
+                if matches!(e, cpal::StreamError::DeviceNotAvailable) {
+                    device_lost.store(true, Ordering::Relaxed);
+                }
+            }
+        };
+
+        // The input is handed to the output as stereo frames
+        let (mut producer, mut consumer) = HeapRb::<[f32; 2]>::new(INPUT_BUFFER_FRAMES).split();
+        let input_channels = input_config.channels() as usize;
+        let input_stream = input_device
+            .build_input_stream(
+                &stream_config(input_config.channels(), sample_rate),
+                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                    push_frames(&mut producer, data, input_channels);
+                },
+                error_callback("Input"),
+                None,
+            )
+            .map_err(|e| format!("Failed to open the input device: {}", e))?;
+
+        let output_channels = output_config.channels() as usize;
+        let plugin = self.plugin.clone();
+        let mut tasks = self.tasks.clone();
+        let mut block_l = vec![0.0; block_size];
+        let mut block_r = vec![0.0; block_size];
+        let output_stream = output_device
+            .build_output_stream(
+                &stream_config(output_config.channels(), sample_rate),
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    for chunk in data.chunks_mut(block_size * output_channels) {
+                        let num_frames = chunk.len() / output_channels;
+                        let left = &mut block_l[..num_frames];
+                        let right = &mut block_r[..num_frames];
+                        let fill = consumer.len();
+                        resampler.process(fill, || consumer.pop(), left, right);
+
+                        match plugin.try_lock() {
+                            Some(mut plugin) => plugin.process_stereo(left, right, &mut tasks),
+                            // Only while the plugin is being set up again
+                            None => {
+                                left.fill(0.0);
+                                right.fill(0.0);
+                            }
+                        }
+
+                        for ((frame, &l), &r) in chunk
+                            .chunks_exact_mut(output_channels)
+                            .zip(left.iter())
+                            .zip(right.iter())
+                        {
+                            frame.fill(0.0);
+                            frame[0] = l;
+                            if let Some(sample) = frame.get_mut(1) {
+                                *sample = r;
+                            }
+                        }
+                    }
+                },
+                error_callback("Output"),
+                None,
+            )
+            .map_err(|e| format!("Failed to open the output device: {}", e))?;
+
+        input_stream.play().map_err(|e| e.to_string())?;
+        output_stream.play().map_err(|e| e.to_string())?;
+
+        let description = format!(
+            "'{}' into '{}' at {} Hz",
+            input_device_name, output_device_name, sample_rate
+        );
+        let streams = Streams {
+            _input_stream: input_stream,
+            _output_stream: output_stream,
+            output_device: output_device_name,
+            preferred_devices: preferred_output && preferred_input,
+            device_lost,
+        };
+        Ok((streams, description))
+    }
+}
+
+/// Runs the editor app's audio on the devices picked in the editor. nih-plug's wrapper
+/// only hosts the editor there, and a thread of its own opens the streams again when
+/// another device is picked. The audio stops when the monitor is dropped.
+pub(crate) struct DeviceMonitor {
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl DeviceMonitor {
+    pub fn spawn(
+        plugin: OpenHeadstagePlugin,
+        params: Arc<OpenHeadstageParams>,
+        options: StreamOptions,
+    ) -> std::io::Result<Self> {
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+        let thread = thread::Builder::new()
+            .name("audio-devices".to_string())
+            .spawn(move || {
+                // The host has to live on this thread, along with its streams
+                let devices = DeviceSelection::from_params(&params);
+                let mut host = match AudioHost::new(plugin, options, devices.clone()) {
+                    Ok(host) => host,
+                    Err(e) => {
+                        nih_log!("Failed to start the audio host: {}", e);
+                        return;
+                    }
+                };
+                log_start(host.start(devices, false));
+
+                let polls_per_check =
+                    POLL_INTERVAL.as_millis() / SELECTION_POLL_INTERVAL.as_millis();
+                let mut polls = 0;
+                while !thread_stop.load(Ordering::Relaxed) {
+                    thread::sleep(SELECTION_POLL_INTERVAL);
+                    polls += 1;
+                    let devices = DeviceSelection::from_params(&params);
+                    if devices != *host.devices() {
+                        log_start(host.start(devices, false));
+                    } else if polls % polls_per_check == 0 {
+                        if let Some(result) = host.poll() {
+                            log_start(result);
+                        }
+                    }
+                }
+            })?;
+        Ok(Self {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for DeviceMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn log_start(result: Result<String, String>) {
+    match result {
+        Ok(description) => nih_log!("Processing {}", description),
+        Err(message) => nih_log!("Failed to open the audio devices: {}", message),
+    }
+}
+
+/// Picks the rate to run both devices at, given each device's default rate and
+/// supported ranges, or the requested rate if both support it. The resampler between
+/// them only follows the drift between their clocks, it doesn't convert between rates,
+/// so there is no rate for devices without one in common.
+fn negotiate_sample_rate(
+    requested: Option<u32>,
+    input: (u32, &[(u32, u32)]),
+    output: (u32, &[(u32, u32)]),
+) -> Option<u32> {
+    let supports = |(default, ranges): (u32, &[(u32, u32)]), rate: u32| {
+        // Devices that don't list their ranges are only trusted with their default
+        if ranges.is_empty() {
+            rate == default
+        } else {
+            ranges.iter().any(|&(min, max)| (min..=max).contains(&rate))
+        }
+    };
+    let candidates: Vec<u32> = match requested {
+        Some(rate) => vec![rate],
+        None => [output.0, input.0]
+            .into_iter()
+            .chain(COMMON_SAMPLE_RATES)
+            .collect(),
+    };
+    candidates
+        .into_iter()
+        .find(|&rate| supports(input, rate) && supports(output, rate))
+}
+
+/// Hands an interleaved input buffer to the output as stereo frames, the first channel
+/// on both sides for mono input. Frames the output can't keep up with are dropped.
+fn push_frames(producer: &mut HeapProducer<[f32; 2]>, data: &[f32], channels: usize) {
+    for frame in data.chunks_exact(channels) {
+        let left = frame[0];
+        let right = frame.get(1).copied().unwrap_or(left);
+        if producer.push([left, right]).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_negotiate_sample_rate() {
+        let usb_dac = [(44100, 44100), (48000, 48000), (96000, 96000)];
+        let interface = [(8000, 192000)];
+        let monitor = [(44100, 44100)];
+
+        // The output's rate if the input has it too, then the input's
+        assert_eq!(
+            negotiate_sample_rate(None, (44100, &interface), (96000, &usb_dac)),
+            Some(96000)
+        );
+        assert_eq!(
+            negotiate_sample_rate(None, (44100, &monitor), (48000, &usb_dac)),
+            Some(44100)
+        );
+        // None when they have no rate in common
+        assert_eq!(
+            negotiate_sample_rate(None, (44100, &monitor), (48000, &[(48000, 48000)])),
+            None
+        );
+        // A requested rate has to suit both
+        assert_eq!(
+            negotiate_sample_rate(Some(96000), (48000, &interface), (48000, &usb_dac)),
+            Some(96000)
+        );
+        assert_eq!(
+            negotiate_sample_rate(Some(96000), (44100, &monitor), (48000, &usb_dac)),
+            None
+        );
+        // Only the default rate of a device that lists no ranges
+        assert_eq!(
+            negotiate_sample_rate(None, (48000, &[]), (44100, &interface)),
+            Some(48000)
+        );
+    }
+
+    #[test]
+    fn test_push_frames_keeps_channels_paired() {
+        let (mut producer, mut consumer) = HeapRb::<[f32; 2]>::new(4).split();
+        // One frame in leaves an odd number of free frames
+        push_frames(&mut producer, &[0.0, 0.5, 9.0], 3);
+        assert_eq!(producer.free_len(), 3);
+
+        // More than fits, from a three-channel device
+        let data: Vec<f32> = (1..=6).flat_map(|i| [i as f32, -(i as f32), 9.0]).collect();
+        push_frames(&mut producer, &data, 3);
+        let frames: Vec<[f32; 2]> = std::iter::from_fn(|| consumer.pop()).collect();
+        assert_eq!(
+            frames,
+            vec![[0.0, 0.5], [1.0, -1.0], [2.0, -2.0], [3.0, -3.0]]
+        );
+
+        // Mono input goes to both sides
+        push_frames(&mut producer, &[0.25, 0.75], 1);
+        assert_eq!(consumer.pop(), Some([0.25, 0.25]));
+        assert_eq!(consumer.pop(), Some([0.75, 0.75]));
+    }
+}
@@ -0,0 +1,67 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/standalone/devices.rs
+
+//! Finds cpal devices by the names the settings store. ALSA, PulseAudio and PipeWire
+//! expose monitor and loopback sources as ordinary capture devices. WASAPI records what an
+//! output device plays when an input stream is opened on it, so its output devices are
+//! listed as inputs too, with a suffix.
+
+use cpal::traits::{DeviceTrait, HostTrait};
+
+/// Marks an output device recorded as an input
+pub(crate) const LOOPBACK_SUFFIX: &str = " (loopback)";
+
+fn records_outputs(host: &cpal::Host) -> bool {
+    host.id().name() == "WASAPI"
+}
+
+/// The names of the devices that can be picked as inputs, loopback ones last.
+pub(crate) fn input_device_names(host_id: cpal::HostId) -> Vec<String> {
+    let Ok(host) = cpal::host_from_id(host_id) else {
+        return Vec::new();
+    };
+    let mut names: Vec<String> = host
+        .input_devices()
+        .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+        .unwrap_or_default();
+    if records_outputs(&host) {
+        if let Ok(devices) = host.output_devices() {
+            names.extend(devices.filter_map(|d| d.name().ok().map(|name| name + LOOPBACK_SUFFIX)));
+        }
+    }
+    names
+}
+
+/// The input device called `name`, or the default one for an empty name.
+pub(crate) fn find_input_device(host: &cpal::Host, name: &str) -> Option<cpal::Device> {
+    if name.is_empty() {
+        return host.default_input_device();
+    }
+    match name.strip_suffix(LOOPBACK_SUFFIX) {
+        Some(output_name) if records_outputs(host) => find_output_device(host, output_name),
+        _ => host
+            .input_devices()
+            .ok()?
+            .find(|device| device.name().is_ok_and(|device_name| device_name == name)),
+    }
+}
+
+/// The output device called `name`.
+pub(crate) fn find_output_device(host: &cpal::Host, name: &str) -> Option<cpal::Device> {
+    host.output_devices()
+        .ok()?
+        .find(|device| device.name().is_ok_and(|device_name| device_name == name))
+}
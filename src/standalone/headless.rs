@@ -0,0 +1,111 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/standalone/headless.rs
+
+//! Processing without the editor, for scripts and services. nih-plug's standalone
+//! wrapper always opens the editor, so the plugin runs on the app's own audio host with
+//! parameters made from the settings, and is set up again when the settings file is
+//! saved. Remote control needs the editor to apply its commands and is not available.
+
+use super::LaunchOptions;
+use super::audio::{AudioHost, DeviceSelection, POLL_INTERVAL};
+use crate::{OpenHeadstageParams, OpenHeadstagePlugin, StandaloneConfig};
+use std::fs;
+use std::sync::Arc;
+use std::time::SystemTime;
+
+/// When the settings file was last saved.
+fn settings_modified() -> Option<SystemTime> {
+    fs::metadata(crate::get_config_path()?)
+        .ok()?
+        .modified()
+        .ok()
+}
+
+/// The parameters for the settings, with the profile of the output device applied when
+/// it is a fallback for the configured one.
+fn params_for(config: &StandaloneConfig, output_device: Option<&str>) -> Arc<OpenHeadstageParams> {
+    let mut config = config.clone();
+    if let Some(device) = output_device.filter(|device| *device != config.audio_device) {
+        config.apply_device_profile(device);
+    }
+    Arc::new(OpenHeadstageParams::new(config))
+}
+
+/// Processes the configured input device into the configured output device until the
+/// process is terminated. Saving the settings sets the plugin up again, and opens the
+/// streams again when another host or device was picked. A lost device falls back to
+/// the default one until it comes back.
+pub(super) fn run(config: &StandaloneConfig, options: &LaunchOptions) -> Result<(), String> {
+    if config.sofa_file_path.is_empty() {
+        return Err("Headless mode needs a SOFA file, pass one with --sofa".to_string());
+    }
+
+    let mut config = config.clone();
+    let params = params_for(&config, None);
+    params.reset_smoothers();
+    let mut plugin = OpenHeadstagePlugin::new(44100.0, params);
+    plugin.start_head_tracker();
+    let devices = DeviceSelection::from_config(&config);
+    let mut host = AudioHost::new(plugin, options.stream_options(), devices.clone())
+        .map_err(|e| format!("Failed to start the audio host: {}", e))?;
+    // A device asked for on the command line has to be there at launch
+    let description = host.start(devices, options.device.is_some())?;
+    println!("Processing {}. Stop with Ctrl+C.", description);
+    // The output device whose profile the parameters are made with
+    let mut params_device = config.audio_device.clone();
+    let mut modified = settings_modified();
+
+    loop {
+        if let Some(device) = host
+            .output_device()
+            .filter(|device| *device != params_device)
+        {
+            params_device = device.to_string();
+            host.set_params(params_for(&config, Some(&params_device)));
+        }
+        std::thread::sleep(POLL_INTERVAL);
+
+        let saved = settings_modified();
+        if saved != modified {
+            modified = saved;
+            // The command line still overrides the saved settings
+            let mut saved_config = crate::load_standalone_config();
+            match options.apply(&mut saved_config) {
+                Ok(()) => {
+                    println!("The settings changed, setting up again.");
+                    config = saved_config;
+                    host.set_params(params_for(&config, Some(&params_device)));
+                    let devices = DeviceSelection::from_config(&config);
+                    if devices != *host.devices() {
+                        report(host.start(devices, false));
+                    }
+                    continue;
+                }
+                Err(message) => eprintln!("Ignoring the saved settings: {}", message),
+            }
+        }
+        if let Some(result) = host.poll() {
+            report(result);
+        }
+    }
+}
+
+fn report(result: Result<String, String>) {
+    match result {
+        Ok(description) => println!("Processing {}.", description),
+        Err(message) => eprintln!("{} Retrying.", message),
+    }
+}
@@ -0,0 +1,335 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+// src/standalone/mod.rs
+
+//! The standalone app's command line. The options that change settings are applied to
+//! the standalone config before the plugin is created, and the stream options are kept
+//! for the app's audio host. nih-plug's standalone wrapper runs with its dummy backend
+//! and only hosts the editor.
+
+pub(crate) mod audio;
+pub(crate) mod devices;
+mod headless;
+
+use crate::autoeq_parser::parse_autoeq_file;
+use crate::presets::load_preset;
+use crate::{OpenHeadstagePlugin, StandaloneConfig};
+use nih_plug::prelude::nih_export_standalone_with_args;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::sync::OnceLock;
+
+pub const USAGE: &str = "\
+Usage: open-headstage [OPTIONS] [-- <WRAPPER OPTIONS>...]
+
+Options:
+  --config <FILE>       Settings file to load and save instead of the default one
+  --preset <NAME>       Preset to load on top of the settings
+  --sofa <FILE>         SOFA file to load
+  --eq <FILE>           AutoEQ CSV or Equalizer APO profile to apply
+  --left <AZ[,EL]>      Left speaker azimuth and elevation in degrees
+  --right <AZ[,EL]>     Right speaker azimuth and elevation in degrees
+  --host <NAME>         Audio host, such as ALSA, JACK, CoreAudio or WASAPI
+  --device <NAME>       Output device
+  --input-device <NAME> Input device, such as a monitor or loopback source
+  --buffer-size <N>     Buffer size in samples
+  --sample-rate <HZ>    Sample rate
+  --headless            Process audio without opening the editor or remote control
+  -h, --help            Print this help
+
+Options after `--` are passed to nih-plug's standalone wrapper as they are, which only
+hosts the editor. Settings changed by the options are saved along with the rest by
+\"Save Settings\".";
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LaunchOptions {
+    pub config: Option<PathBuf>,
+    pub preset: Option<String>,
+    pub sofa: Option<PathBuf>,
+    pub eq_profile: Option<PathBuf>,
+    /// Azimuth and elevation of the left and right speakers in degrees
+    pub speakers: [Option<(f32, f32)>; 2],
+    pub host: Option<String>,
+    pub device: Option<String>,
+    pub input_device: Option<String>,
+    pub buffer_size: Option<u32>,
+    pub sample_rate: Option<u32>,
+    pub headless: bool,
+    pub wrapper_args: Vec<String>,
+}
+
+static CONFIG_PATH: OnceLock<PathBuf> = OnceLock::new();
+static STARTUP_CONFIG: OnceLock<StandaloneConfig> = OnceLock::new();
+static STREAM_OPTIONS: OnceLock<audio::StreamOptions> = OnceLock::new();
+
+/// The settings file given with `--config`.
+pub(crate) fn config_path() -> Option<&'static Path> {
+    CONFIG_PATH.get().map(PathBuf::as_path)
+}
+
+/// The settings the plugin starts with when the app was started from the command line.
+pub(crate) fn startup_config() -> Option<&'static StandaloneConfig> {
+    STARTUP_CONFIG.get()
+}
+
+/// The buffer size and sample rate given on the command line, for the app's audio host.
+pub(crate) fn stream_options() -> audio::StreamOptions {
+    STREAM_OPTIONS.get().copied().unwrap_or_default()
+}
+
+impl LaunchOptions {
+    /// Parses the command line, without the program name. `Ok(None)` asks for the help.
+    pub fn from_args(
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Option<LaunchOptions>, String> {
+        let mut args = args.into_iter();
+        let mut options = LaunchOptions::default();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-h" | "--help" => return Ok(None),
+                "--headless" => {
+                    options.headless = true;
+                    continue;
+                }
+                "--" => {
+                    options.wrapper_args.extend(args.by_ref());
+                    continue;
+                }
+                _ if !arg.starts_with("--") => return Err(format!("Unexpected argument: {}", arg)),
+                _ => {}
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} needs a value", arg))?;
+            let invalid = || format!("Invalid value for {}: '{}'", arg, value);
+            let angle = |value: &str| {
+                value
+                    .trim()
+                    .parse::<f32>()
+                    .ok()
+                    .filter(|angle| angle.is_finite())
+                    .ok_or_else(invalid)
+            };
+            match arg.as_str() {
+                "--config" => options.config = Some(PathBuf::from(value)),
+                "--preset" => options.preset = Some(value),
+                "--sofa" => options.sofa = Some(PathBuf::from(value)),
+                "--eq" => options.eq_profile = Some(PathBuf::from(value)),
+                "--left" | "--right" => {
+                    let angles = match value.split_once(',') {
+                        Some((azimuth, elevation)) => (angle(azimuth)?, angle(elevation)?),
+                        None => (angle(&value)?, 0.0),
+                    };
+                    options.speakers[(arg == "--right") as usize] = Some(angles);
+                }
+                "--host" => options.host = Some(value),
+                "--device" => options.device = Some(value),
+                "--input-device" => options.input_device = Some(value),
+                "--buffer-size" => {
+                    options.buffer_size = Some(
+                        value
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|&size| size > 0)
+                            .ok_or_else(invalid)?,
+                    )
+                }
+                "--sample-rate" => {
+                    options.sample_rate = Some(
+                        value
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|&rate| rate > 0)
+                            .ok_or_else(invalid)?,
+                    )
+                }
+                _ => return Err(format!("Unknown option: {}", arg)),
+            }
+        }
+        Ok(Some(options))
+    }
+
+    /// Applies the options that change settings to the settings loaded from disk, on top
+    /// of the profile for the output device.
+    fn apply(&self, config: &mut StandaloneConfig) -> Result<(), String> {
+        if let Some(host) = &self.host {
+            config.audio_host = host.clone();
+        }
+        if let Some(device) = &self.device {
+            config.audio_device = device.clone();
+        }
+        if let Some(device) = &self.input_device {
+            config.audio_input_device = device.clone();
+        }
+        let device = config.audio_device.clone();
+        config.apply_device_profile(&device);
+
+        if let Some(name) = &self.preset {
+            let dir = crate::get_presets_dir().ok_or("There is no presets folder")?;
+            let preset = load_preset(&dir, name).map_err(|e| e.to_string())?;
+            config.apply_preset(&preset);
+        }
+        if let Some(path) = &self.eq_profile {
+            let profile = parse_autoeq_file(path)
+                .map_err(|e| format!("Failed to load '{}': {}", path.display(), e))?;
+            config.apply_auto_eq_profile(&profile);
+        }
+        if let Some(path) = &self.sofa {
+            // Saved settings keep working from any directory
+            let path = path
+                .canonicalize()
+                .map_err(|e| format!("Failed to open '{}': {}", path.display(), e))?;
+            config.sofa_file_path = path.to_string_lossy().to_string();
+        }
+        if let Some((azimuth, elevation)) = self.speakers[0] {
+            config.speaker_azimuth_left = azimuth;
+            config.speaker_elevation_left = elevation;
+        }
+        if let Some((azimuth, elevation)) = self.speakers[1] {
+            config.speaker_azimuth_right = azimuth;
+            config.speaker_elevation_right = elevation;
+        }
+        Ok(())
+    }
+
+    /// The stream settings for the app's own audio host.
+    fn stream_options(&self) -> audio::StreamOptions {
+        audio::StreamOptions {
+            buffer_size: self.buffer_size,
+            sample_rate: self.sample_rate,
+        }
+    }
+
+    /// Checks that the audio host is one cpal offers on this system.
+    fn check_host(&self) -> Result<(), String> {
+        match &self.host {
+            Some(host) if audio::host_id(host).is_none() => Err(format!(
+                "Unknown audio host '{}', expected one of {}",
+                host,
+                cpal::available_hosts()
+                    .iter()
+                    .map(|id| id.name())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The arguments for nih-plug's standalone wrapper, after the program name. The app
+    /// runs the audio itself, on devices it can switch while running, so the wrapper
+    /// doesn't open any.
+    fn wrapper_args(&self) -> Vec<String> {
+        let mut args = vec!["--backend".to_string(), "dummy".to_string()];
+        args.extend(self.wrapper_args.iter().cloned());
+        args
+    }
+}
+
+/// Runs the standalone app with the process's command line.
+pub fn run(mut args: impl Iterator<Item = String>) -> ExitCode {
+    let program = args.next().unwrap_or_else(|| "open-headstage".to_string());
+    let options = match LaunchOptions::from_args(args) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    if let Err(message) = options.check_host() {
+        eprintln!("{}", message);
+        return ExitCode::from(2);
+    }
+    if let Some(path) = &options.config {
+        let _ = CONFIG_PATH.set(path.clone());
+    }
+    let mut config = crate::load_standalone_config();
+    if let Err(message) = options.apply(&mut config) {
+        eprintln!("{}", message);
+        return ExitCode::FAILURE;
+    }
+
+    if options.headless {
+        return match headless::run(&config, &options) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(message) => {
+                eprintln!("{}", message);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let _ = STARTUP_CONFIG.set(config);
+    let _ = STREAM_OPTIONS.set(options.stream_options());
+    let args = std::iter::once(program).chain(options.wrapper_args());
+    if nih_export_standalone_with_args::<OpenHeadstagePlugin, _>(args) {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let options = LaunchOptions::from_args(args(
+            "--preset Studio --left -45,10 --right 45 --host coreaudio --input-device Loopback \
+             --buffer-size 256 --headless -- --dpi-scale 2",
+        ))
+        .unwrap()
+        .unwrap();
+        assert_eq!(options.preset.as_deref(), Some("Studio"));
+        assert_eq!(options.speakers, [Some((-45.0, 10.0)), Some((45.0, 0.0))]);
+        assert_eq!(options.buffer_size, Some(256));
+        assert!(options.headless);
+        assert_eq!(options.input_device.as_deref(), Some("Loopback"));
+        assert_eq!(
+            options.wrapper_args(),
+            args("--backend dummy --dpi-scale 2")
+        );
+
+        assert!(LaunchOptions::from_args(args("--help")).unwrap().is_none());
+        for line in [
+            "--sofa",
+            "--frobnicate 1",
+            "--buffer-size 0",
+            "--sample-rate fast",
+            "--left left",
+        ] {
+            assert!(
+                LaunchOptions::from_args(args(line)).is_err(),
+                "Accepted '{}'",
+                line
+            );
+        }
+        let options = LaunchOptions::from_args(args("--host pulse"))
+            .unwrap()
+            .unwrap();
+        assert!(options.check_host().is_err());
+    }
+}
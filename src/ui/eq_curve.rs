@@ -0,0 +1,324 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use nih_plug_egui::egui::{
+    Align2, Color32, FontId, Pos2, Rect, Response, Sense, Shape, Stroke, Ui, Vec2, Widget,
+};
+use strum_macros::EnumIter;
+
+use crate::autoeq_parser::BandSetting;
+use crate::dsp::parametric_eq::{BandChannel, EqChannelMode, EqResponse};
+
+const MIN_FREQ: f32 = 20.0;
+const MAX_FREQ: f32 = 20000.0;
+const GRID_FREQUENCIES: [f32; 9] = [
+    50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0, 20000.0,
+];
+const CHANNEL_COLORS: [Color32; 2] = [
+    Color32::from_rgb(90, 170, 255),
+    Color32::from_rgb(255, 140, 90),
+];
+const OVERLAY_COLOR: Color32 = Color32::from_gray(150);
+
+// The nodes keep to the ranges of the band parameters
+const MAX_GAIN_DB: f32 = 16.0;
+const MIN_Q: f32 = 0.1;
+const MAX_Q: f32 = 10.0;
+const NODE_RADIUS: f32 = 7.0;
+// Q changes by a factor of e for every 200 points of scrolling
+const Q_SCROLL_RATE: f32 = 1.0 / 200.0;
+
+/// The property of the EQ response the curve shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, Default)]
+pub enum EqCurveView {
+    #[default]
+    Magnitude,
+    Phase,
+    GroupDelay,
+}
+
+impl EqCurveView {
+    pub fn name(self) -> &'static str {
+        match self {
+            EqCurveView::Magnitude => "Magnitude",
+            EqCurveView::Phase => "Phase",
+            EqCurveView::GroupDelay => "Group Delay",
+        }
+    }
+
+    fn unit(self) -> &'static str {
+        match self {
+            EqCurveView::Magnitude => "dB",
+            EqCurveView::Phase => "°",
+            EqCurveView::GroupDelay => "ms",
+        }
+    }
+
+    /// The curves of the first `num_channels` channels.
+    fn curves(self, response: &EqResponse, num_channels: usize) -> Vec<&Vec<f32>> {
+        match self {
+            EqCurveView::Magnitude => &response.magnitude_db,
+            EqCurveView::Phase => &response.phase_deg,
+            EqCurveView::GroupDelay => &response.group_delay_ms,
+        }
+        .iter()
+        .take(num_channels)
+        .collect()
+    }
+}
+
+/// Plots an `EqResponse` over a logarithmic frequency axis, with one curve per channel
+/// unless the channels are linked. The magnitude view also shows each band's own
+/// curve and a node per enabled band: dragging a node moves the band's frequency and
+/// gain, scrolling over it changes its Q and double-clicking it resets it.
+pub struct EqCurve<'a> {
+    pub response: Option<&'a EqResponse>,
+    // Drawn in grey for comparison, e.g. a loaded AutoEQ profile
+    pub overlay: Option<&'a EqResponse>,
+    // The bands the nodes edit, in the same order as the response's bands
+    pub bands: &'a mut [BandSetting],
+    pub view: EqCurveView,
+    pub channel_mode: EqChannelMode,
+    pub size: Vec2,
+}
+
+impl EqCurve<'_> {
+    /// The value range and grid spacing of the vertical axis.
+    fn value_range(&self, curves: &[&Vec<f32>]) -> (f32, f32, f32) {
+        match self.view {
+            EqCurveView::Magnitude => (-18.0, 18.0, 6.0),
+            EqCurveView::Phase => (-180.0, 180.0, 90.0),
+            EqCurveView::GroupDelay => {
+                let max_delay = curves
+                    .iter()
+                    .flat_map(|curve| curve.iter())
+                    .fold(1.0f32, |max, &delay| max.max(delay));
+                // Round up to a multiple of four grid steps of 1, 2 or 5 times a power of ten
+                let raw_step = max_delay / 4.0;
+                let magnitude = 10f32.powf(raw_step.log10().floor());
+                let step = [1.0, 2.0, 5.0, 10.0]
+                    .into_iter()
+                    .map(|factor| factor * magnitude)
+                    .find(|&step| step >= raw_step)
+                    .unwrap_or(raw_step);
+                (0.0, 4.0 * step, step)
+            }
+        }
+    }
+}
+
+impl Widget for EqCurve<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let (mut response, painter) = ui.allocate_painter(self.size, Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 4.0, Color32::from_gray(30));
+
+        let num_channels = if self.channel_mode == EqChannelMode::Linked {
+            1
+        } else {
+            2
+        };
+        let curves = self
+            .response
+            .map(|eq_response| self.view.curves(eq_response, num_channels))
+            .unwrap_or_default();
+        let overlay_curves = self
+            .overlay
+            .map(|overlay| self.view.curves(overlay, num_channels))
+            .unwrap_or_default();
+        let all_curves: Vec<&Vec<f32>> = curves.iter().chain(&overlay_curves).copied().collect();
+        let (min_value, max_value, value_step) = self.value_range(&all_curves);
+
+        let log_range = (MAX_FREQ / MIN_FREQ).ln();
+        let x_for = |freq: f32| {
+            let position = (freq / MIN_FREQ).ln() / log_range;
+            rect.left() + rect.width() * position.clamp(0.0, 1.0)
+        };
+        let freq_for = |x: f32| MIN_FREQ * (log_range * (x - rect.left()) / rect.width()).exp();
+        let y_for = |value: f32| {
+            let position = (value - min_value) / (max_value - min_value);
+            rect.bottom() - rect.height() * position.clamp(0.0, 1.0)
+        };
+        let value_for =
+            |y: f32| min_value + (max_value - min_value) * (rect.bottom() - y) / rect.height();
+
+        let grid_stroke = Stroke::new(1.0, Color32::from_gray(60));
+        let label_color = Color32::from_gray(140);
+        let font = FontId::proportional(11.0);
+        for freq in GRID_FREQUENCIES {
+            let x = x_for(freq);
+            painter.line_segment(
+                [Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+                grid_stroke,
+            );
+            let label = if freq >= 1000.0 {
+                format!("{}k", freq / 1000.0)
+            } else {
+                format!("{}", freq)
+            };
+            painter.text(
+                Pos2::new(x - 2.0, rect.bottom() - 2.0),
+                Align2::RIGHT_BOTTOM,
+                label,
+                font.clone(),
+                label_color,
+            );
+        }
+        let num_steps = ((max_value - min_value) / value_step).round() as usize;
+        for step in 0..=num_steps {
+            let value = min_value + step as f32 * value_step;
+            let y = y_for(value);
+            painter.line_segment(
+                [Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)],
+                grid_stroke,
+            );
+            painter.text(
+                Pos2::new(rect.left() + 2.0, y),
+                Align2::LEFT_CENTER,
+                format!("{} {}", (value * 100.0).round() / 100.0, self.view.unit()),
+                font.clone(),
+                label_color,
+            );
+        }
+
+        let draw_curve = |frequencies: &[f32], values: &[f32], stroke: Stroke| {
+            let mut segment: Vec<Pos2> = Vec::new();
+            let mut previous: Option<f32> = None;
+            for (&freq, &value) in frequencies.iter().zip(values) {
+                // Don't connect the ends of a phase wrap
+                if previous.is_some_and(|previous| (value - previous).abs() > 180.0) {
+                    painter.add(Shape::line(std::mem::take(&mut segment), stroke));
+                }
+                segment.push(Pos2::new(x_for(freq), y_for(value)));
+                previous = Some(value);
+            }
+            painter.add(Shape::line(segment, stroke));
+        };
+
+        if let Some(overlay) = self.overlay {
+            for curve in &overlay_curves {
+                draw_curve(&overlay.frequencies, curve, Stroke::new(1.5, OVERLAY_COLOR));
+            }
+        }
+        if let Some(eq_response) = self.response {
+            if self.view == EqCurveView::Magnitude {
+                let band_stroke = Stroke::new(1.0, Color32::from_white_alpha(60));
+                for band_curve in &eq_response.band_magnitude_db {
+                    draw_curve(&eq_response.frequencies, band_curve, band_stroke);
+                }
+            }
+            for (channel, curve) in curves.iter().enumerate() {
+                draw_curve(
+                    &eq_response.frequencies,
+                    curve,
+                    Stroke::new(2.0, CHANNEL_COLORS[channel]),
+                );
+            }
+        }
+
+        let mut legend: Vec<(&str, Color32)> = Vec::new();
+        if num_channels > 1 {
+            legend.push((
+                BandChannel::First.label(self.channel_mode),
+                CHANNEL_COLORS[0],
+            ));
+            legend.push((
+                BandChannel::Second.label(self.channel_mode),
+                CHANNEL_COLORS[1],
+            ));
+        }
+        if self.overlay.is_some() {
+            legend.push(("Loaded profile", OVERLAY_COLOR));
+        }
+        for (row, (label, color)) in legend.into_iter().enumerate() {
+            painter.text(
+                Pos2::new(rect.right() - 6.0, rect.top() + 6.0 + 14.0 * row as f32),
+                Align2::RIGHT_TOP,
+                label,
+                font.clone(),
+                color,
+            );
+        }
+
+        if self.view != EqCurveView::Magnitude {
+            return response;
+        }
+
+        for (i, band) in self.bands.iter_mut().enumerate() {
+            if !band.enabled {
+                continue;
+            }
+            let has_gain = band.filter_type.has_gain();
+            let gain_y = |band: &BandSetting| y_for(if has_gain { band.gain } else { 0.0 });
+            let center = Pos2::new(x_for(band.frequency), gain_y(band));
+            let node = ui.interact(
+                Rect::from_center_size(center, Vec2::splat(NODE_RADIUS * 2.5)),
+                response.id.with(i),
+                Sense::click_and_drag(),
+            );
+
+            if node.dragged() {
+                let delta = node.drag_delta();
+                band.frequency = freq_for(center.x + delta.x).clamp(MIN_FREQ, MAX_FREQ);
+                if has_gain {
+                    band.gain = value_for(center.y + delta.y).clamp(-MAX_GAIN_DB, MAX_GAIN_DB);
+                }
+                response.mark_changed();
+            }
+            if node.hovered() && band.filter_type.has_q() {
+                let scroll = ui.input(|input| input.smooth_scroll_delta.y);
+                if scroll != 0.0 {
+                    band.q = (band.q * (scroll * Q_SCROLL_RATE).exp()).clamp(MIN_Q, MAX_Q);
+                    response.mark_changed();
+                }
+            }
+            if node.double_clicked() {
+                let default = BandSetting::default();
+                band.frequency = default.frequency;
+                band.q = default.q;
+                band.gain = default.gain;
+                response.mark_changed();
+            }
+
+            let color = match band.channel {
+                BandChannel::First if num_channels > 1 => CHANNEL_COLORS[0],
+                BandChannel::Second if num_channels > 1 => CHANNEL_COLORS[1],
+                _ => Color32::from_gray(220),
+            };
+            let center = Pos2::new(x_for(band.frequency), gain_y(band));
+            let outline = if node.hovered() || node.dragged() {
+                Stroke::new(2.0, Color32::WHITE)
+            } else {
+                Stroke::new(1.0, Color32::BLACK)
+            };
+            painter.circle(center, NODE_RADIUS, color, outline);
+            painter.text(
+                center,
+                Align2::CENTER_CENTER,
+                format!("{}", i + 1),
+                FontId::proportional(9.0),
+                Color32::BLACK,
+            );
+            node.on_hover_text(format!(
+                "Band {}: {:.0} Hz, {:+.1} dB, Q {:.2}",
+                i + 1,
+                band.frequency,
+                band.gain,
+                band.q
+            ));
+        }
+
+        response
+    }
+}
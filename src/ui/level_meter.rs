@@ -0,0 +1,164 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use nih_plug::util;
+use nih_plug_egui::egui::{
+    Button, Color32, Pos2, Rect, Response, RichText, Sense, Stroke, Ui, Vec2, Widget,
+};
+
+use crate::dsp::meter::{METER_FLOOR_DB, MeterReadings};
+
+// The range of the bars
+const BAR_MIN_DB: f32 = -60.0;
+const BAR_MAX_DB: f32 = 0.0;
+const BAR_HEIGHT: f32 = 10.0;
+// How fast the peak bars fall back after a peak
+const PEAK_FALL_DB_PER_SECOND: f32 = 20.0;
+
+const RMS_COLOR: Color32 = Color32::from_rgb(60, 150, 90);
+const CLIP_COLOR: Color32 = Color32::from_rgb(230, 50, 50);
+
+fn format_db(level_db: f32) -> String {
+    if level_db <= METER_FLOOR_DB {
+        "-inf".to_string()
+    } else {
+        format!("{:.1}", level_db)
+    }
+}
+
+/// What the editor shows of a `MeterReadings`: peaks that fall back slowly and the
+/// highest true peak since the last reset.
+pub struct MeterDisplay {
+    peak_db: [f32; 2],
+    true_peak_max_db: [f32; 2],
+}
+
+impl Default for MeterDisplay {
+    fn default() -> Self {
+        Self {
+            peak_db: [METER_FLOOR_DB; 2],
+            true_peak_max_db: [METER_FLOOR_DB; 2],
+        }
+    }
+}
+
+impl MeterDisplay {
+    fn update(&mut self, readings: &MeterReadings, elapsed: f32) {
+        for channel in 0..2 {
+            let peak_db = util::gain_to_db(readings.take_peak(channel));
+            let fallen_db = self.peak_db[channel] - PEAK_FALL_DB_PER_SECOND * elapsed;
+            self.peak_db[channel] = peak_db.max(fallen_db).max(METER_FLOOR_DB);
+
+            let true_peak_db = util::gain_to_db(readings.take_true_peak(channel));
+            self.true_peak_max_db[channel] = self.true_peak_max_db[channel].max(true_peak_db);
+        }
+    }
+}
+
+/// Peak and RMS bars for both channels of a stereo signal, its true peak maximum and
+/// EBU R128 loudness, and a clip indicator that stays lit until it is clicked.
+pub struct LevelMeters<'a> {
+    pub label: &'a str,
+    pub readings: &'a MeterReadings,
+    pub display: &'a mut MeterDisplay,
+    pub width: f32,
+}
+
+impl Widget for LevelMeters<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let readings = self.readings;
+        let display = self.display;
+        display.update(readings, ui.input(|input| input.unstable_dt));
+
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                ui.label(RichText::new(self.label).strong());
+                let clip_color = if readings.clipped() {
+                    CLIP_COLOR
+                } else {
+                    Color32::from_gray(70)
+                };
+                if ui
+                    .add(Button::new(RichText::new("CLIP").color(Color32::WHITE)).fill(clip_color))
+                    .on_hover_text("Lights when the true peak exceeds 0 dBTP. Click to clear.")
+                    .clicked()
+                {
+                    readings.clear_clip();
+                }
+            });
+
+            let x_for = |rect: Rect, level_db: f32| {
+                let position = (level_db - BAR_MIN_DB) / (BAR_MAX_DB - BAR_MIN_DB);
+                rect.left() + rect.width() * position.clamp(0.0, 1.0)
+            };
+            for channel in 0..2 {
+                let (rect, _) =
+                    ui.allocate_exact_size(Vec2::new(self.width, BAR_HEIGHT), Sense::hover());
+                let painter = ui.painter();
+                painter.rect_filled(rect, 2.0, Color32::from_gray(30));
+
+                let rms_db = util::gain_to_db(readings.rms(channel));
+                let rms_rect =
+                    Rect::from_min_max(rect.min, Pos2::new(x_for(rect, rms_db), rect.bottom()));
+                painter.rect_filled(rms_rect, 2.0, RMS_COLOR);
+
+                let peak_db = display.peak_db[channel];
+                let peak_color = if peak_db >= -0.1 {
+                    CLIP_COLOR
+                } else if peak_db >= -6.0 {
+                    Color32::YELLOW
+                } else {
+                    Color32::from_rgb(140, 230, 160)
+                };
+                let peak_x = x_for(rect, peak_db);
+                painter.line_segment(
+                    [
+                        Pos2::new(peak_x, rect.top()),
+                        Pos2::new(peak_x, rect.bottom()),
+                    ],
+                    Stroke::new(2.0, peak_color),
+                );
+            }
+
+            let [peak_l, peak_r] = display.peak_db;
+            let [true_peak_l, true_peak_r] = display.true_peak_max_db;
+            ui.label(format!(
+                "Peak {} / {} dB   RMS {} / {} dB   True peak max {} / {} dBTP",
+                format_db(peak_l),
+                format_db(peak_r),
+                format_db(util::gain_to_db(readings.rms(0))),
+                format_db(util::gain_to_db(readings.rms(1))),
+                format_db(true_peak_l),
+                format_db(true_peak_r),
+            ));
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "M {}   S {}   I {} LUFS",
+                    format_db(readings.momentary_lufs()),
+                    format_db(readings.short_term_lufs()),
+                    format_db(readings.integrated_lufs()),
+                ));
+                if ui
+                    .small_button("Reset")
+                    .on_hover_text("Restart the integrated loudness and true peak maximum")
+                    .clicked()
+                {
+                    readings.reset_integrated();
+                    display.true_peak_max_db = [METER_FLOOR_DB; 2];
+                }
+            });
+        })
+        .response
+    }
+}
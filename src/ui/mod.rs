@@ -12,4 +12,7 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
+pub mod eq_curve;
+pub mod level_meter;
 pub mod speaker_visualizer;
+pub mod spectrum_view;
@@ -0,0 +1,141 @@
+// Copyright 2025 SignalVerse
+//
+// Licensed under the Apache License, Version 2.0 (the "License");
+// you may not use this file except in compliance with the License.
+// You may obtain a copy of the License at
+//
+//     http://www.apache.org/licenses/LICENSE-2.0
+//
+// Unless required by applicable law or agreed to in writing, software
+// distributed under the License is distributed on an "AS IS" BASIS,
+// WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+// See the License for the specific language governing permissions and
+// limitations under the License.
+
+use nih_plug_egui::egui::{
+    Align2, Color32, FontId, Pos2, Response, Sense, Shape, Stroke, Ui, Vec2, Widget,
+};
+use strum::IntoEnumIterator;
+
+use crate::dsp::analyzer::{AnalyzerTap, SpectrumAnalyzer};
+
+const MIN_FREQ: f32 = 20.0;
+const MAX_FREQ: f32 = 20000.0;
+const GRID_FREQUENCIES: [f32; 9] = [
+    50.0, 100.0, 200.0, 500.0, 1000.0, 2000.0, 5000.0, 10000.0, 20000.0,
+];
+const MIN_DB: f32 = -100.0;
+const MAX_DB: f32 = 0.0;
+const DB_STEP: f32 = 20.0;
+
+pub fn tap_color(tap: AnalyzerTap) -> Color32 {
+    match tap {
+        AnalyzerTap::Input => Color32::from_gray(170),
+        AnalyzerTap::PostEq => Color32::from_rgb(120, 220, 120),
+        AnalyzerTap::OutputLeft => Color32::from_rgb(90, 170, 255),
+        AnalyzerTap::OutputRight => Color32::from_rgb(255, 140, 90),
+    }
+}
+
+/// Draws the spectra of the shown analyser taps over a logarithmic frequency axis, with
+/// their held peaks as thin lines when peak hold is on.
+pub struct SpectrumView<'a> {
+    pub analyzer: &'a SpectrumAnalyzer,
+    pub sample_rate: f32,
+    pub shown: [bool; AnalyzerTap::COUNT],
+    pub size: Vec2,
+}
+
+impl Widget for SpectrumView<'_> {
+    fn ui(self, ui: &mut Ui) -> Response {
+        let (response, painter) = ui.allocate_painter(self.size, Sense::hover());
+        let rect = response.rect;
+        painter.rect_filled(rect, 4.0, Color32::from_gray(30));
+
+        let log_range = (MAX_FREQ / MIN_FREQ).ln();
+        let x_for = |freq: f32| rect.left() + rect.width() * (freq / MIN_FREQ).ln() / log_range;
+        let y_for = |level_db: f32| {
+            let position = (level_db - MIN_DB) / (MAX_DB - MIN_DB);
+            rect.bottom() - rect.height() * position.clamp(0.0, 1.0)
+        };
+
+        let grid_stroke = Stroke::new(1.0, Color32::from_gray(60));
+        let label_color = Color32::from_gray(140);
+        let font = FontId::proportional(11.0);
+        for freq in GRID_FREQUENCIES {
+            let x = x_for(freq);
+            painter.line_segment(
+                [Pos2::new(x, rect.top()), Pos2::new(x, rect.bottom())],
+                grid_stroke,
+            );
+            let label = if freq >= 1000.0 {
+                format!("{}k", freq / 1000.0)
+            } else {
+                format!("{}", freq)
+            };
+            painter.text(
+                Pos2::new(x - 2.0, rect.bottom() - 2.0),
+                Align2::RIGHT_BOTTOM,
+                label,
+                font.clone(),
+                label_color,
+            );
+        }
+        let mut level_db = MIN_DB;
+        while level_db <= MAX_DB {
+            let y = y_for(level_db);
+            painter.line_segment(
+                [Pos2::new(rect.left(), y), Pos2::new(rect.right(), y)],
+                grid_stroke,
+            );
+            painter.text(
+                Pos2::new(rect.left() + 2.0, y),
+                Align2::LEFT_CENTER,
+                format!("{} dB", level_db),
+                font.clone(),
+                label_color,
+            );
+            level_db += DB_STEP;
+        }
+
+        // Large FFTs have many bins per pixel at the top end, so each pixel column is
+        // drawn once at the loudest of its bins
+        let frequencies = self.analyzer.frequencies(self.sample_rate);
+        let draw_levels = |levels: &[f32], stroke: Stroke| {
+            let mut points: Vec<Pos2> = Vec::new();
+            for (&freq, &level_db) in frequencies.iter().zip(levels) {
+                if !(MIN_FREQ..=MAX_FREQ).contains(&freq) {
+                    continue;
+                }
+                let point = Pos2::new(x_for(freq).round(), y_for(level_db));
+                match points.last_mut() {
+                    Some(last) if last.x == point.x => last.y = last.y.min(point.y),
+                    _ => points.push(point),
+                }
+            }
+            painter.add(Shape::line(points, stroke));
+        };
+
+        let shown_taps = AnalyzerTap::iter().filter(|&tap| self.shown[tap as usize]);
+        for (legend_row, tap) in shown_taps.enumerate() {
+            let color = tap_color(tap);
+            if let Some(peaks) = self.analyzer.peaks(tap) {
+                draw_levels(peaks, Stroke::new(1.0, color.gamma_multiply(0.5)));
+            }
+            draw_levels(self.analyzer.spectrum(tap), Stroke::new(1.5, color));
+
+            painter.text(
+                Pos2::new(
+                    rect.right() - 6.0,
+                    rect.top() + 6.0 + 14.0 * legend_row as f32,
+                ),
+                Align2::RIGHT_TOP,
+                tap.name(),
+                font.clone(),
+                color,
+            );
+        }
+
+        response
+    }
+}